@@ -0,0 +1,12 @@
+-- Pending authorization requests to external OpenID Connect / OAuth 2.0 providers.
+-- The `state` handed to the provider is only stored hashed; the PKCE verifier and
+-- nonce are needed in the clear to finish the exchange.
+CREATE TABLE oauth_states (
+    id TEXT PRIMARY KEY,
+    provider TEXT NOT NULL,
+    state_hash TEXT UNIQUE NOT NULL,
+    code_verifier TEXT NOT NULL,
+    nonce TEXT NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP
+);
@@ -0,0 +1,3 @@
+-- Authorization requests started by a signed-in user to link another identity.
+-- Sign-in requests leave this NULL, so neither kind can finish as the other.
+ALTER TABLE oauth_states ADD COLUMN user_id TEXT REFERENCES users(id);
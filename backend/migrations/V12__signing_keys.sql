@@ -0,0 +1,12 @@
+-- Asymmetric keys for signing access tokens. The newest unretired key signs;
+-- every unretired key, and retired ones for a grace period, still verify.
+-- `private_key` is DER (PKCS#8 for EdDSA, PKCS#1 for RS256) and `public_jwk`
+-- the JSON Web Key published at /.well-known/jwks.json.
+CREATE TABLE signing_keys (
+    kid TEXT PRIMARY KEY,
+    algorithm TEXT NOT NULL,
+    private_key BLOB NOT NULL,
+    public_jwk TEXT NOT NULL,
+    created_at TIMESTAMP NOT NULL,
+    retired_at TIMESTAMP
+);
@@ -0,0 +1,70 @@
+-- Roles bundle permissions, and groups hand roles to all of their members.
+-- A user's effective permissions are their direct grants plus those of their
+-- roles and of the roles of every group they belong to.
+CREATE TABLE roles (
+    id TEXT PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE role_permissions (
+    role_id TEXT NOT NULL,
+    permission_id TEXT NOT NULL,
+    PRIMARY KEY (role_id, permission_id),
+    FOREIGN KEY (role_id) REFERENCES roles(id),
+    FOREIGN KEY (permission_id) REFERENCES permissions(id)
+);
+
+CREATE TABLE user_roles (
+    user_id TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    granted_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (user_id, role_id),
+    FOREIGN KEY (user_id) REFERENCES users(id),
+    FOREIGN KEY (role_id) REFERENCES roles(id)
+);
+
+-- `groups` is an SQL keyword
+CREATE TABLE user_groups (
+    id TEXT PRIMARY KEY,
+    name TEXT UNIQUE NOT NULL,
+    description TEXT,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE TABLE group_roles (
+    group_id TEXT NOT NULL,
+    role_id TEXT NOT NULL,
+    PRIMARY KEY (group_id, role_id),
+    FOREIGN KEY (group_id) REFERENCES user_groups(id),
+    FOREIGN KEY (role_id) REFERENCES roles(id)
+);
+
+CREATE TABLE group_members (
+    group_id TEXT NOT NULL,
+    user_id TEXT NOT NULL,
+    added_at TIMESTAMP NOT NULL,
+    PRIMARY KEY (group_id, user_id),
+    FOREIGN KEY (group_id) REFERENCES user_groups(id),
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE INDEX idx_user_roles_role_id ON user_roles(role_id);
+CREATE INDEX idx_group_members_user_id ON group_members(user_id);
+
+-- The seeded permissions become default roles, and existing grants of them
+-- become role assignments
+INSERT INTO roles (id, name, description, created_at) VALUES
+    ('role_admin', 'admin', 'Administrator access', strftime('%Y-%m-%dT%H:%M:%SZ', 'now')),
+    ('role_user', 'user', 'Standard user access', strftime('%Y-%m-%dT%H:%M:%SZ', 'now'));
+
+INSERT INTO role_permissions (role_id, permission_id) VALUES
+    ('role_admin', 'perm_admin'),
+    ('role_user', 'perm_user');
+
+INSERT INTO user_roles (user_id, role_id, granted_at)
+    SELECT user_id, 'role_' || substr(permission_id, 6), strftime('%Y-%m-%dT%H:%M:%SZ', 'now')
+    FROM user_permissions WHERE permission_id IN ('perm_admin', 'perm_user');
+
+DELETE FROM user_permissions WHERE permission_id IN ('perm_admin', 'perm_user');
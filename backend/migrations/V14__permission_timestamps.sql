@@ -0,0 +1,4 @@
+-- Rows seeded in V1 took SQLite's CURRENT_TIMESTAMP default ("YYYY-MM-DD HH:MM:SS");
+-- store them as RFC 3339 like every timestamp written by the application
+UPDATE permissions SET created_at = replace(created_at, ' ', 'T') || 'Z' WHERE created_at NOT LIKE '%T%';
+UPDATE user_permissions SET granted_at = replace(granted_at, ' ', 'T') || 'Z' WHERE granted_at NOT LIKE '%T%';
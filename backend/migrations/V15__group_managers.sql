@@ -0,0 +1,2 @@
+-- Managers administer the other members of their group
+ALTER TABLE group_members ADD COLUMN is_manager BOOLEAN NOT NULL DEFAULT 0;
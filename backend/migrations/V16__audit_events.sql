@@ -0,0 +1,30 @@
+-- Security-relevant events. No foreign keys: the log outlives the users it
+-- mentions, and the CLI acts without a user.
+CREATE TABLE audit_events (
+    id TEXT PRIMARY KEY,
+    source TEXT NOT NULL,
+    actor_id TEXT,
+    impersonator_id TEXT,
+    action TEXT NOT NULL,
+    target_type TEXT,
+    target_id TEXT,
+    ip_address TEXT,
+    user_agent TEXT,
+    diff TEXT,
+    created_at TIMESTAMP NOT NULL
+);
+
+CREATE INDEX idx_audit_events_created_at ON audit_events(created_at);
+CREATE INDEX idx_audit_events_actor_id ON audit_events(actor_id);
+CREATE INDEX idx_audit_events_target_id ON audit_events(target_id);
+
+-- Append-only
+CREATE TRIGGER audit_events_no_update BEFORE UPDATE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
+
+CREATE TRIGGER audit_events_no_delete BEFORE DELETE ON audit_events
+BEGIN
+    SELECT RAISE(ABORT, 'audit_events is append-only');
+END;
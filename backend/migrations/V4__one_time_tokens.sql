@@ -0,0 +1,14 @@
+-- Single-use tokens mailed to users, e.g. for password resets. Only a hash of the
+-- token is stored; `purpose` keeps tokens from being redeemed for another flow.
+CREATE TABLE one_time_tokens (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    purpose TEXT NOT NULL,
+    token_hash TEXT UNIQUE NOT NULL,
+    expires_at TIMESTAMP NOT NULL,
+    used_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE INDEX idx_one_time_tokens_user_purpose ON one_time_tokens(user_id, purpose);
@@ -0,0 +1,2 @@
+-- Set once the user proves they own their email address
+ALTER TABLE users ADD COLUMN email_verified_at TIMESTAMP;
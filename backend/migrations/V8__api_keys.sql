@@ -0,0 +1,24 @@
+-- API keys for scripts and integrations. Only a hash of the key is stored; `prefix`
+-- is the start of the key, kept so users can tell their keys apart.
+CREATE TABLE api_keys (
+    id TEXT PRIMARY KEY,
+    user_id TEXT NOT NULL,
+    name TEXT NOT NULL,
+    prefix TEXT NOT NULL,
+    key_hash TEXT UNIQUE NOT NULL,
+    expires_at TIMESTAMP,
+    last_used_at TIMESTAMP,
+    created_at TIMESTAMP DEFAULT CURRENT_TIMESTAMP,
+    FOREIGN KEY (user_id) REFERENCES users(id)
+);
+
+CREATE INDEX idx_api_keys_user_id ON api_keys(user_id);
+
+-- Permissions a key may use, a subset of its owner's
+CREATE TABLE api_key_permissions (
+    api_key_id TEXT NOT NULL,
+    permission_id TEXT NOT NULL,
+    PRIMARY KEY (api_key_id, permission_id),
+    FOREIGN KEY (api_key_id) REFERENCES api_keys(id),
+    FOREIGN KEY (permission_id) REFERENCES permissions(id)
+);
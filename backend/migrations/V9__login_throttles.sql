@@ -0,0 +1,10 @@
+-- Failed login counters, keyed by account (normalized email) or by client IP.
+-- Kept in the database so lockouts survive a restart.
+CREATE TABLE login_throttles (
+    scope TEXT NOT NULL,
+    throttle_key TEXT NOT NULL,
+    failures INTEGER NOT NULL DEFAULT 0,
+    last_failure_at TIMESTAMP NOT NULL,
+    locked_until TIMESTAMP,
+    PRIMARY KEY (scope, throttle_key)
+);
@@ -0,0 +1,125 @@
+use actix_web::{delete, get, post, web, HttpResponse, Result};
+use chrono::Duration;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::middleware::AuthUser;
+use crate::models::{ApiKey, ApiKeyResponse, AppState, ClientInfo, CreateApiKeyRequest, CreatedApiKeyResponse};
+
+#[utoipa::path(
+    post,
+    path = "/api/me/api-keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created; the key is only shown in this response", body = CreatedApiKeyResponse),
+        (status = 400, description = "Invalid name, expiry or scopes"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "API keys and impersonation tokens cannot create API keys")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/api-keys")]
+pub async fn create_api_key(
+    request: web::Json<CreateApiKeyRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Name is required"));
+    }
+    if request.expires_in_days.is_some_and(|days| days <= 0) {
+        return Ok(HttpResponse::BadRequest().json("expires_in_days must be positive"));
+    }
+    if !request.scopes.iter().all(|scope| auth.has_permission(scope)) {
+        return Ok(HttpResponse::BadRequest().json("Scopes must be a subset of your permissions"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let ttl = request.expires_in_days.map(Duration::days);
+    let created = ApiKey::create(conn, &auth.user.id, name, &request.scopes, ttl)
+        .and_then(|(api_key, key)| Ok((ApiKey::scopes(conn, &api_key.id)?, api_key, key)));
+    
+    match created {
+        Ok((scopes, api_key, key)) => {
+            let event = caller_event(&auth, &client, "auth.api_key_created").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "api_key_id": api_key.id, "name": api_key.name, "scopes": scopes })));
+            Ok(HttpResponse::Created().json(CreatedApiKeyResponse {
+                key,
+                api_key: ApiKeyResponse::new(api_key, scopes),
+            }))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/api-keys",
+    responses(
+        (status = 200, description = "API keys of the caller", body = Vec<ApiKeyResponse>),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/me/api-keys")]
+pub async fn list_my_api_keys(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let api_keys = ApiKey::find_for_user(conn, &auth.user.id).and_then(|api_keys| {
+        api_keys
+            .into_iter()
+            .map(|api_key| {
+                let scopes = ApiKey::scopes(conn, &api_key.id)?;
+                Ok(ApiKeyResponse::new(api_key, scopes))
+            })
+            .collect::<rusqlite::Result<Vec<_>>>()
+    });
+    
+    match api_keys {
+        Ok(api_keys) => Ok(HttpResponse::Ok().json(api_keys)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/api-keys/{id}",
+    responses(
+        (status = 204, description = "API key revoked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "API key not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/api-keys/{id}")]
+pub async fn revoke_my_api_key(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let api_key_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match ApiKey::delete_for_user(conn, &auth.user.id, &api_key_id) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "auth.api_key_revoked").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "api_key_id": api_key_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("API key not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::{get, web, HttpResponse, Result};
+use rusqlite::Connection;
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, AuditEvent, AuditEventPage, AuditEventQuery, ClientInfo, NewAuditEvent, ADMIN_PERMISSION, AUDIT_PAGE_SIZE,
+    MAX_AUDIT_PAGE_SIZE,
+};
+
+// An event performed by the authenticated caller
+pub(crate) fn caller_event(auth: &AuthUser, client: &ClientInfo, action: &'static str) -> NewAuditEvent {
+    NewAuditEvent {
+        impersonator_id: auth.impersonator_id().map(str::to_string),
+        ..NewAuditEvent::new(action, client).actor(&auth.user.id)
+    }
+}
+
+// The change has already been made by the time it is audited, so a failure to
+// record it is logged rather than turned into an error response
+pub(crate) fn audit(conn: &Connection, event: NewAuditEvent) {
+    let action = event.action;
+    if let Err(err) = AuditEvent::record(conn, event) {
+        log::error!("Failed to record audit event {action}: {err}");
+    }
+}
+
+// `{"field": {"from": .., "to": ..}}` for each top-level field that differs,
+// ignoring bookkeeping timestamps
+pub(crate) fn changes(before: &impl Serialize, after: &impl Serialize) -> Value {
+    let (Ok(Value::Object(before)), Ok(Value::Object(after))) = (serde_json::to_value(before), serde_json::to_value(after)) else {
+        return Value::Null;
+    };
+    
+    let mut diff = Map::new();
+    for (field, to) in after {
+        let from = before.get(&field).cloned().unwrap_or(Value::Null);
+        if field != "updated_at" && from != to {
+            diff.insert(field, json!({ "from": from, "to": to }));
+        }
+    }
+    Value::Object(diff)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/audit-events",
+    params(AuditEventQuery),
+    responses(
+        (status = 200, description = "Matching audit events, newest first", body = AuditEventPage),
+        (status = 400, description = "Invalid filter"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/audit-events")]
+pub async fn list_audit_events(
+    query: web::Query<AuditEventQuery>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let limit = query.limit.unwrap_or(AUDIT_PAGE_SIZE).clamp(1, MAX_AUDIT_PAGE_SIZE);
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let page = AuditEvent::find(conn, &query, Some(limit))
+        .and_then(|events| Ok(AuditEventPage { events, total: AuditEvent::count(conn, &query)? }));
+    match page {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
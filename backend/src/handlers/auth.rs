@@ -1,5 +1,25 @@
-use actix_web::{post, web, HttpResponse, Result};
-use crate::models::{AppState, LoginRequest, LoginResponse, GoogleAuthRequest};
+use std::sync::LazyLock;
+
+use actix_web::{http::header, post, web, HttpResponse, Result};
+use crate::middleware::AuthUser;
+use chrono::Duration;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use serde::Deserialize;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::models::{AppState, Claims, ACCESS_TOKEN_TTL_MINUTES, ClientInfo, ExternalProfile, LoginRequest, LoginResponse, GoogleAuthRequest, NewAuditEvent, OAuthIdentity, OneTimeToken, RefreshRequest, RefreshToken, Session, TokenPurpose, TwoFactor, TwoFactorChallengeResponse, User, UserResponse};
+use crate::models::{account_throttle_key, LoginThrottle, LoginThrottlePolicy, ThrottleScope, Throttled};
+
+// Idle lifetime of a session and of each refresh token issued for it
+const REFRESH_TOKEN_TTL_DAYS: i64 = 30;
+// Time allowed between the password and the second factor
+const TWO_FACTOR_CHALLENGE_TTL_MINUTES: i64 = 5;
+
+// Hash checked when the email is unknown so that a miss costs as much as a
+// wrong password and response times do not reveal which accounts exist
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    bcrypt::hash("surjo-dummy-password", bcrypt::DEFAULT_COST).expect("Failed to hash dummy password")
+});
 
 #[utoipa::path(
     post,
@@ -7,16 +27,340 @@ use crate::models::{AppState, LoginRequest, LoginResponse, GoogleAuthRequest};
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = LoginResponse),
-        (status = 401, description = "Invalid credentials")
+        (status = 202, description = "Password accepted, second factor required", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid credentials"),
+        (status = 403, description = "Account is disabled or email not verified"),
+        (status = 423, description = "Account temporarily locked after repeated failures; see Retry-After"),
+        (status = 429, description = "Too many failed attempts; see Retry-After")
     )
 )]
 #[post("/api/auth/login")]
 pub async fn login(
-    _credentials: web::Json<LoginRequest>,
-    _state: web::Data<AppState>,
+    credentials: web::Json<LoginRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let limits = &state.policy.login_throttle;
+    let account_key = account_throttle_key(&credentials.email);
+    let ip_address = client.ip_address.as_deref();
+    
+    // Look up the user, releasing the database lock before the slow bcrypt check
+    let found = {
+        let database = state.database.lock().unwrap();
+        let conn = database.get_connection();
+        
+        match throttle_response(conn, &account_key, ip_address, limits) {
+            Ok(Some(response)) => return Ok(response),
+            Ok(None) => {}
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+        
+        match User::find_by_email_with_password(conn, &credentials.email) {
+            Ok(found) => found,
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+    };
+    
+    // Always run exactly one bcrypt verification, whether or not the user exists
+    let (user, password_hash) = match found {
+        Some((user, Some(hash))) => (Some(user), hash),
+        _ => (None, DUMMY_PASSWORD_HASH.clone()),
+    };
+    let password_valid = bcrypt::verify(&credentials.password, &password_hash).unwrap_or(false);
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user = match user {
+        Some(user) if password_valid => user,
+        _ => {
+            if record_login_failure(conn, &account_key, ip_address, limits).is_err() {
+                return Ok(HttpResponse::InternalServerError().json("Database error"));
+            }
+            let mut event = NewAuditEvent::new("auth.login_failed", &client).diff(json!({ "email": credentials.email }));
+            if let Some(user) = &user {
+                event = event.target("user", &user.id);
+            }
+            audit(conn, event);
+            return Ok(HttpResponse::Unauthorized().json("Invalid credentials"));
+        }
+    };
+    
+    Ok(login_response(&state, conn, user, &client))
+}
+
+// Refuses the attempt while the client address or the account is throttled
+pub(crate) fn throttle_response(
+    conn: &rusqlite::Connection,
+    account_key: &str,
+    ip_address: Option<&str>,
+    limits: &LoginThrottlePolicy,
+) -> rusqlite::Result<Option<HttpResponse>> {
+    if let Some(response) = ip_throttle_response(conn, ip_address, limits)? {
+        return Ok(Some(response));
+    }
+    
+    Ok(match LoginThrottle::check(conn, ThrottleScope::Account, account_key, &limits.account)? {
+        Some(Throttled::Locked(wait)) => Some(retry_after(HttpResponse::Locked(), wait, "Account temporarily locked")),
+        Some(Throttled::Delayed(wait)) => {
+            Some(retry_after(HttpResponse::TooManyRequests(), wait, "Too many failed login attempts"))
+        }
+        None => None,
+    })
+}
+
+// Refuses the attempt while the client address is throttled, for checks made
+// before the account is known
+pub(crate) fn ip_throttle_response(
+    conn: &rusqlite::Connection,
+    ip_address: Option<&str>,
+    limits: &LoginThrottlePolicy,
+) -> rusqlite::Result<Option<HttpResponse>> {
+    let ip_throttled = match ip_address {
+        Some(ip_address) => LoginThrottle::check(conn, ThrottleScope::Ip, ip_address, &limits.ip)?,
+        None => None,
+    };
+    Ok(match ip_throttled {
+        Some(Throttled::Locked(wait) | Throttled::Delayed(wait)) => {
+            Some(retry_after(HttpResponse::TooManyRequests(), wait, "Too many failed login attempts"))
+        }
+        None => None,
+    })
+}
+
+// Counts a wrong password, second factor or passkey against both the account and the address
+pub(crate) fn record_login_failure(
+    conn: &rusqlite::Connection,
+    account_key: &str,
+    ip_address: Option<&str>,
+    limits: &LoginThrottlePolicy,
+) -> rusqlite::Result<()> {
+    LoginThrottle::record_failure(conn, ThrottleScope::Account, account_key, &limits.account)?;
+    match ip_address {
+        Some(ip_address) => LoginThrottle::record_failure(conn, ThrottleScope::Ip, ip_address, &limits.ip),
+        None => Ok(()),
+    }
+}
+
+// Resets the account's failures once every factor has been checked. An address
+// keeps its failures so that signing in to one account does not buy more
+// guesses at others.
+pub(crate) fn clear_account_failures(conn: &rusqlite::Connection, user: &User) -> rusqlite::Result<bool> {
+    LoginThrottle::clear(conn, ThrottleScope::Account, &account_throttle_key(&user.email))
+}
+
+fn retry_after(mut builder: actix_web::HttpResponseBuilder, wait: Duration, message: &str) -> HttpResponse {
+    // Whole seconds, rounded up so clients never retry too early
+    let seconds = (wait.num_milliseconds() + 999) / 1000;
+    builder
+        .insert_header((header::RETRY_AFTER, seconds.max(1).to_string()))
+        .json(message)
+}
+
+// Checks the account may sign in, then either opens a session or asks for the second factor
+pub(crate) fn login_response(state: &AppState, conn: &rusqlite::Connection, user: User, client: &ClientInfo) -> HttpResponse {
+    if let Some(response) = sign_in_refusal(state, &user) {
+        return response;
+    }
+    
+    // With 2FA on, the first factor only earns a challenge for `/api/auth/2fa/verify`
+    match TwoFactor::is_enabled(conn, &user.id) {
+        Ok(true) => return two_factor_challenge_response(conn, &user),
+        Ok(false) => {}
+        Err(_) => return HttpResponse::InternalServerError().json("Database error"),
+    }
+    
+    if clear_account_failures(conn, &user).is_err() {
+        return HttpResponse::InternalServerError().json("Database error");
+    }
+    start_session_response(state, conn, user, client)
+}
+
+// Returns the response for an account that may not sign in, whatever the method
+pub(crate) fn sign_in_refusal(state: &AppState, user: &User) -> Option<HttpResponse> {
+    if !user.is_active {
+        return Some(HttpResponse::Forbidden().json("Account is disabled"));
+    }
+    
+    if state.policy.require_email_verification && user.email_verified_at.is_none() {
+        return Some(HttpResponse::Forbidden().json("Email address is not verified"));
+    }
+    
+    None
+}
+
+// Opens a new session once every required factor has been checked
+pub(crate) fn start_session_response(state: &AppState, conn: &rusqlite::Connection, user: User, client: &ClientInfo) -> HttpResponse {
+    let session = match Session::delete_expired_for_user(conn, &user.id)
+        .and_then(|_| Session::create(conn, &user.id, Duration::days(REFRESH_TOKEN_TTL_DAYS), client))
+    {
+        Ok(session) => session,
+        Err(_) => return HttpResponse::InternalServerError().json("Failed to create session"),
+    };
+    let event = NewAuditEvent::new("auth.login", client).actor(&user.id).target("user", &user.id);
+    audit(conn, event.diff(json!({ "session_id": session.id })));
+    
+    session_tokens_response(state, conn, &session, user)
+}
+
+fn two_factor_challenge_response(conn: &rusqlite::Connection, user: &User) -> HttpResponse {
+    let ttl = Duration::minutes(TWO_FACTOR_CHALLENGE_TTL_MINUTES);
+    match OneTimeToken::issue(conn, &user.id, TokenPurpose::TwoFactorChallenge, ttl) {
+        Ok(challenge_token) => HttpResponse::Accepted().json(TwoFactorChallengeResponse {
+            challenge_token,
+            expires_in: ttl.num_seconds(),
+        }),
+        Err(_) => HttpResponse::InternalServerError().json("Database error"),
+    }
+}
+
+// Issues a fresh access/refresh token pair for an existing session
+fn session_tokens_response(state: &AppState, conn: &rusqlite::Connection, session: &Session, user: User) -> HttpResponse {
+    let refresh_token = match RefreshToken::issue(conn, &session.id, &user.id, Duration::days(REFRESH_TOKEN_TTL_DAYS)) {
+        Ok(refresh_token) => refresh_token,
+        Err(_) => return HttpResponse::InternalServerError().json("Failed to create refresh token"),
+    };
+    
+    let ttl = Duration::minutes(ACCESS_TOKEN_TTL_MINUTES);
+    let claims = Claims::new(&user.id, &session.id, ttl);
+    let token = match claims.encode(conn, &state.jwt_secret) {
+        Ok(token) => token,
+        Err(_) => return HttpResponse::InternalServerError().json("Failed to create token"),
+    };
+    
+    HttpResponse::Ok().json(LoginResponse {
+        token,
+        refresh_token,
+        expires_in: ttl.num_seconds(),
+        user: UserResponse::from(user),
+    })
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/refresh",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "Tokens rotated", body = LoginResponse),
+        (status = 401, description = "Invalid, expired or reused refresh token"),
+        (status = 403, description = "Account is disabled")
+    )
+)]
+#[post("/api/auth/refresh")]
+pub async fn refresh(
+    request: web::Json<RefreshRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let refresh_token = match RefreshToken::find_by_token(conn, &request.refresh_token) {
+        Ok(Some(refresh_token)) => refresh_token,
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid refresh token")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    // A token that was already rotated is being replayed, so someone else may hold
+    // the family. Revoke the whole session to lock out both parties.
+    if refresh_token.used_at.is_some() {
+        log::warn!(
+            "Refresh token reuse detected for user {}, revoking session {}",
+            refresh_token.user_id,
+            refresh_token.session_id
+        );
+        if Session::delete(conn, &refresh_token.session_id).is_err() {
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+        let event = NewAuditEvent::new("auth.refresh_token_reused", &client).target("user", &refresh_token.user_id);
+        audit(conn, event.diff(json!({ "session_id": refresh_token.session_id })));
+        return Ok(HttpResponse::Unauthorized().json("Invalid refresh token"));
+    }
+    
+    if refresh_token.expires_at <= chrono::Utc::now() {
+        return Ok(HttpResponse::Unauthorized().json("Invalid refresh token"));
+    }
+    
+    let session = match Session::find_active(conn, &refresh_token.session_id) {
+        Ok(Some(session)) => session,
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid refresh token")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    let user = match User::find_by_id(conn, &refresh_token.user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid refresh token")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    if !user.is_active {
+        return Ok(HttpResponse::Forbidden().json("Account is disabled"));
+    }
+    
+    if RefreshToken::mark_used(conn, &refresh_token.id)
+        .and_then(|_| Session::extend(conn, &session.id, Duration::days(REFRESH_TOKEN_TTL_DAYS)))
+        .is_err()
+    {
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    
+    Ok(session_tokens_response(&state, conn, &session, user))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/logout",
+    responses(
+        (status = 204, description = "Session ended"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Authenticated with an API key rather than a session")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/auth/logout")]
+pub async fn logout(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
 ) -> Result<HttpResponse> {
-    // TODO: Implement password authentication
-    Ok(HttpResponse::Unauthorized().json("Invalid credentials"))
+    let session_id = auth.require_session()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    // Ends the session, which invalidates this access token and its refresh tokens
+    match Session::delete(conn, session_id) {
+        Ok(_) => {
+            let event = caller_event(&auth, &client, "auth.logout").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "session_id": session_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+pub(crate) const GOOGLE_PROVIDER: &str = "google";
+const GOOGLE_ISSUERS: [&str; 2] = ["https://accounts.google.com", "accounts.google.com"];
+
+#[derive(Debug, Deserialize)]
+struct GoogleTokenResponse {
+    access_token: String,
+    id_token: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleIdTokenClaims {
+    sub: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct GoogleUserInfo {
+    sub: String,
+    email: String,
+    #[serde(default)]
+    email_verified: bool,
+    given_name: Option<String>,
+    family_name: Option<String>,
 }
 
 #[utoipa::path(
@@ -25,14 +369,88 @@ pub async fn login(
     request_body = GoogleAuthRequest,
     responses(
         (status = 200, description = "Google authentication successful", body = LoginResponse),
-        (status = 401, description = "Invalid Google code")
+        (status = 202, description = "Second factor required", body = TwoFactorChallengeResponse),
+        (status = 401, description = "Invalid Google code"),
+        (status = 403, description = "Account is disabled"),
+        (status = 409, description = "An account with this email exists and it or the Google email is unverified")
     )
 )]
 #[post("/api/auth/google")]
 pub async fn google_auth(
-    _auth_data: web::Json<GoogleAuthRequest>,
-    _state: web::Data<AppState>,
+    auth_data: web::Json<GoogleAuthRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
 ) -> Result<HttpResponse> {
-    // TODO: Implement Google OAuth
-    Ok(HttpResponse::Unauthorized().json("Invalid Google code"))
-}
\ No newline at end of file
+    let profile = match fetch_google_profile(&state, &auth_data.code).await {
+        Some(profile) => profile,
+        None => return Ok(HttpResponse::Unauthorized().json("Invalid Google code")),
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match OAuthIdentity::find_or_create_user(conn, GOOGLE_PROVIDER, &profile, &client) {
+        Ok(Some(user)) => Ok(login_response(&state, conn, user, &client)),
+        Ok(None) => Ok(HttpResponse::Conflict().json("An account with this email already exists")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+// Exchanges the authorization code and returns the Google profile it grants access to.
+// The ID token comes straight from the token endpoint over TLS, so per OpenID Connect
+// Core 3.1.3.7 its issuer, audience and expiry are validated but not its signature.
+pub(crate) async fn fetch_google_profile(state: &AppState, code: &str) -> Option<ExternalProfile> {
+    let config = &state.google;
+    let client = reqwest::Client::new();
+    
+    let tokens: GoogleTokenResponse = client
+        .post(&config.token_url)
+        .form(&[
+            ("code", code),
+            ("client_id", config.client_id.as_str()),
+            ("client_secret", config.client_secret.as_str()),
+            ("redirect_uri", config.redirect_uri.as_str()),
+            ("grant_type", "authorization_code"),
+        ])
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    
+    let mut validation = Validation::default();
+    validation.insecure_disable_signature_validation();
+    validation.set_issuer(&GOOGLE_ISSUERS);
+    validation.set_audience(&[&config.client_id]);
+    let id_token = decode::<GoogleIdTokenClaims>(&tokens.id_token, &DecodingKey::from_secret(&[]), &validation)
+        .ok()?
+        .claims;
+    
+    let profile: GoogleUserInfo = client
+        .get(&config.userinfo_url)
+        .bearer_auth(&tokens.access_token)
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    
+    // The userinfo response must describe the same account the ID token was issued for
+    if profile.sub != id_token.sub {
+        return None;
+    }
+    
+    Some(ExternalProfile {
+        subject: profile.sub,
+        email: profile.email,
+        email_verified: profile.email_verified,
+        first_name: profile.given_name,
+        last_name: profile.family_name,
+    })
+}
@@ -0,0 +1,422 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Result};
+use rusqlite::Connection;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event, changes};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, ClientInfo, CreateGroupRequest, Group, GroupResponse, Role, UpdateGroupRequest, User, UserResponse, ADMIN_PERMISSION,
+};
+
+fn group_response(conn: &Connection, group: Group) -> rusqlite::Result<GroupResponse> {
+    let roles = Group::roles(conn, &group.id)?;
+    Ok(GroupResponse::new(group, roles))
+}
+
+// Names the first role that does not exist, if any
+fn unknown_role(conn: &Connection, roles: &[String]) -> rusqlite::Result<Option<String>> {
+    for role in roles {
+        if Role::find_by_name(conn, role)?.is_none() {
+            return Ok(Some(role.clone()));
+        }
+    }
+    Ok(None)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups",
+    responses(
+        (status = 200, description = "All groups with their roles", body = Vec<GroupResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/groups")]
+pub async fn list_groups(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let groups = Group::find_all(conn)
+        .and_then(|groups| groups.into_iter().map(|group| group_response(conn, group)).collect::<rusqlite::Result<Vec<_>>>());
+    match groups {
+        Ok(groups) => Ok(HttpResponse::Ok().json(groups)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/groups",
+    request_body = CreateGroupRequest,
+    responses(
+        (status = 201, description = "Group created", body = GroupResponse),
+        (status = 400, description = "Missing name or unknown role"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 409, description = "A group with this name already exists")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/groups")]
+pub async fn create_group(
+    request: web::Json<CreateGroupRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Name is required"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match unknown_role(conn, &request.roles) {
+        Ok(None) => {}
+        Ok(Some(role)) => return Ok(HttpResponse::BadRequest().json(format!("Unknown role: {role}"))),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    let group = match Group::create(conn, name, request.description.as_deref()) {
+        Ok(group) => group,
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            return Ok(HttpResponse::Conflict().json("A group with this name already exists"));
+        }
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match Group::set_roles(conn, &group.id, &request.roles).and_then(|_| group_response(conn, group)) {
+        Ok(response) => {
+            let event = caller_event(&auth, &client, "group.create").target("group", &response.id);
+            audit(conn, event.diff(json!({ "name": response.name, "roles": response.roles })));
+            Ok(HttpResponse::Created().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}",
+    responses(
+        (status = 200, description = "Group found", body = GroupResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Group not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/groups/{id}")]
+pub async fn get_group(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(group)) => match group_response(conn, group) {
+            Ok(response) => Ok(HttpResponse::Ok().json(response)),
+            Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+        },
+        Ok(None) => Ok(HttpResponse::NotFound().json("Group not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/groups/{id}",
+    request_body = UpdateGroupRequest,
+    responses(
+        (status = 200, description = "Group updated", body = GroupResponse),
+        (status = 400, description = "Unknown role"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Group not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/groups/{id}")]
+pub async fn update_group(
+    path: web::Path<String>,
+    request: web::Json<UpdateGroupRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let group = match Group::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(group)) => group,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Group not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    let before = match group_response(conn, group.clone()) {
+        Ok(before) => before,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    if let Some(roles) = &request.roles {
+        match unknown_role(conn, roles) {
+            Ok(None) => {}
+            Ok(Some(role)) => return Ok(HttpResponse::BadRequest().json(format!("Unknown role: {role}"))),
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+        if Group::set_roles(conn, &group.id, roles).is_err() {
+            return Ok(HttpResponse::InternalServerError().json("Database error"));
+        }
+    }
+    
+    let updated = match &request.description {
+        Some(description) => Group::set_description(conn, &group.id, Some(description))
+            .and_then(|_| Group::find_by_id(conn, &group.id))
+            .map(|updated| updated.unwrap_or(group)),
+        None => Ok(group),
+    };
+    match updated.and_then(|group| group_response(conn, group)) {
+        Ok(response) => {
+            let event = caller_event(&auth, &client, "group.update").target("group", &response.id);
+            audit(conn, event.diff(changes(&before, &response)));
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}",
+    responses(
+        (status = 204, description = "Group deleted; its members lose the group's roles"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Group not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/groups/{id}")]
+pub async fn delete_group(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let group_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::delete(conn, &group_id) {
+        Ok(true) => {
+            audit(conn, caller_event(&auth, &client, "group.delete").target("group", &group_id));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Group not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/groups/{id}/members",
+    responses(
+        (status = 200, description = "Members of the group", body = Vec<UserResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Group not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/groups/{id}/members")]
+pub async fn list_group_members(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let group_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::find_by_id(conn, &group_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Group not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match Group::members(conn, &group_id) {
+        Ok(members) => {
+            let response: Vec<UserResponse> = members.into_iter().map(UserResponse::from).collect();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/groups/{id}/members/{user_id}",
+    responses(
+        (status = 204, description = "User added to the group"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Group or user not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/groups/{id}/members/{user_id}")]
+pub async fn add_group_member(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (group_id, user_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let found = Group::find_by_id(conn, &group_id)
+        .and_then(|group| Ok(group.is_some() && User::find_by_id(conn, &user_id)?.is_some()));
+    match found {
+        Ok(true) => {}
+        Ok(false) => return Ok(HttpResponse::NotFound().json("Group or user not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match Group::add_member(conn, &group_id, &user_id) {
+        Ok(added) => {
+            if added {
+                let event = caller_event(&auth, &client, "group.member_added").target("user", &user_id);
+                audit(conn, event.diff(json!({ "group_id": group_id })));
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}/members/{user_id}",
+    responses(
+        (status = 204, description = "User removed from the group"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "The user is not a member of this group")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/groups/{id}/members/{user_id}")]
+pub async fn remove_group_member(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (group_id, user_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::remove_member(conn, &group_id, &user_id) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "group.member_removed").target("user", &user_id);
+            audit(conn, event.diff(json!({ "group_id": group_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("The user is not a member of this group")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/groups/{id}/managers/{user_id}",
+    responses(
+        (status = 204, description = "Member may now read and update the group's other members"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "The user is not a member of this group")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/groups/{id}/managers/{user_id}")]
+pub async fn add_group_manager(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (group_id, user_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::set_manager(conn, &group_id, &user_id, true) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "group.manager_added").target("user", &user_id);
+            audit(conn, event.diff(json!({ "group_id": group_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("The user is not a member of this group")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/groups/{id}/managers/{user_id}",
+    responses(
+        (status = 204, description = "Member is no longer a manager but stays in the group"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "The user is not a member of this group")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/groups/{id}/managers/{user_id}")]
+pub async fn remove_group_manager(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (group_id, user_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Group::set_manager(conn, &group_id, &user_id, false) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "group.manager_removed").target("user", &user_id);
+            audit(conn, event.diff(json!({ "group_id": group_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("The user is not a member of this group")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
@@ -0,0 +1,189 @@
+use actix_web::{delete, get, post, web, HttpResponse, Result};
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::handlers::auth::{fetch_google_profile, GOOGLE_PROVIDER};
+use crate::handlers::oauth::{authorization_response, fetch_profile, find_provider};
+use crate::middleware::AuthUser;
+use crate::models::{AppState, ClientInfo, IdentityResponse, LinkIdentityRequest, OAuthIdentity, OAuthStartResponse, OAuthState, Passkey, User};
+
+#[utoipa::path(
+    get,
+    path = "/api/me/identities",
+    responses(
+        (status = 200, description = "External identities linked to the current user", body = Vec<IdentityResponse>),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/me/identities")]
+pub async fn list_my_identities(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    
+    match OAuthIdentity::find_for_user(database.get_connection(), &auth.user.id) {
+        Ok(identities) => {
+            let response: Vec<IdentityResponse> = identities.into_iter().map(IdentityResponse::from).collect();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/identities/{provider}/start",
+    responses(
+        (status = 200, description = "Authorization URL to send the browser to", body = OAuthStartResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 404, description = "Unknown provider"),
+        (status = 502, description = "Provider discovery failed")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/me/identities/{provider}/start")]
+pub async fn start_identity_link(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let Some(provider) = find_provider(&state, &path.into_inner()) else {
+        return Ok(HttpResponse::NotFound().json("Unknown provider"));
+    };
+    
+    Ok(authorization_response(&state, provider, Some(&auth.user.id)).await)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/identities/{provider}",
+    request_body = LinkIdentityRequest,
+    responses(
+        (status = 201, description = "Identity linked", body = IdentityResponse),
+        (status = 400, description = "Missing, invalid or expired state"),
+        (status = 401, description = "Not authenticated, or invalid authorization code"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 404, description = "Unknown provider"),
+        (status = 409, description = "The identity is already linked")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/identities/{provider}")]
+pub async fn link_identity(
+    path: web::Path<String>,
+    request: web::Json<LinkIdentityRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    let provider_name = path.into_inner();
+    
+    let profile = if let Some(provider) = find_provider(&state, &provider_name) {
+        let Some(oauth_state) = request.state.as_deref() else {
+            return Ok(HttpResponse::BadRequest().json("Missing state"));
+        };
+        
+        // Only a state this user started for linking is accepted
+        let consumed = {
+            let database = state.database.lock().unwrap();
+            OAuthState::consume(database.get_connection(), &provider.name, Some(&auth.user.id), oauth_state)
+        };
+        let pending = match consumed {
+            Ok(Some(pending)) => pending,
+            Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired state")),
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        };
+        fetch_profile(provider, &request.code, &pending).await
+    } else if provider_name == GOOGLE_PROVIDER {
+        fetch_google_profile(&state, &request.code).await
+    } else {
+        return Ok(HttpResponse::NotFound().json("Unknown provider"));
+    };
+    let Some(profile) = profile else {
+        return Ok(HttpResponse::Unauthorized().json("Invalid authorization code"));
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match OAuthIdentity::find_user_id(conn, &provider_name, &profile.subject) {
+        Ok(None) => {}
+        Ok(Some(user_id)) if user_id == auth.user.id => {
+            return Ok(HttpResponse::Conflict().json("Identity is already linked to your account"));
+        }
+        Ok(Some(_)) => return Ok(HttpResponse::Conflict().json("Identity is linked to another account")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match OAuthIdentity::create(conn, &auth.user.id, &provider_name, &profile.subject) {
+        Ok(identity) => {
+            let event = caller_event(&auth, &client, "auth.identity_linked").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "identity_id": identity.id, "provider": provider_name })));
+            Ok(HttpResponse::Created().json(IdentityResponse::from(identity)))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/identities/{id}",
+    responses(
+        (status = 204, description = "Identity unlinked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 404, description = "Identity not found"),
+        (status = 409, description = "The identity is the account's only way to sign in")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/identities/{id}")]
+pub async fn unlink_identity(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    let identity_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let identities = match OAuthIdentity::find_for_user(conn, &auth.user.id) {
+        Ok(identities) => identities,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    if !identities.iter().any(|identity| identity.id == identity_id) {
+        return Ok(HttpResponse::NotFound().json("Identity not found"));
+    }
+    
+    // Password-less accounts keep at least one other way to sign in
+    let other_methods = User::has_password(conn, &auth.user.id).and_then(|has_password| {
+        Ok(has_password || identities.len() > 1 || !Passkey::find_for_user(conn, &auth.user.id)?.is_empty())
+    });
+    match other_methods {
+        Ok(true) => {}
+        Ok(false) => {
+            return Ok(HttpResponse::Conflict().json("Set a password or add another sign-in method first"));
+        }
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match OAuthIdentity::delete_for_user(conn, &auth.user.id, &identity_id) {
+        Ok(_) => {
+            let event = caller_event(&auth, &client, "auth.identity_unlinked").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "identity_id": identity_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
@@ -0,0 +1,70 @@
+use actix_web::{post, web, HttpResponse, Result};
+use chrono::Duration;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, Claims, ClientInfo, ImpersonationResponse, Session, User, UserResponse, ADMIN_PERMISSION,
+    IMPERSONATION_TTL_MINUTES,
+};
+
+#[utoipa::path(
+    post,
+    path = "/api/admin/users/{id}/impersonate",
+    responses(
+        (status = 200, description = "Access token acting as the user", body = ImpersonationResponse),
+        (status = 400, description = "Cannot impersonate yourself"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission and a signed-in session"),
+        (status = 404, description = "User not found"),
+        (status = 409, description = "Account is disabled")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/admin/users/{id}/impersonate")]
+pub async fn impersonate_user(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    let user_id = path.into_inner();
+    
+    if user_id == auth.user.id {
+        return Ok(HttpResponse::BadRequest().json("Cannot impersonate yourself"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user = match User::find_by_id(conn, &user_id) {
+        Ok(Some(user)) if user.is_active => user,
+        Ok(Some(_)) => return Ok(HttpResponse::Conflict().json("Account is disabled")),
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    // A session of its own, so the user sees it and it can be revoked like any other
+    let ttl = Duration::minutes(IMPERSONATION_TTL_MINUTES);
+    let session = match Session::create(conn, &user.id, ttl, &client) {
+        Ok(session) => session,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Failed to create session")),
+    };
+    let token = match Claims::new(&user.id, &session.id, ttl).impersonated_by(&auth.user.id).encode(conn, &state.jwt_secret) {
+        Ok(token) => token,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Failed to create token")),
+    };
+    
+    log::warn!("Admin {} started impersonating user {} (session {})", auth.user.id, user.id, session.id);
+    let event = caller_event(&auth, &client, "auth.impersonation_started").target("user", &user.id);
+    audit(conn, event.diff(json!({ "session_id": session.id })));
+    
+    Ok(HttpResponse::Ok().json(ImpersonationResponse {
+        token,
+        expires_in: ttl.num_seconds(),
+        user: UserResponse::from(user),
+    }))
+}
@@ -0,0 +1,29 @@
+use actix_web::{get, http::header, web, HttpResponse, Result};
+use crate::models::{AppState, JwksResponse, SigningKey, JWKS_MAX_AGE_SECONDS};
+
+#[utoipa::path(
+    get,
+    path = "/.well-known/jwks.json",
+    responses(
+        (status = 200, description = "Public keys that verify access tokens", body = JwksResponse)
+    )
+)]
+#[get("/.well-known/jwks.json")]
+pub async fn jwks(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    
+    // New keys are listed before they sign, and retired ones until tokens they
+    // signed have expired
+    let keys = match SigningKey::verification_keys(database.get_connection()) {
+        Ok(keys) => keys,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    let keys = keys
+        .iter()
+        .filter_map(|key| serde_json::from_str(&key.public_jwk).ok())
+        .collect();
+    
+    Ok(HttpResponse::Ok()
+        .insert_header((header::CACHE_CONTROL, format!("public, max-age={JWKS_MAX_AGE_SECONDS}")))
+        .json(JwksResponse { keys }))
+}
@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+
+use actix_web::{post, web, HttpResponse, Result};
+use chrono::Duration;
+use serde_json::json;
+use crate::handlers::audit::audit;
+use crate::handlers::auth::login_response;
+use crate::mailer::MAGIC_LINK;
+use crate::models::{
+    AppState, ClientInfo, LoginResponse, MagicLinkRequest, MagicLinkVerifyRequest, NewAuditEvent, OneTimeToken,
+    TokenPurpose, TwoFactorChallengeResponse, User,
+};
+
+const MAGIC_LINK_TTL_MINUTES: i64 = 15;
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/magic-link",
+    request_body = MagicLinkRequest,
+    responses(
+        (status = 202, description = "A sign-in link was sent if the address can sign in"),
+        (status = 400, description = "Invalid email address")
+    )
+)]
+#[post("/api/auth/magic-link")]
+pub async fn request_magic_link(
+    request: web::Json<MagicLinkRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    // The response is the same whether or not the email is registered
+    let accepted = HttpResponse::Accepted().json("If the address can sign in, a link has been sent");
+    
+    let email = request.email.trim();
+    if !email.contains('@') {
+        return Ok(HttpResponse::BadRequest().json("Invalid email address"));
+    }
+    
+    let token = {
+        let database = state.database.lock().unwrap();
+        let conn = database.get_connection();
+        
+        // Unknown addresses get a password-less account, which stays unverified
+        // until the link is followed
+        let user = match User::find_by_email(conn, email) {
+            Ok(Some(user)) if user.is_active => user,
+            Ok(Some(_)) => return Ok(accepted),
+            Ok(None) => match User::create(conn, email, None, None, None) {
+                Ok(user) => {
+                    let event = NewAuditEvent::new("user.create", &client).target("user", &user.id);
+                    audit(conn, event.diff(json!({ "email": user.email, "via": "magic_link" })));
+                    user
+                }
+                Err(_) => return Ok(HttpResponse::InternalServerError().json("Failed to create user")),
+            },
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        };
+        
+        match OneTimeToken::issue(conn, &user.id, TokenPurpose::MagicLink, Duration::minutes(MAGIC_LINK_TTL_MINUTES)) {
+            Ok(token) => token,
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+    };
+    
+    let message = MAGIC_LINK.render(
+        email,
+        &HashMap::from([
+            ("link", format!("{}/magic-link?token={token}", state.app_url)),
+            ("ttl_minutes", MAGIC_LINK_TTL_MINUTES.to_string()),
+        ]),
+    );
+    if let Err(e) = state.mailer.send(&message) {
+        log::error!("Failed to send magic link email: {e}");
+    }
+    
+    Ok(accepted)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/magic-link/verify",
+    request_body = MagicLinkVerifyRequest,
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 202, description = "Second factor required", body = TwoFactorChallengeResponse),
+        (status = 400, description = "Invalid, expired or already used link"),
+        (status = 403, description = "Account is disabled")
+    )
+)]
+#[post("/api/auth/magic-link/verify")]
+pub async fn verify_magic_link(
+    request: web::Json<MagicLinkVerifyRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user_id = match OneTimeToken::consume(conn, &request.token, TokenPurpose::MagicLink) {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired link")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    // Following the emailed link proves the user owns the address. If nobody had
+    // before, whoever registered it may have been squatting on it.
+    let user = match User::claim_unverified(conn, &user_id) {
+        Ok(claimed) => {
+            if claimed {
+                audit(conn, NewAuditEvent::new("auth.account_claimed", &client).actor(&user_id).target("user", &user_id));
+            }
+            match User::find_by_id(conn, &user_id) {
+                Ok(Some(user)) => user,
+                Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired link")),
+                Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+            }
+        }
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    Ok(login_response(&state, conn, user, &client))
+}
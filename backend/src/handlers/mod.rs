@@ -1,7 +1,37 @@
 pub mod hello;
 pub mod users;
+pub mod roles;
+pub mod groups;
+pub mod permissions;
 pub mod auth;
+pub mod sessions;
+pub mod password;
+pub mod verification;
+pub mod two_factor;
+pub mod passkeys;
+pub mod api_keys;
+pub mod oauth;
+pub mod identities;
+pub mod magic_link;
+pub mod jwks;
+pub mod impersonation;
+pub mod audit;
 
 pub use hello::*;
 pub use users::*;
-pub use auth::*;
\ No newline at end of file
+pub use roles::*;
+pub use groups::*;
+pub use permissions::*;
+pub use auth::*;
+pub use sessions::*;
+pub use password::*;
+pub use verification::*;
+pub use two_factor::*;
+pub use passkeys::*;
+pub use api_keys::*;
+pub use oauth::*;
+pub use identities::*;
+pub use magic_link::*;
+pub use jwks::*;
+pub use impersonation::*;
+pub use audit::*;
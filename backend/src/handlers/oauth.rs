@@ -0,0 +1,300 @@
+use actix_web::{get, post, web, HttpResponse, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Duration;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use reqwest::header;
+use serde::Deserialize;
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use crate::handlers::auth::login_response;
+use crate::models::{
+    AppState, ClientInfo, ExternalProfile, LoginResponse, OAuthCallbackRequest, OAuthIdentity, OAuthProviderConfig,
+    OAuthStartResponse, OAuthState, PendingAuthorization, TwoFactorChallengeResponse,
+};
+
+const OAUTH_STATE_TTL_MINUTES: i64 = 10;
+
+// The parts of an OpenID Connect discovery document we use
+#[derive(Debug, Deserialize)]
+struct DiscoveryDocument {
+    issuer: String,
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: Option<String>,
+}
+
+#[derive(Debug)]
+struct ProviderEndpoints {
+    authorization_url: String,
+    token_url: String,
+    userinfo_url: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    id_token: Option<String>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/auth/oauth/{provider}/start",
+    responses(
+        (status = 200, description = "Authorization URL to send the browser to", body = OAuthStartResponse),
+        (status = 404, description = "Unknown provider"),
+        (status = 502, description = "Provider discovery failed")
+    )
+)]
+#[get("/api/auth/oauth/{provider}/start")]
+pub async fn start_oauth(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+) -> Result<HttpResponse> {
+    let Some(provider) = find_provider(&state, &path.into_inner()) else {
+        return Ok(HttpResponse::NotFound().json("Unknown provider"));
+    };
+    
+    Ok(authorization_response(&state, provider, None).await)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/oauth/{provider}/callback",
+    request_body = OAuthCallbackRequest,
+    responses(
+        (status = 200, description = "Authentication successful", body = LoginResponse),
+        (status = 202, description = "Second factor required", body = TwoFactorChallengeResponse),
+        (status = 400, description = "Invalid or expired state"),
+        (status = 401, description = "Invalid authorization code"),
+        (status = 403, description = "Account is disabled"),
+        (status = 404, description = "Unknown provider"),
+        (status = 409, description = "An account with this email exists and it or the provider email is unverified")
+    )
+)]
+#[post("/api/auth/oauth/{provider}/callback")]
+pub async fn oauth_callback(
+    path: web::Path<String>,
+    request: web::Json<OAuthCallbackRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let Some(provider) = find_provider(&state, &path.into_inner()) else {
+        return Ok(HttpResponse::NotFound().json("Unknown provider"));
+    };
+    
+    // The state is single use and bound to the provider it was issued for
+    let consumed = {
+        let database = state.database.lock().unwrap();
+        OAuthState::consume(database.get_connection(), &provider.name, None, &request.state)
+    };
+    let pending = match consumed {
+        Ok(Some(pending)) => pending,
+        Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired state")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    let Some(profile) = fetch_profile(provider, &request.code, &pending).await else {
+        return Ok(HttpResponse::Unauthorized().json("Invalid authorization code"));
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match OAuthIdentity::find_or_create_user(conn, &provider.name, &profile, &client) {
+        Ok(Some(user)) => Ok(login_response(&state, conn, user, &client)),
+        Ok(None) => Ok(HttpResponse::Conflict().json("An account with this email already exists")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+// Starts an authorization request and answers with the URL to send the browser to.
+// Links pass the signed-in user so the state can only finish a link for them.
+pub(crate) async fn authorization_response(
+    state: &AppState,
+    provider: &OAuthProviderConfig,
+    user_id: Option<&str>,
+) -> HttpResponse {
+    let Some(endpoints) = resolve_endpoints(provider).await else {
+        return HttpResponse::BadGateway().json("Provider is unavailable");
+    };
+    
+    let issued = {
+        let database = state.database.lock().unwrap();
+        let ttl = Duration::minutes(OAUTH_STATE_TTL_MINUTES);
+        OAuthState::issue(database.get_connection(), &provider.name, user_id, ttl)
+    };
+    let (oauth_state, pending) = match issued {
+        Ok(issued) => issued,
+        Err(_) => return HttpResponse::InternalServerError().json("Database error"),
+    };
+    
+    let code_challenge = URL_SAFE_NO_PAD.encode(Sha256::digest(pending.code_verifier.as_bytes()));
+    let scope = provider.scopes.join(" ");
+    let mut params = vec![
+        ("response_type", "code"),
+        ("client_id", provider.client_id.as_str()),
+        ("redirect_uri", provider.redirect_uri.as_str()),
+        ("state", oauth_state.as_str()),
+        ("code_challenge", code_challenge.as_str()),
+        ("code_challenge_method", "S256"),
+    ];
+    if !scope.is_empty() {
+        params.push(("scope", scope.as_str()));
+    }
+    if provider.issuer.is_some() {
+        params.push(("nonce", pending.nonce.as_str()));
+    }
+    
+    match reqwest::Url::parse_with_params(&endpoints.authorization_url, &params) {
+        Ok(url) => HttpResponse::Ok().json(OAuthStartResponse { authorization_url: url.into() }),
+        Err(_) => HttpResponse::BadGateway().json("Provider is unavailable"),
+    }
+}
+
+pub(crate) fn find_provider<'a>(state: &'a AppState, name: &str) -> Option<&'a OAuthProviderConfig> {
+    state.oauth_providers.iter().find(|provider| provider.name == name)
+}
+
+fn http_client() -> Option<reqwest::Client> {
+    // Some providers, GitHub among them, reject requests without a user agent
+    reqwest::Client::builder().user_agent("Surjo").build().ok()
+}
+
+// Fills in the endpoints that were not configured from the issuer's discovery document
+async fn resolve_endpoints(provider: &OAuthProviderConfig) -> Option<ProviderEndpoints> {
+    let discovered = match &provider.issuer {
+        Some(issuer) => {
+            let url = format!("{}/.well-known/openid-configuration", issuer.trim_end_matches('/'));
+            let document: DiscoveryDocument = http_client()?
+                .get(url)
+                .send()
+                .await
+                .ok()?
+                .error_for_status()
+                .ok()?
+                .json()
+                .await
+                .ok()?;
+            
+            // A document describing some other issuer must not be trusted
+            if document.issuer != *issuer {
+                return None;
+            }
+            Some(document)
+        }
+        None => None,
+    };
+    
+    Some(ProviderEndpoints {
+        authorization_url: provider
+            .authorization_url
+            .clone()
+            .or_else(|| discovered.as_ref().map(|document| document.authorization_endpoint.clone()))?,
+        token_url: provider
+            .token_url
+            .clone()
+            .or_else(|| discovered.as_ref().map(|document| document.token_endpoint.clone()))?,
+        userinfo_url: provider
+            .userinfo_url
+            .clone()
+            .or_else(|| discovered.and_then(|document| document.userinfo_endpoint)),
+    })
+}
+
+// Redeems the authorization code and returns the profile of the account it was
+// issued for. OpenID Connect providers must return an ID token for our client and
+// nonce; as with Google, it comes straight from the token endpoint, so its claims
+// are validated but not its signature. Plain OAuth 2.0 providers are described by
+// their userinfo endpoint alone.
+pub(crate) async fn fetch_profile(
+    provider: &OAuthProviderConfig,
+    code: &str,
+    pending: &PendingAuthorization,
+) -> Option<ExternalProfile> {
+    let endpoints = resolve_endpoints(provider).await?;
+    let client = http_client()?;
+    
+    let tokens: TokenResponse = client
+        .post(&endpoints.token_url)
+        .header(header::ACCEPT, "application/json")
+        .form(&[
+            ("code", code),
+            ("client_id", provider.client_id.as_str()),
+            ("client_secret", provider.client_secret.as_str()),
+            ("redirect_uri", provider.redirect_uri.as_str()),
+            ("grant_type", "authorization_code"),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ])
+        .send()
+        .await
+        .ok()?
+        .error_for_status()
+        .ok()?
+        .json()
+        .await
+        .ok()?;
+    
+    let mut claims = match &provider.issuer {
+        Some(issuer) => {
+            let mut validation = Validation::default();
+            validation.insecure_disable_signature_validation();
+            validation.set_issuer(&[issuer]);
+            validation.set_audience(&[&provider.client_id]);
+            let claims = decode::<Value>(tokens.id_token.as_deref()?, &DecodingKey::from_secret(&[]), &validation)
+                .ok()?
+                .claims;
+            
+            // The nonce ties the ID token to the request this browser started
+            if claims["nonce"].as_str() != Some(pending.nonce.as_str()) {
+                return None;
+            }
+            claims
+        }
+        None => Value::Object(Default::default()),
+    };
+    
+    if let Some(userinfo_url) = &endpoints.userinfo_url {
+        let userinfo: Value = client
+            .get(userinfo_url)
+            .bearer_auth(&tokens.access_token)
+            .header(header::ACCEPT, "application/json")
+            .send()
+            .await
+            .ok()?
+            .error_for_status()
+            .ok()?
+            .json()
+            .await
+            .ok()?;
+        
+        // The userinfo response must describe the same account the ID token was issued for
+        if provider.issuer.is_some() && userinfo["sub"] != claims["sub"] {
+            return None;
+        }
+        claims.as_object_mut()?.extend(userinfo.as_object()?.clone());
+    }
+    
+    profile_from_claims(&claims)
+}
+
+// Reads the standard OpenID Connect claims, falling back to the numeric `id` that
+// GitHub-style APIs use in place of `sub`. Emails only count as verified when the
+// provider says so.
+fn profile_from_claims(claims: &Value) -> Option<ExternalProfile> {
+    let subject = match (&claims["sub"], &claims["id"]) {
+        (Value::String(sub), _) => sub.clone(),
+        (_, Value::String(id)) => id.clone(),
+        (_, Value::Number(id)) => id.to_string(),
+        _ => return None,
+    };
+    let text = |key: &str| claims[key].as_str().filter(|value| !value.is_empty()).map(str::to_string);
+    
+    Some(ExternalProfile {
+        subject,
+        email: text("email")?,
+        email_verified: matches!(&claims["email_verified"], Value::Bool(true))
+            || claims["email_verified"].as_str() == Some("true"),
+        first_name: text("given_name"),
+        last_name: text("family_name"),
+    })
+}
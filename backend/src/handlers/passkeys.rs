@@ -0,0 +1,426 @@
+use actix_web::{delete, get, patch, post, web, HttpResponse, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::Duration;
+use rusqlite::Connection;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::handlers::auth::{
+    clear_account_failures, ip_throttle_response, record_login_failure, sign_in_refusal, start_session_response,
+    throttle_response,
+};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, AuthenticatorSelection, CeremonyPurpose, ClientInfo, CredentialDescriptor, CredentialParameter,
+    LoginResponse, NewAuditEvent, Passkey, PasskeyAuthenticationOptions, PasskeyLoginFinishRequest,
+    PasskeyRegistrationOptions, PasskeyRegistrationRequest, PasskeyResponse, PasskeyUser, RelyingParty,
+    RenamePasskeyRequest, User, WebAuthnChallenge,
+};
+use crate::models::{account_throttle_key, LoginThrottle, ThrottleScope};
+use crate::webauthn::{self, decode_base64url, ClientData, WebAuthnError, CREATE_CEREMONY, ES256, GET_CEREMONY};
+
+const CEREMONY_TTL_MINUTES: i64 = 5;
+const DEFAULT_PASSKEY_NAME: &str = "Passkey";
+const MAX_PASSKEY_NAME_LENGTH: usize = 64;
+
+// Records a rejected passkey sign-in and builds the 401 for it. Failures count
+// against the login throttle like wrong passwords do; a passkey nobody owns
+// only counts against the client address.
+fn passkey_login_failed(
+    conn: &Connection,
+    state: &AppState,
+    client: &ClientInfo,
+    user: Option<&User>,
+    reason: String,
+) -> HttpResponse {
+    let limits = &state.policy.login_throttle;
+    let ip_address = client.ip_address.as_deref();
+    let recorded = match (user, ip_address) {
+        (Some(user), _) => record_login_failure(conn, &account_throttle_key(&user.email), ip_address, limits),
+        (None, Some(ip_address)) => LoginThrottle::record_failure(conn, ThrottleScope::Ip, ip_address, &limits.ip),
+        (None, None) => Ok(()),
+    };
+    if recorded.is_err() {
+        return HttpResponse::InternalServerError().json("Database error");
+    }
+    
+    let mut event = NewAuditEvent::new("auth.passkey_login_failed", client).diff(json!({ "reason": reason }));
+    if let Some(user) = user {
+        event = event.target("user", &user.id);
+    }
+    audit(conn, event);
+    HttpResponse::Unauthorized().json(reason)
+}
+
+fn credential_descriptors(passkeys: Vec<Passkey>) -> Vec<CredentialDescriptor> {
+    passkeys
+        .into_iter()
+        .map(|passkey| CredentialDescriptor {
+            credential_type: "public-key".to_string(),
+            id: passkey.credential_id,
+        })
+        .collect()
+}
+
+fn passkey_name(name: Option<&str>) -> Result<String, HttpResponse> {
+    let name = name.map(str::trim).unwrap_or(DEFAULT_PASSKEY_NAME);
+    if name.is_empty() || name.chars().count() > MAX_PASSKEY_NAME_LENGTH {
+        return Err(HttpResponse::BadRequest().json("Passkey name must be between 1 and 64 characters"));
+    }
+    Ok(name.to_string())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/passkeys/register/start",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.create()", body = PasskeyRegistrationOptions),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/passkeys/register/start")]
+pub async fn start_passkey_registration(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let ttl = Duration::minutes(CEREMONY_TTL_MINUTES);
+    let (challenge, existing) = match WebAuthnChallenge::issue(conn, Some(&auth.user.id), CeremonyPurpose::Registration, ttl)
+        .and_then(|challenge| Ok((challenge, Passkey::find_for_user(conn, &auth.user.id)?)))
+    {
+        Ok(result) => result,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    let display_name = match (&auth.user.first_name, &auth.user.last_name) {
+        (Some(first), Some(last)) => format!("{first} {last}"),
+        (Some(name), None) | (None, Some(name)) => name.clone(),
+        (None, None) => auth.user.email.clone(),
+    };
+    
+    Ok(HttpResponse::Ok().json(PasskeyRegistrationOptions {
+        challenge,
+        rp: RelyingParty {
+            id: state.webauthn.rp_id.clone(),
+            name: state.webauthn.rp_name.clone(),
+        },
+        user: PasskeyUser {
+            id: URL_SAFE_NO_PAD.encode(auth.user.id.as_bytes()),
+            name: auth.user.email.clone(),
+            display_name,
+        },
+        pub_key_cred_params: vec![CredentialParameter {
+            credential_type: "public-key".to_string(),
+            alg: ES256,
+        }],
+        timeout: ttl.num_milliseconds() as u32,
+        attestation: "none".to_string(),
+        authenticator_selection: AuthenticatorSelection {
+            resident_key: "required".to_string(),
+            user_verification: "required".to_string(),
+        },
+        exclude_credentials: credential_descriptors(existing),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/passkeys/register/finish",
+    request_body = PasskeyRegistrationRequest,
+    responses(
+        (status = 201, description = "Passkey registered", body = PasskeyResponse),
+        (status = 400, description = "Invalid or expired registration"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 409, description = "Passkey is already registered")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/passkeys/register/finish")]
+pub async fn finish_passkey_registration(
+    request: web::Json<PasskeyRegistrationRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let name = match passkey_name(request.name.as_deref()) {
+        Ok(name) => name,
+        Err(response) => return Ok(response),
+    };
+    
+    let response = &request.credential.response;
+    let verified = decode_base64url(&response.client_data_json).and_then(|client_data_json| {
+        let client_data = ClientData::parse(&client_data_json, CREATE_CEREMONY, &state.webauthn)?;
+        let attestation_object = decode_base64url(&response.attestation_object)?;
+        Ok((client_data, webauthn::verify_registration(&attestation_object, &state.webauthn)?))
+    });
+    let (client_data, credential) = match verified {
+        Ok(verified) => verified,
+        Err(e) => return Ok(HttpResponse::BadRequest().json(e.to_string())),
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match WebAuthnChallenge::consume(conn, &client_data.challenge, CeremonyPurpose::Registration) {
+        Ok(Some(Some(user_id))) if user_id == auth.user.id => {}
+        Ok(_) => return Ok(HttpResponse::BadRequest().json("Invalid or expired challenge")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match Passkey::find_by_credential_id(conn, &credential.credential_id) {
+        Ok(None) => {}
+        Ok(Some(_)) => return Ok(HttpResponse::Conflict().json("Passkey is already registered")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match Passkey::create(
+        conn,
+        &auth.user.id,
+        &credential.credential_id,
+        &credential.public_key,
+        credential.sign_count,
+        &name,
+    ) {
+        Ok(passkey) => {
+            let event = caller_event(&auth, &client, "auth.passkey_added").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "passkey_id": passkey.id, "name": passkey.name })));
+            Ok(HttpResponse::Created().json(PasskeyResponse::from(passkey)))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/me/passkeys",
+    responses(
+        (status = 200, description = "Passkeys of the caller", body = Vec<PasskeyResponse>),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/me/passkeys")]
+pub async fn list_my_passkeys(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    
+    match Passkey::find_for_user(database.get_connection(), &auth.user.id) {
+        Ok(passkeys) => {
+            let response: Vec<PasskeyResponse> = passkeys.into_iter().map(PasskeyResponse::from).collect();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/me/passkeys/{id}",
+    request_body = RenamePasskeyRequest,
+    responses(
+        (status = 200, description = "Passkey renamed", body = PasskeyResponse),
+        (status = 400, description = "Invalid name"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Passkey not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[patch("/api/me/passkeys/{id}")]
+pub async fn rename_my_passkey(
+    path: web::Path<String>,
+    request: web::Json<RenamePasskeyRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    let name = match passkey_name(Some(&request.name)) {
+        Ok(name) => name,
+        Err(response) => return Ok(response),
+    };
+    
+    let database = state.database.lock().unwrap();
+    
+    match Passkey::rename(database.get_connection(), &auth.user.id, &path.into_inner(), &name) {
+        Ok(Some(passkey)) => Ok(HttpResponse::Ok().json(PasskeyResponse::from(passkey))),
+        Ok(None) => Ok(HttpResponse::NotFound().json("Passkey not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/passkeys/{id}",
+    responses(
+        (status = 204, description = "Passkey removed"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 404, description = "Passkey not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/passkeys/{id}")]
+pub async fn delete_my_passkey(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    let passkey_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Passkey::delete_for_user(conn, &auth.user.id, &passkey_id) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "auth.passkey_removed").target("user", &auth.user.id);
+            audit(conn, event.diff(json!({ "passkey_id": passkey_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("Passkey not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/passkey/start",
+    responses(
+        (status = 200, description = "Options for navigator.credentials.get()", body = PasskeyAuthenticationOptions)
+    )
+)]
+#[post("/api/auth/passkey/start")]
+pub async fn start_passkey_login(state: web::Data<AppState>) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    // Every ceremony is discoverable: the authenticator offers whichever passkey
+    // it holds for this site, so the options never reveal which accounts exist
+    // or which credentials they have
+    let ttl = Duration::minutes(CEREMONY_TTL_MINUTES);
+    let challenge = match WebAuthnChallenge::issue(conn, None, CeremonyPurpose::Authentication, ttl) {
+        Ok(challenge) => challenge,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    Ok(HttpResponse::Ok().json(PasskeyAuthenticationOptions {
+        challenge,
+        rp_id: state.webauthn.rp_id.clone(),
+        timeout: ttl.num_milliseconds() as u32,
+        user_verification: "required".to_string(),
+        allow_credentials: Vec::new(),
+    }))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/passkey/finish",
+    request_body = PasskeyLoginFinishRequest,
+    responses(
+        (status = 200, description = "Passkey sign-in successful", body = LoginResponse),
+        (status = 401, description = "Invalid passkey assertion"),
+        (status = 403, description = "Account is disabled or email not verified"),
+        (status = 423, description = "Account temporarily locked after repeated failures"),
+        (status = 429, description = "Too many failed attempts; retry after the given delay")
+    )
+)]
+#[post("/api/auth/passkey/finish")]
+pub async fn finish_passkey_login(
+    request: web::Json<PasskeyLoginFinishRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let response = &request.response;
+    let decoded = decode_base64url(&response.client_data_json).and_then(|client_data_json| {
+        let client_data = ClientData::parse(&client_data_json, GET_CEREMONY, &state.webauthn)?;
+        let authenticator_data = decode_base64url(&response.authenticator_data)?;
+        let signature = decode_base64url(&response.signature)?;
+        let user_handle = response.user_handle.as_deref().map(decode_base64url).transpose()?;
+        Ok((client_data_json, client_data, authenticator_data, signature, user_handle))
+    });
+    let (client_data_json, client_data, authenticator_data, signature, user_handle) = match decoded {
+        Ok(decoded) => decoded,
+        Err(e) => return Ok(HttpResponse::Unauthorized().json(e.to_string())),
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let limits = &state.policy.login_throttle;
+    let ip_address = client.ip_address.as_deref();
+    match ip_throttle_response(conn, ip_address, limits) {
+        Ok(Some(response)) => return Ok(response),
+        Ok(None) => {}
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match WebAuthnChallenge::consume(conn, &client_data.challenge, CeremonyPurpose::Authentication) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid or expired challenge")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    let passkey = match Passkey::find_by_credential_id(conn, &request.id) {
+        Ok(Some(passkey)) => passkey,
+        Ok(None) => return Ok(passkey_login_failed(conn, &state, &client, None, "Unknown passkey".to_string())),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    let user = match User::find_by_id(conn, &passkey.user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(passkey_login_failed(conn, &state, &client, None, "Unknown passkey".to_string())),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match throttle_response(conn, &account_throttle_key(&user.email), ip_address, limits) {
+        Ok(Some(response)) => return Ok(response),
+        Ok(None) => {}
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    // The passkey must belong to the account the authenticator says it was created for
+    if user_handle.is_some_and(|handle| handle != passkey.user_id.as_bytes()) {
+        return Ok(passkey_login_failed(conn, &state, &client, Some(&user), "Unknown passkey".to_string()));
+    }
+    
+    let sign_count = match webauthn::verify_assertion(
+        &passkey.public_key,
+        passkey.sign_count,
+        &authenticator_data,
+        &client_data_json,
+        &signature,
+        &state.webauthn,
+    ) {
+        Ok(sign_count) => sign_count,
+        Err(e) => {
+            if e == WebAuthnError::SignCount {
+                log::warn!("Passkey {} for user {} reported a stale signature counter", passkey.id, passkey.user_id);
+            }
+            return Ok(passkey_login_failed(conn, &state, &client, Some(&user), e.to_string()));
+        }
+    };
+    
+    if Passkey::record_use(conn, &passkey.id, sign_count).is_err() {
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    
+    // A user-verifying passkey is already two factors, so no TOTP challenge follows
+    if let Some(response) = sign_in_refusal(&state, &user) {
+        return Ok(response);
+    }
+    if clear_account_failures(conn, &user).is_err() {
+        return Ok(HttpResponse::InternalServerError().json("Database error"));
+    }
+    Ok(start_session_response(&state, conn, user, &client))
+}
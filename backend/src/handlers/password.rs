@@ -0,0 +1,130 @@
+use std::collections::HashMap;
+
+use actix_web::{post, web, HttpResponse, Result};
+use chrono::Duration;
+use serde_json::json;
+use crate::handlers::audit::audit;
+use crate::mailer::PASSWORD_RESET;
+use crate::models::{
+    account_throttle_key, AppState, ClientInfo, LoginThrottle, NewAuditEvent, OneTimeToken, PasswordResetConfirmRequest, PasswordResetRequest,
+    Session, ThrottleScope, TokenPurpose, User,
+};
+
+const PASSWORD_RESET_TTL_MINUTES: i64 = 30;
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/password-reset/request",
+    request_body = PasswordResetRequest,
+    responses(
+        (status = 202, description = "A reset link was sent if the account exists")
+    )
+)]
+#[post("/api/auth/password-reset/request")]
+pub async fn request_password_reset(
+    request: web::Json<PasswordResetRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    // The response is the same whether or not the email is registered
+    let accepted = HttpResponse::Accepted().json("If the account exists, a reset link has been sent");
+    
+    let token = {
+        let database = state.database.lock().unwrap();
+        let conn = database.get_connection();
+        
+        let user = match User::find_by_email(conn, &request.email) {
+            Ok(user) => user,
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        };
+        
+        // Recorded for unknown addresses too, so probing for accounts shows up
+        let mut event = NewAuditEvent::new("auth.password_reset_requested", &client).diff(json!({ "email": request.email }));
+        if let Some(user) = &user {
+            event = event.target("user", &user.id);
+        }
+        audit(conn, event);
+        
+        let user = match user {
+            Some(user) if user.is_active => user,
+            _ => return Ok(accepted),
+        };
+        
+        match OneTimeToken::issue(
+            conn,
+            &user.id,
+            TokenPurpose::PasswordReset,
+            Duration::minutes(PASSWORD_RESET_TTL_MINUTES),
+        ) {
+            Ok(token) => token,
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+    };
+    
+    let message = PASSWORD_RESET.render(
+        &request.email,
+        &HashMap::from([
+            ("link", format!("{}/reset-password?token={token}", state.app_url)),
+            ("ttl_minutes", PASSWORD_RESET_TTL_MINUTES.to_string()),
+        ]),
+    );
+    if let Err(e) = state.mailer.send(&message) {
+        log::error!("Failed to send password reset email: {e}");
+    }
+    
+    Ok(accepted)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/password-reset/confirm",
+    request_body = PasswordResetConfirmRequest,
+    responses(
+        (status = 204, description = "Password changed and all sessions revoked"),
+        (status = 400, description = "Invalid or expired token, or password too short")
+    )
+)]
+#[post("/api/auth/password-reset/confirm")]
+pub async fn confirm_password_reset(
+    request: web::Json<PasswordResetConfirmRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    if request.new_password.chars().count() < MIN_PASSWORD_LENGTH {
+        return Ok(HttpResponse::BadRequest().json("Password is too short"));
+    }
+    
+    // Hash before touching the token so a hashing failure cannot burn it
+    let password_hash = match bcrypt::hash(&request.new_password, bcrypt::DEFAULT_COST) {
+        Ok(hash) => hash,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Failed to hash password")),
+    };
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user_id = match OneTimeToken::consume(conn, &request.token, TokenPurpose::PasswordReset) {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired token")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    // Whoever was signed in with the old password is signed out. Following the
+    // emailed link also proves the user owns the address, so any lockout is lifted.
+    match User::set_password_hash(conn, &user_id, &password_hash)
+        .and_then(|_| User::mark_email_verified(conn, &user_id))
+        .and_then(|_| Session::delete_all_for_user(conn, &user_id))
+        .and_then(|_| User::find_by_id(conn, &user_id))
+        .and_then(|user| match user {
+            Some(user) => LoginThrottle::clear(conn, ThrottleScope::Account, &account_throttle_key(&user.email)),
+            None => Ok(false),
+        })
+    {
+        Ok(_) => {
+            audit(conn, NewAuditEvent::new("auth.password_reset", &client).actor(&user_id).target("user", &user_id));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
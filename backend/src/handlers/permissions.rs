@@ -0,0 +1,310 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Result};
+use rusqlite::Connection;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event, changes};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, ClientInfo, CreatePermissionRequest, Group, Permission, PermissionResponse, Role, UpdatePermissionRequest, User,
+    UserPermissionsResponse, ADMIN_PERMISSION, USER_PERMISSION,
+};
+
+fn sorted(names: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut names: Vec<String> = names.into_iter().collect();
+    names.sort();
+    names
+}
+
+fn user_permissions_response(conn: &Connection, user_id: &str) -> rusqlite::Result<UserPermissionsResponse> {
+    Ok(UserPermissionsResponse {
+        user_id: user_id.to_string(),
+        permissions: sorted(Permission::names_for_user(conn, user_id)?),
+        direct: sorted(Permission::direct_names_for_user(conn, user_id)?),
+        roles: Role::find_for_user(conn, user_id)?.into_iter().map(|role| role.name).collect(),
+        groups: Group::find_for_user(conn, user_id)?.into_iter().map(|group| group.name).collect(),
+    })
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/permissions",
+    responses(
+        (status = 200, description = "All permissions", body = Vec<PermissionResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/permissions")]
+pub async fn list_permissions(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    
+    match Permission::find_all(database.get_connection()) {
+        Ok(permissions) => {
+            let response: Vec<PermissionResponse> = permissions.into_iter().map(PermissionResponse::from).collect();
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/permissions",
+    request_body = CreatePermissionRequest,
+    responses(
+        (status = 201, description = "Permission created", body = PermissionResponse),
+        (status = 400, description = "Missing name or name contains whitespace"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 409, description = "A permission with this name already exists")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/permissions")]
+pub async fn create_permission(
+    request: web::Json<CreatePermissionRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Name is required"));
+    }
+    // Names are matched verbatim by `require_permission` and API key scopes
+    if name.contains(char::is_whitespace) {
+        return Ok(HttpResponse::BadRequest().json("Name must not contain whitespace"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Permission::create(conn, name, request.description.as_deref()) {
+        Ok(permission) => {
+            let event = caller_event(&auth, &client, "permission.create").target("permission", &permission.id);
+            audit(conn, event.diff(json!({ "name": permission.name })));
+            Ok(HttpResponse::Created().json(PermissionResponse::from(permission)))
+        }
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            Ok(HttpResponse::Conflict().json("A permission with this name already exists"))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/permissions/{id}",
+    request_body = UpdatePermissionRequest,
+    responses(
+        (status = 200, description = "Permission updated", body = PermissionResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Permission not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/permissions/{id}")]
+pub async fn update_permission(
+    path: web::Path<String>,
+    request: web::Json<UpdatePermissionRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let permission_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let before = match Permission::find_by_id(conn, &permission_id) {
+        Ok(Some(permission)) => PermissionResponse::from(permission),
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Permission not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    let updated = Permission::set_description(conn, &permission_id, request.description.as_deref())
+        .and_then(|_| Permission::find_by_id(conn, &permission_id));
+    match updated {
+        Ok(Some(permission)) => {
+            let response = PermissionResponse::from(permission);
+            let event = caller_event(&auth, &client, "permission.update").target("permission", &permission_id);
+            audit(conn, event.diff(changes(&before, &response)));
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("Permission not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/permissions/{id}",
+    responses(
+        (status = 204, description = "Permission deleted and revoked from every user, role and API key"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Permission not found"),
+        (status = 409, description = "Default permissions cannot be deleted")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/permissions/{id}")]
+pub async fn delete_permission(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let permission = match Permission::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(permission)) => permission,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Permission not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    if permission.name == ADMIN_PERMISSION || permission.name == USER_PERMISSION {
+        return Ok(HttpResponse::Conflict().json("Default permissions cannot be deleted"));
+    }
+    
+    match Permission::delete(conn, &permission.id) {
+        Ok(_) => {
+            let event = caller_event(&auth, &client, "permission.delete").target("permission", &permission.id);
+            audit(conn, event.diff(json!({ "name": permission.name })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/permissions",
+    responses(
+        (status = 200, description = "The user's effective permissions and where they come from", body = UserPermissionsResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/users/{id}/permissions")]
+pub async fn get_user_permissions(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let user_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match User::find_by_id(conn, &user_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match user_permissions_response(conn, &user_id) {
+        Ok(response) => Ok(HttpResponse::Ok().json(response)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/users/{id}/permissions/{permission_id}",
+    responses(
+        (status = 204, description = "Permission granted to the user directly"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "User or permission not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/users/{id}/permissions/{permission_id}")]
+pub async fn grant_user_permission(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (user_id, permission_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let permission = match User::find_by_id(conn, &user_id) {
+        Ok(Some(_)) => Permission::find_by_id(conn, &permission_id),
+        Ok(None) => Ok(None),
+        Err(err) => Err(err),
+    };
+    let permission = match permission {
+        Ok(Some(permission)) => permission,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User or permission not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match Permission::grant(conn, &user_id, &permission.name) {
+        Ok(granted) => {
+            if granted {
+                let event = caller_event(&auth, &client, "permission.grant").target("user", &user_id);
+                audit(conn, event.diff(json!({ "permission": permission.name })));
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}/permissions/{permission_id}",
+    responses(
+        (status = 204, description = "Direct grant removed; grants through roles and groups are unaffected"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "The user does not have this permission directly")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/users/{id}/permissions/{permission_id}")]
+pub async fn revoke_user_permission(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (user_id, permission_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let revoked = Permission::find_by_id(conn, &permission_id).and_then(|permission| match permission {
+        Some(permission) => Ok(Permission::revoke(conn, &user_id, &permission.name)?.then_some(permission.name)),
+        None => Ok(None),
+    });
+    match revoked {
+        Ok(Some(name)) => {
+            let event = caller_event(&auth, &client, "permission.revoke").target("user", &user_id);
+            audit(conn, event.diff(json!({ "permission": name })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(None) => Ok(HttpResponse::NotFound().json("The user does not have this permission directly")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
@@ -0,0 +1,357 @@
+use actix_web::{delete, get, post, put, web, HttpResponse, Result};
+use rusqlite::Connection;
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event, changes};
+use crate::middleware::AuthUser;
+use crate::models::{
+    AppState, ClientInfo, CreateRoleRequest, Permission, Role, RoleResponse, UpdateRoleRequest, User, ADMIN_PERMISSION, ADMIN_ROLE,
+    USER_ROLE,
+};
+
+fn role_response(conn: &Connection, role: Role) -> rusqlite::Result<RoleResponse> {
+    let permissions = Role::permissions(conn, &role.id)?;
+    Ok(RoleResponse::new(role, permissions))
+}
+
+// Names the first permission that does not exist, if any
+fn unknown_permission(conn: &Connection, permissions: &[String]) -> rusqlite::Result<Option<String>> {
+    let known = Permission::all_names(conn)?;
+    Ok(permissions.iter().find(|permission| !known.contains(*permission)).cloned())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/roles",
+    responses(
+        (status = 200, description = "All roles with their permissions", body = Vec<RoleResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/roles")]
+pub async fn list_roles(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let roles = Role::find_all(conn)
+        .and_then(|roles| roles.into_iter().map(|role| role_response(conn, role)).collect::<rusqlite::Result<Vec<_>>>());
+    match roles {
+        Ok(roles) => Ok(HttpResponse::Ok().json(roles)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/roles",
+    request_body = CreateRoleRequest,
+    responses(
+        (status = 201, description = "Role created", body = RoleResponse),
+        (status = 400, description = "Missing name or unknown permission"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 409, description = "A role with this name already exists")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/roles")]
+pub async fn create_role(
+    request: web::Json<CreateRoleRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let name = request.name.trim();
+    if name.is_empty() {
+        return Ok(HttpResponse::BadRequest().json("Name is required"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match unknown_permission(conn, &request.permissions) {
+        Ok(None) => {}
+        Ok(Some(permission)) => return Ok(HttpResponse::BadRequest().json(format!("Unknown permission: {permission}"))),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    let role = match Role::create(conn, name, request.description.as_deref(), &request.permissions) {
+        Ok(role) => role,
+        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
+            return Ok(HttpResponse::Conflict().json("A role with this name already exists"));
+        }
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match role_response(conn, role) {
+        Ok(response) => {
+            let event = caller_event(&auth, &client, "role.create").target("role", &response.id);
+            audit(conn, event.diff(json!({ "name": response.name, "permissions": response.permissions })));
+            Ok(HttpResponse::Created().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/roles/{id}",
+    responses(
+        (status = 200, description = "Role found", body = RoleResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Role not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/roles/{id}")]
+pub async fn get_role(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Role::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(role)) => match role_response(conn, role) {
+            Ok(response) => Ok(HttpResponse::Ok().json(response)),
+            Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+        },
+        Ok(None) => Ok(HttpResponse::NotFound().json("Role not found")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/roles/{id}",
+    request_body = UpdateRoleRequest,
+    responses(
+        (status = 200, description = "Role updated", body = RoleResponse),
+        (status = 400, description = "Unknown permission"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Role not found"),
+        (status = 409, description = "The admin role must keep the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/roles/{id}")]
+pub async fn update_role(
+    path: web::Path<String>,
+    request: web::Json<UpdateRoleRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let role = match Role::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(role)) => role,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Role not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    let before = match role_response(conn, role.clone()) {
+        Ok(before) => before,
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    if let Some(permissions) = &request.permissions {
+        // Otherwise nobody holding only the role could manage roles any more
+        if role.name == ADMIN_ROLE && !permissions.iter().any(|permission| permission == ADMIN_PERMISSION) {
+            return Ok(HttpResponse::Conflict().json("The admin role must keep the admin permission"));
+        }
+        match unknown_permission(conn, permissions) {
+            Ok(None) => {}
+            Ok(Some(permission)) => return Ok(HttpResponse::BadRequest().json(format!("Unknown permission: {permission}"))),
+            Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+        }
+    }
+    
+    let updated = Role::update(conn, &role.id, request.description.as_deref(), request.permissions.as_deref())
+        .and_then(|_| Role::find_by_id(conn, &role.id))
+        .map(|updated| updated.unwrap_or(role));
+    match updated.and_then(|role| role_response(conn, role)) {
+        Ok(response) => {
+            let event = caller_event(&auth, &client, "role.update").target("role", &response.id);
+            audit(conn, event.diff(changes(&before, &response)));
+            Ok(HttpResponse::Ok().json(response))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/roles/{id}",
+    responses(
+        (status = 204, description = "Role deleted and removed from every user and group"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Role not found"),
+        (status = 409, description = "Default roles cannot be deleted")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/roles/{id}")]
+pub async fn delete_role(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let role = match Role::find_by_id(conn, &path.into_inner()) {
+        Ok(Some(role)) => role,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("Role not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    if role.name == ADMIN_ROLE || role.name == USER_ROLE {
+        return Ok(HttpResponse::Conflict().json("Default roles cannot be deleted"));
+    }
+    
+    match Role::delete(conn, &role.id) {
+        Ok(_) => {
+            let event = caller_event(&auth, &client, "role.delete").target("role", &role.id);
+            audit(conn, event.diff(json!({ "name": role.name })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/roles",
+    responses(
+        (status = 200, description = "Roles assigned to the user directly", body = Vec<RoleResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/users/{id}/roles")]
+pub async fn list_user_roles(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let user_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match User::find_by_id(conn, &user_id) {
+        Ok(Some(_)) => {}
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    let roles = Role::find_for_user(conn, &user_id)
+        .and_then(|roles| roles.into_iter().map(|role| role_response(conn, role)).collect::<rusqlite::Result<Vec<_>>>());
+    match roles {
+        Ok(roles) => Ok(HttpResponse::Ok().json(roles)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/users/{id}/roles/{role_id}",
+    responses(
+        (status = 204, description = "Role assigned"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "User or role not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[put("/api/users/{id}/roles/{role_id}")]
+pub async fn assign_role(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (user_id, role_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let role = User::find_by_id(conn, &user_id).and_then(|user| match user {
+        Some(_) => Role::find_by_id(conn, &role_id),
+        None => Ok(None),
+    });
+    let role = match role {
+        Ok(Some(role)) => role,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User or role not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match Role::assign(conn, &user_id, &role.id) {
+        Ok(assigned) => {
+            if assigned {
+                let event = caller_event(&auth, &client, "role.assign").target("user", &user_id);
+                audit(conn, event.diff(json!({ "role": role.name })));
+            }
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}/roles/{role_id}",
+    responses(
+        (status = 204, description = "Role removed from the user"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "The user does not have this role")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/users/{id}/roles/{role_id}")]
+pub async fn unassign_role(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (user_id, role_id) = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Role::unassign(conn, &user_id, &role_id) {
+        Ok(true) => {
+            let event = caller_event(&auth, &client, "role.unassign").target("user", &user_id);
+            audit(conn, event.diff(json!({ "role_id": role_id })));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Ok(false) => Ok(HttpResponse::NotFound().json("The user does not have this role")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
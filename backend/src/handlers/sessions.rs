@@ -0,0 +1,143 @@
+use actix_web::{delete, get, web, HttpResponse, Result};
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::middleware::AuthUser;
+use crate::models::{AppState, ClientInfo, NewAuditEvent, Session, SessionResponse, ADMIN_PERMISSION};
+
+#[utoipa::path(
+    get,
+    path = "/api/me/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the caller", body = Vec<SessionResponse>),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/me/sessions")]
+pub async fn list_my_sessions(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    Ok(sessions_response(&state, &auth.user.id, auth.session_id().unwrap_or_default()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/sessions",
+    responses(
+        (status = 204, description = "Signed out everywhere"),
+        (status = 401, description = "Not authenticated")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/sessions")]
+pub async fn revoke_all_my_sessions(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Session::delete_all_for_user(conn, &auth.user.id) {
+        Ok(_) => {
+            audit(conn, caller_event(&auth, &client, "auth.sessions_revoked").target("user", &auth.user.id));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/sessions/{id}",
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 404, description = "Session not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/sessions/{id}")]
+pub async fn revoke_my_session(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let event = caller_event(&auth, &client, "auth.session_revoked").target("user", &auth.user.id);
+    Ok(revoke_response(&state, &auth.user.id, &path.into_inner(), event))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/users/{id}/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the user", body = Vec<SessionResponse>),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[get("/api/users/{id}/sessions")]
+pub async fn list_user_sessions(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    Ok(sessions_response(&state, &path.into_inner(), auth.session_id().unwrap_or_default()))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/users/{id}/sessions/{session_id}",
+    responses(
+        (status = 204, description = "Session revoked"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "Session not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/users/{id}/sessions/{session_id}")]
+pub async fn revoke_user_session(
+    path: web::Path<(String, String)>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let (user_id, session_id) = path.into_inner();
+    let event = caller_event(&auth, &client, "auth.session_revoked").target("user", &user_id);
+    Ok(revoke_response(&state, &user_id, &session_id, event))
+}
+
+fn sessions_response(state: &AppState, user_id: &str, current_session_id: &str) -> HttpResponse {
+    let database = state.database.lock().unwrap();
+    
+    match Session::find_active_for_user(database.get_connection(), user_id) {
+        Ok(sessions) => {
+            let response: Vec<SessionResponse> = sessions
+                .into_iter()
+                .map(|session| SessionResponse::new(session, current_session_id))
+                .collect();
+            HttpResponse::Ok().json(response)
+        }
+        Err(_) => HttpResponse::InternalServerError().json("Database error"),
+    }
+}
+
+fn revoke_response(state: &AppState, user_id: &str, session_id: &str, event: NewAuditEvent) -> HttpResponse {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match Session::delete_for_user(conn, user_id, session_id) {
+        Ok(true) => {
+            audit(conn, event.diff(json!({ "session_id": session_id })));
+            HttpResponse::NoContent().finish()
+        }
+        Ok(false) => HttpResponse::NotFound().json("Session not found"),
+        Err(_) => HttpResponse::InternalServerError().json("Database error"),
+    }
+}
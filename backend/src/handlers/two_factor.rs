@@ -0,0 +1,256 @@
+use actix_web::{delete, post, web, HttpResponse, Result};
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event};
+use crate::handlers::auth::{
+    clear_account_failures, record_login_failure, sign_in_refusal, start_session_response, throttle_response,
+};
+use crate::middleware::AuthUser;
+use crate::models::{
+    account_throttle_key, otpauth_uri, AppState, ClientInfo, LoginResponse, NewAuditEvent, OneTimeToken, RecoveryCodesResponse, TokenPurpose, TwoFactor,
+    TwoFactorCodeRequest, TwoFactorSetupResponse, TwoFactorVerifyRequest, User,
+};
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/2fa/verify",
+    request_body = TwoFactorVerifyRequest,
+    responses(
+        (status = 200, description = "Second factor accepted", body = LoginResponse),
+        (status = 400, description = "Neither a code nor a recovery code was given"),
+        (status = 401, description = "Invalid challenge or code"),
+        (status = 403, description = "Account is disabled"),
+        (status = 423, description = "Account temporarily locked after repeated failures; see Retry-After"),
+        (status = 429, description = "Too many failed attempts; see Retry-After")
+    )
+)]
+#[post("/api/auth/2fa/verify")]
+pub async fn verify_two_factor(
+    request: web::Json<TwoFactorVerifyRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    if request.code.is_none() && request.recovery_code.is_none() {
+        return Ok(HttpResponse::BadRequest().json("A code or recovery code is required"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    // The challenge is spent by this attempt whatever the outcome, so each password
+    // check buys exactly one guess at the second factor
+    let user_id = match OneTimeToken::consume(conn, &request.challenge_token, TokenPurpose::TwoFactorChallenge) {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid or expired challenge")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    let user = match User::find_by_id(conn, &user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(HttpResponse::Unauthorized().json("Invalid or expired challenge")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    if let Some(response) = sign_in_refusal(&state, &user) {
+        return Ok(response);
+    }
+    
+    // Wrong codes count towards the same limits as wrong passwords
+    let limits = &state.policy.login_throttle;
+    let account_key = account_throttle_key(&user.email);
+    let ip_address = client.ip_address.as_deref();
+    match throttle_response(conn, &account_key, ip_address, limits) {
+        Ok(Some(response)) => return Ok(response),
+        Ok(None) => {}
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    let verified = match (&request.code, &request.recovery_code) {
+        (Some(code), _) => TwoFactor::verify(conn, &user.id, code),
+        (None, Some(recovery_code)) => TwoFactor::consume_recovery_code(conn, &user.id, recovery_code),
+        (None, None) => Ok(false),
+    };
+    
+    match verified {
+        Ok(true) => match clear_account_failures(conn, &user) {
+            Ok(_) => Ok(start_session_response(&state, conn, user, &client)),
+            Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+        },
+        Ok(false) => {
+            if record_login_failure(conn, &account_key, ip_address, limits).is_err() {
+                return Ok(HttpResponse::InternalServerError().json("Database error"));
+            }
+            let event = NewAuditEvent::new("auth.2fa_failed", &client).target("user", &user.id);
+            audit(conn, event.diff(json!({ "method": if request.code.is_some() { "code" } else { "recovery_code" } })));
+            Ok(HttpResponse::Unauthorized().json("Invalid two-factor code"))
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/2fa/setup",
+    responses(
+        (status = 200, description = "New secret to add to an authenticator app", body = TwoFactorSetupResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 409, description = "Two-factor authentication is already enabled")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/2fa/setup")]
+pub async fn setup_two_factor(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match TwoFactor::is_enabled(conn, &auth.user.id) {
+        Ok(false) => {}
+        Ok(true) => return Ok(HttpResponse::Conflict().json("Two-factor authentication is already enabled")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match TwoFactor::begin_enrollment(conn, &auth.user.id) {
+        Ok(secret) => Ok(HttpResponse::Ok().json(TwoFactorSetupResponse {
+            otpauth_uri: otpauth_uri(&secret, &auth.user.email),
+            secret,
+        })),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/2fa/confirm",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Two-factor authentication enabled", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code or no pending setup"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/2fa/confirm")]
+pub async fn confirm_two_factor(
+    request: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    match TwoFactor::confirm(conn, &auth.user.id, &request.code) {
+        Ok(true) => {}
+        Ok(false) => return Ok(HttpResponse::BadRequest().json("Invalid two-factor code")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+    audit(conn, caller_event(&auth, &client, "auth.2fa_enabled").target("user", &auth.user.id));
+    
+    Ok(recovery_codes_response(conn, &auth.user.id))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/me/2fa/recovery-codes",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 200, description = "Previous recovery codes replaced", body = RecoveryCodesResponse),
+        (status = 400, description = "Invalid code"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 409, description = "Two-factor authentication is not enabled")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/me/2fa/recovery-codes")]
+pub async fn regenerate_recovery_codes(
+    request: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    if let Some(response) = check_enabled_code(conn, &auth.user.id, &request.code) {
+        return Ok(response);
+    }
+    audit(conn, caller_event(&auth, &client, "auth.2fa_recovery_codes_regenerated").target("user", &auth.user.id));
+    
+    Ok(recovery_codes_response(conn, &auth.user.id))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/me/2fa",
+    request_body = TwoFactorCodeRequest,
+    responses(
+        (status = 204, description = "Two-factor authentication disabled"),
+        (status = 400, description = "Invalid code"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires a signed-in session, not impersonation"),
+        (status = 409, description = "Two-factor authentication is not enabled")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[delete("/api/me/2fa")]
+pub async fn disable_two_factor(
+    request: web::Json<TwoFactorCodeRequest>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_session()?;
+    auth.require_not_impersonating()?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    if let Some(response) = check_enabled_code(conn, &auth.user.id, &request.code) {
+        return Ok(response);
+    }
+    
+    match TwoFactor::disable(conn, &auth.user.id) {
+        Ok(_) => {
+            audit(conn, caller_event(&auth, &client, "auth.2fa_disabled").target("user", &auth.user.id));
+            Ok(HttpResponse::NoContent().finish())
+        }
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+// Changes to an enabled second factor need a fresh code, so a stolen access token
+// alone cannot remove it. Returns the error response when the check fails.
+fn check_enabled_code(conn: &rusqlite::Connection, user_id: &str, code: &str) -> Option<HttpResponse> {
+    match TwoFactor::is_enabled(conn, user_id) {
+        Ok(true) => {}
+        Ok(false) => return Some(HttpResponse::Conflict().json("Two-factor authentication is not enabled")),
+        Err(_) => return Some(HttpResponse::InternalServerError().json("Database error")),
+    }
+    
+    match TwoFactor::verify(conn, user_id, code) {
+        Ok(true) => None,
+        Ok(false) => Some(HttpResponse::BadRequest().json("Invalid two-factor code")),
+        Err(_) => Some(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+fn recovery_codes_response(conn: &rusqlite::Connection, user_id: &str) -> HttpResponse {
+    match TwoFactor::generate_recovery_codes(conn, user_id) {
+        Ok(recovery_codes) => HttpResponse::Ok().json(RecoveryCodesResponse { recovery_codes }),
+        Err(_) => HttpResponse::InternalServerError().json("Database error"),
+    }
+}
@@ -1,5 +1,14 @@
 use actix_web::{get, post, put, web, HttpResponse, Result};
-use crate::models::{AppState, CreateUserRequest, UpdateUserRequest, UserResponse, User};
+use serde_json::json;
+use crate::handlers::audit::{audit, caller_event, changes};
+use crate::handlers::verification::send_verification_email;
+use crate::middleware::AuthUser;
+use crate::policy::{UserAction, UserPolicy};
+use crate::models::{
+    account_throttle_key, AppState, ClientInfo, CreateUserRequest, LoginThrottle, NewAuditEvent, ThrottleScope,
+    UpdateUserRequest, User, UserCursor, UserListQuery, UserPage, UserResponse, ADMIN_PERMISSION, MAX_USER_PAGE_SIZE,
+    USER_PAGE_SIZE,
+};
 use bcrypt;
 
 #[utoipa::path(
@@ -14,6 +23,7 @@ use bcrypt;
 pub async fn create_user(
     user_data: web::Json<CreateUserRequest>,
     state: web::Data<AppState>,
+    client: ClientInfo,
 ) -> Result<HttpResponse> {
     // Hash the password
     let password_hash = match bcrypt::hash(&user_data.password, bcrypt::DEFAULT_COST) {
@@ -29,11 +39,20 @@ pub async fn create_user(
     match User::create(
         conn,
         &user_data.email,
-        &password_hash,
+        Some(&password_hash),
         user_data.first_name.as_deref(),
         user_data.last_name.as_deref(),
     ) {
         Ok(user) => {
+            let event = NewAuditEvent::new("user.create", &client).target("user", &user.id);
+            audit(conn, event.diff(json!({ "email": user.email })));
+            
+            // Ask the new user to confirm their address. The account exists either
+            // way, so a failure here leaves them to ask again through the resend flow.
+            if let Err(e) = send_verification_email(&state, conn, &user) {
+                log::error!("Failed to create verification token for user {}: {e}", user.id);
+            }
+            
             let response = UserResponse::from(user);
             Ok(HttpResponse::Created().json(response))
         }
@@ -50,19 +69,24 @@ pub async fn create_user(
     path = "/api/users/{id}",
     responses(
         (status = 200, description = "User found", body = UserResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Not allowed to access this user"),
         (status = 404, description = "User not found")
-    )
+    ),
+    security(("bearer_auth" = []))
 )]
 #[get("/api/users/{id}")]
 pub async fn get_user(
     path: web::Path<String>,
     state: web::Data<AppState>,
+    auth: AuthUser,
 ) -> Result<HttpResponse> {
     let user_id = path.into_inner();
     
     // Get database connection
     let database = state.database.lock().unwrap();
     let conn = database.get_connection();
+    auth.authorize::<UserPolicy>(conn, UserAction::Read, &user_id)?;
     
     // Find user
     match User::find_by_id(conn, &user_id) {
@@ -81,20 +105,32 @@ pub async fn get_user(
     request_body = UpdateUserRequest,
     responses(
         (status = 200, description = "User updated successfully", body = UserResponse),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Not allowed to access this user"),
         (status = 404, description = "User not found")
-    )
+    ),
+    security(("bearer_auth" = []))
 )]
 #[put("/api/users/{id}")]
 pub async fn update_user(
     path: web::Path<String>,
     user_data: web::Json<UpdateUserRequest>,
     state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
 ) -> Result<HttpResponse> {
     let user_id = path.into_inner();
     
     // Get database connection
     let database = state.database.lock().unwrap();
     let conn = database.get_connection();
+    auth.authorize::<UserPolicy>(conn, UserAction::Update, &user_id)?;
+    
+    let before = match User::find_by_id(conn, &user_id) {
+        Ok(Some(user)) => UserResponse::from(user),
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
     
     // Update user
     match User::update(
@@ -105,6 +141,8 @@ pub async fn update_user(
     ) {
         Ok(Some(user)) => {
             let response = UserResponse::from(user);
+            let event = caller_event(&auth, &client, "user.update").target("user", &user_id);
+            audit(conn, event.diff(changes(&before, &response)));
             Ok(HttpResponse::Ok().json(response))
         }
         Ok(None) => Ok(HttpResponse::NotFound().json("User not found")),
@@ -115,24 +153,82 @@ pub async fn update_user(
 #[utoipa::path(
     get,
     path = "/api/users",
+    params(UserListQuery),
     responses(
-        (status = 200, description = "List of users", body = Vec<UserResponse>)
-    )
+        (status = 200, description = "One page of users", body = UserPage),
+        (status = 400, description = "Invalid filter or cursor"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission")
+    ),
+    security(("bearer_auth" = []))
 )]
 #[get("/api/users")]
 pub async fn list_users(
+    query: web::Query<UserListQuery>,
     state: web::Data<AppState>,
+    auth: AuthUser,
 ) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    
+    let limit = query.limit.unwrap_or(USER_PAGE_SIZE).clamp(1, MAX_USER_PAGE_SIZE);
+    let cursor = match query.cursor.as_deref().map(|cursor| UserCursor::decode(cursor, &query)) {
+        Some(Some(cursor)) => Some(cursor),
+        Some(None) => return Ok(HttpResponse::BadRequest().json("Invalid cursor")),
+        None => None,
+    };
+    
     // Get database connection
     let database = state.database.lock().unwrap();
     let conn = database.get_connection();
     
-    // Find all users
-    match User::find_all(conn) {
-        Ok(users) => {
-            let response: Vec<UserResponse> = users.into_iter().map(UserResponse::from).collect();
-            Ok(HttpResponse::Ok().json(response))
+    let page = User::find_page(conn, &query, cursor.as_ref(), limit).and_then(|(users, next_cursor)| {
+        Ok(UserPage {
+            users: users.into_iter().map(UserResponse::from).collect(),
+            next_cursor: next_cursor.map(|cursor| cursor.encode()),
+            total: if query.include_total { Some(User::count(conn, &query)?) } else { None },
+        })
+    });
+    match page {
+        Ok(page) => Ok(HttpResponse::Ok().json(page)),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/users/{id}/unlock",
+    responses(
+        (status = 204, description = "Failed sign-in attempts cleared and any lockout lifted"),
+        (status = 401, description = "Not authenticated"),
+        (status = 403, description = "Requires the admin permission"),
+        (status = 404, description = "User not found")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/users/{id}/unlock")]
+pub async fn unlock_user(
+    path: web::Path<String>,
+    state: web::Data<AppState>,
+    auth: AuthUser,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    auth.require_permission(ADMIN_PERMISSION)?;
+    let user_id = path.into_inner();
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user = match User::find_by_id(conn, &user_id) {
+        Ok(Some(user)) => user,
+        Ok(None) => return Ok(HttpResponse::NotFound().json("User not found")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match LoginThrottle::clear(conn, ThrottleScope::Account, &account_throttle_key(&user.email)) {
+        Ok(_) => {
+            audit(conn, caller_event(&auth, &client, "user.unlock").target("user", &user.id));
+            Ok(HttpResponse::NoContent().finish())
         }
         Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,93 @@
+use std::collections::HashMap;
+
+use actix_web::{post, web, HttpResponse, Result};
+use chrono::Duration;
+use crate::handlers::audit::audit;
+use crate::mailer::EMAIL_VERIFICATION;
+use crate::middleware::AuthUser;
+use crate::models::{AppState, ClientInfo, NewAuditEvent, OneTimeToken, TokenPurpose, User, UserResponse, VerifyEmailRequest};
+
+const EMAIL_VERIFICATION_TTL_HOURS: i64 = 48;
+
+// Issues a verification token and mails the link to the user's address
+pub fn send_verification_email(state: &AppState, conn: &rusqlite::Connection, user: &User) -> rusqlite::Result<()> {
+    let token = OneTimeToken::issue(
+        conn,
+        &user.id,
+        TokenPurpose::EmailVerification,
+        Duration::hours(EMAIL_VERIFICATION_TTL_HOURS),
+    )?;
+    
+    let message = EMAIL_VERIFICATION.render(
+        &user.email,
+        &HashMap::from([
+            ("link", format!("{}/verify-email?token={token}", state.app_url)),
+            ("ttl_hours", EMAIL_VERIFICATION_TTL_HOURS.to_string()),
+        ]),
+    );
+    if let Err(e) = state.mailer.send(&message) {
+        log::error!("Failed to send verification email: {e}");
+    }
+    
+    Ok(())
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/verify-email",
+    request_body = VerifyEmailRequest,
+    responses(
+        (status = 200, description = "Email verified", body = UserResponse),
+        (status = 400, description = "Invalid or expired token")
+    )
+)]
+#[post("/api/auth/verify-email")]
+pub async fn verify_email(
+    request: web::Json<VerifyEmailRequest>,
+    state: web::Data<AppState>,
+    client: ClientInfo,
+) -> Result<HttpResponse> {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    let user_id = match OneTimeToken::consume(conn, &request.token, TokenPurpose::EmailVerification) {
+        Ok(Some(user_id)) => user_id,
+        Ok(None) => return Ok(HttpResponse::BadRequest().json("Invalid or expired token")),
+        Err(_) => return Ok(HttpResponse::InternalServerError().json("Database error")),
+    };
+    
+    match User::mark_email_verified(conn, &user_id).and_then(|_| User::find_by_id(conn, &user_id)) {
+        Ok(Some(user)) => {
+            audit(conn, NewAuditEvent::new("auth.email_verified", &client).actor(&user.id).target("user", &user.id));
+            Ok(HttpResponse::Ok().json(UserResponse::from(user)))
+        }
+        Ok(None) => Ok(HttpResponse::BadRequest().json("Invalid or expired token")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/auth/verify-email/resend",
+    responses(
+        (status = 202, description = "A new verification link was sent"),
+        (status = 401, description = "Not authenticated"),
+        (status = 409, description = "Email is already verified")
+    ),
+    security(("bearer_auth" = []))
+)]
+#[post("/api/auth/verify-email/resend")]
+pub async fn resend_verification_email(
+    state: web::Data<AppState>,
+    auth: AuthUser,
+) -> Result<HttpResponse> {
+    if auth.user.email_verified_at.is_some() {
+        return Ok(HttpResponse::Conflict().json("Email is already verified"));
+    }
+    
+    let database = state.database.lock().unwrap();
+    match send_verification_email(&state, database.get_connection(), &auth.user) {
+        Ok(()) => Ok(HttpResponse::Accepted().json("Verification email sent")),
+        Err(_) => Ok(HttpResponse::InternalServerError().json("Database error")),
+    }
+}
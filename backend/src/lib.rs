@@ -1,5 +1,9 @@
 pub mod models;
 pub mod handlers;
+pub mod middleware;
+pub mod policy;
+pub mod mailer;
+pub mod webauthn;
 
-pub use models::{Database, AppState, User, UserResponse, CreateUserRequest, UpdateUserRequest, LoginRequest, LoginResponse, GoogleAuthRequest};
+pub use models::{Database, AppState, User, UserResponse, CreateUserRequest, UpdateUserRequest, LoginRequest, LoginResponse, RefreshRequest, GoogleAuthRequest};
 pub use handlers::{hello, users, auth};
\ No newline at end of file
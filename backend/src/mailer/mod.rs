@@ -0,0 +1,72 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+pub mod templates;
+pub mod transports;
+
+pub use templates::*;
+pub use transports::*;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmailMessage {
+    pub to: String,
+    pub subject: String,
+    pub text_body: String,
+    pub html_body: Option<String>,
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    Io(std::io::Error),
+    Message(String),
+    Transport(String),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::Io(e) => write!(f, "I/O error while sending mail: {e}"),
+            MailError::Message(message) => write!(f, "Invalid email message: {message}"),
+            MailError::Transport(message) => write!(f, "Mail transport error: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::Io(e)
+    }
+}
+
+// Outbound email transport. Implementations may block while delivering; wrap slow
+// ones in `BackgroundMailer` so handlers only pay for queueing the message.
+pub trait Mailer: Send + Sync + fmt::Debug {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError>;
+}
+
+// Builds the transport selected by MAIL_TRANSPORT:
+// - `file` writes one .eml file per message into MAIL_DIR
+// - `maildir` delivers into the Maildir at MAIL_DIR
+// - `smtp` relays through SMTP_HOST, see `SmtpConfig::from_env`
+// - `log` writes messages to the application log; the default in development only,
+//   since messages carry working sign-in and reset links
+// Every transport except `log` delivers on a background thread.
+pub fn mailer_from_env(dev: bool) -> Result<Arc<dyn Mailer>, MailError> {
+    let from = std::env::var("MAIL_FROM").unwrap_or_else(|_| "Surjo <no-reply@localhost>".to_string());
+    let dir = || PathBuf::from(std::env::var("MAIL_DIR").unwrap_or_else(|_| "mail".to_string()));
+    
+    let transport: Arc<dyn Mailer> = match std::env::var("MAIL_TRANSPORT").as_deref() {
+        Ok("file") => Arc::new(FileMailer::new(dir(), from)),
+        Ok("maildir") => Arc::new(MaildirMailer::new(dir(), from)),
+        Ok("smtp") => Arc::new(SmtpMailer::new(&SmtpConfig::from_env(), from)?),
+        Ok("log") | Err(_) if dev => return Ok(Arc::new(LogMailer::new(from))),
+        Ok("log") => return Err(MailError::Transport("MAIL_TRANSPORT `log` is only allowed with --dev".to_string())),
+        Err(_) => return Err(MailError::Transport("MAIL_TRANSPORT is not set".to_string())),
+        Ok(other) => return Err(MailError::Transport(format!("Unknown MAIL_TRANSPORT `{other}`"))),
+    };
+    
+    Ok(Arc::new(BackgroundMailer::new(transport)))
+}
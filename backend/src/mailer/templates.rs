@@ -0,0 +1,85 @@
+use std::collections::HashMap;
+
+use crate::mailer::EmailMessage;
+
+// A message with `{{name}}` placeholders in its subject and bodies. Values are
+// HTML-escaped when substituted into the HTML body.
+#[derive(Debug, Clone, Copy)]
+pub struct EmailTemplate {
+    pub subject: &'static str,
+    pub text: &'static str,
+    pub html: Option<&'static str>,
+}
+
+pub const PASSWORD_RESET: EmailTemplate = EmailTemplate {
+    subject: "Reset your password",
+    text: include_str!("../../templates/email/password_reset.txt"),
+    html: Some(include_str!("../../templates/email/password_reset.html")),
+};
+
+pub const EMAIL_VERIFICATION: EmailTemplate = EmailTemplate {
+    subject: "Verify your email address",
+    text: include_str!("../../templates/email/email_verification.txt"),
+    html: Some(include_str!("../../templates/email/email_verification.html")),
+};
+
+pub const MAGIC_LINK: EmailTemplate = EmailTemplate {
+    subject: "Your sign-in link",
+    text: include_str!("../../templates/email/magic_link.txt"),
+    html: Some(include_str!("../../templates/email/magic_link.html")),
+};
+
+impl EmailTemplate {
+    pub fn render(&self, to: &str, vars: &HashMap<&str, String>) -> EmailMessage {
+        EmailMessage {
+            to: to.to_string(),
+            subject: substitute(self.subject, vars, |value| value.to_string()),
+            text_body: substitute(self.text, vars, |value| value.to_string()),
+            html_body: self.html.map(|html| substitute(html, vars, escape_html)),
+        }
+    }
+}
+
+// Replaces every `{{name}}`; unknown names are left in place so mistakes are visible
+fn substitute(template: &str, vars: &HashMap<&str, String>, encode: impl Fn(&str) -> String) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+    
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let after_open = &rest[start + 2..];
+        
+        match after_open.find("}}") {
+            Some(end) => {
+                let name = after_open[..end].trim();
+                match vars.get(name) {
+                    Some(value) => output.push_str(&encode(value)),
+                    None => output.push_str(&rest[start..start + 2 + end + 2]),
+                }
+                rest = &after_open[end + 2..];
+            }
+            None => {
+                output.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    
+    output.push_str(rest);
+    output
+}
+
+fn escape_html(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
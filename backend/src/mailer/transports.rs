@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use chrono::Utc;
+use lettre::message::{header::ContentType, Mailbox, MultiPart, SinglePart};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{Message, SmtpTransport, Transport};
+use uuid::Uuid;
+
+use crate::mailer::{EmailMessage, MailError, Mailer};
+
+// Builds the MIME message, as multipart/alternative when there is an HTML body
+fn build_message(from: &str, message: &EmailMessage) -> Result<Message, MailError> {
+    let from: Mailbox = from.parse().map_err(|e| MailError::Message(format!("invalid sender: {e}")))?;
+    let to: Mailbox = message
+        .to
+        .parse()
+        .map_err(|e| MailError::Message(format!("invalid recipient: {e}")))?;
+    
+    let builder = Message::builder().from(from).to(to).subject(message.subject.clone());
+    let result = match &message.html_body {
+        Some(html) => builder.multipart(MultiPart::alternative_plain_html(
+            message.text_body.clone(),
+            html.clone(),
+        )),
+        None => builder.singlepart(
+            SinglePart::builder()
+                .header(ContentType::TEXT_PLAIN)
+                .body(message.text_body.clone()),
+        ),
+    };
+    
+    result.map_err(|e| MailError::Message(e.to_string()))
+}
+
+// Writes messages to the application log, for development without any mail setup
+#[derive(Debug)]
+pub struct LogMailer {
+    from: String,
+}
+
+impl LogMailer {
+    pub fn new(from: String) -> Self {
+        LogMailer { from }
+    }
+}
+
+impl Mailer for LogMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        log::info!(
+            "Email from {} to {}\nSubject: {}\n\n{}",
+            self.from,
+            message.to,
+            message.subject,
+            message.text_body
+        );
+        Ok(())
+    }
+}
+
+// Stores each message as an .eml file that any mail client can open
+#[derive(Debug)]
+pub struct FileMailer {
+    dir: PathBuf,
+    from: String,
+}
+
+impl FileMailer {
+    pub fn new(dir: PathBuf, from: String) -> Self {
+        FileMailer { dir, from }
+    }
+}
+
+impl Mailer for FileMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        let formatted = build_message(&self.from, message)?.formatted();
+        fs::create_dir_all(&self.dir)?;
+        
+        let path = self.dir.join(format!("{}-{}.eml", Utc::now().format("%Y%m%dT%H%M%S"), Uuid::new_v4()));
+        fs::write(path, formatted)?;
+        Ok(())
+    }
+}
+
+// Delivers into a Maildir: written to `tmp/` first, then renamed into `new/` so
+// readers never see a partial message
+#[derive(Debug)]
+pub struct MaildirMailer {
+    dir: PathBuf,
+    from: String,
+}
+
+impl MaildirMailer {
+    pub fn new(dir: PathBuf, from: String) -> Self {
+        MaildirMailer { dir, from }
+    }
+}
+
+impl Mailer for MaildirMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        let formatted = build_message(&self.from, message)?.formatted();
+        for sub_dir in ["tmp", "new", "cur"] {
+            fs::create_dir_all(self.dir.join(sub_dir))?;
+        }
+        
+        let file_name = format!("{}.{}.surjo", Utc::now().timestamp(), Uuid::new_v4().simple());
+        let tmp_path = self.dir.join("tmp").join(&file_name);
+        fs::write(&tmp_path, formatted)?;
+        fs::rename(tmp_path, self.dir.join("new").join(file_name))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    // `starttls` (default), `tls` for implicit TLS, or `none` for local relays
+    pub tls: String,
+}
+
+impl SmtpConfig {
+    pub fn from_env() -> Self {
+        SmtpConfig {
+            host: std::env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string()),
+            port: std::env::var("SMTP_PORT").ok().and_then(|port| port.parse().ok()),
+            username: std::env::var("SMTP_USERNAME").ok(),
+            password: std::env::var("SMTP_PASSWORD").ok(),
+            tls: std::env::var("SMTP_TLS").unwrap_or_else(|_| "starttls".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: String,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig, from: String) -> Result<Self, MailError> {
+        let mut builder = match config.tls.as_str() {
+            "tls" => SmtpTransport::relay(&config.host),
+            "none" => Ok(SmtpTransport::builder_dangerous(&config.host)),
+            _ => SmtpTransport::starttls_relay(&config.host),
+        }
+        .map_err(|e| MailError::Transport(e.to_string()))?;
+        
+        if let Some(port) = config.port {
+            builder = builder.port(port);
+        }
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        
+        Ok(SmtpMailer {
+            transport: builder.build(),
+            from,
+        })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        let email = build_message(&self.from, message)?;
+        self.transport
+            .send(&email)
+            .map(|_| ())
+            .map_err(|e| MailError::Transport(e.to_string()))
+    }
+}
+
+// Queues messages for a worker thread that hands them to the wrapped transport,
+// so a slow SMTP server never holds up a request. Delivery failures are logged.
+#[derive(Debug)]
+pub struct BackgroundMailer {
+    queue: Mutex<mpsc::Sender<EmailMessage>>,
+}
+
+impl BackgroundMailer {
+    pub fn new(transport: Arc<dyn Mailer>) -> Self {
+        let (sender, receiver) = mpsc::channel::<EmailMessage>();
+        
+        thread::Builder::new()
+            .name("mailer".to_string())
+            .spawn(move || {
+                for message in receiver {
+                    if let Err(e) = transport.send(&message) {
+                        log::error!("Failed to deliver email to {}: {e}", message.to);
+                    }
+                }
+            })
+            .expect("Failed to start mailer thread");
+        
+        BackgroundMailer {
+            queue: Mutex::new(sender),
+        }
+    }
+}
+
+impl Mailer for BackgroundMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        self.queue
+            .lock()
+            .unwrap()
+            .send(message.clone())
+            .map_err(|_| MailError::Transport("mailer thread has stopped".to_string()))
+    }
+}
+
+// Keeps sent messages in memory so tests can inspect them
+#[derive(Debug, Default)]
+pub struct MemoryMailer {
+    messages: Mutex<Vec<EmailMessage>>,
+}
+
+impl MemoryMailer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn messages(&self) -> Vec<EmailMessage> {
+        self.messages.lock().unwrap().clone()
+    }
+}
+
+impl Mailer for MemoryMailer {
+    fn send(&self, message: &EmailMessage) -> Result<(), MailError> {
+        self.messages.lock().unwrap().push(message.clone());
+        Ok(())
+    }
+}
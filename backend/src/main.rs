@@ -2,13 +2,17 @@ use actix_web::{web, App, HttpServer, middleware::Logger};
 use clap::{Parser, Subcommand};
 use std::sync::{Arc, Mutex};
 use dotenvy::dotenv;
-use utoipa::OpenApi;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
 use utoipa_swagger_ui::SwaggerUi;
 
-mod models;
-mod handlers;
-
-use models::{Database, AppState};
+use surjo_backend::{handlers, mailer, models};
+use models::{ApiKey, Database, AppState, AuthPolicy, GoogleConfig, OAuthProviderConfig, Permission, Role, User, WebAuthnConfig, ADMIN_ROLE};
+use models::{validate_jwt_secret, JwtSecretError, DEFAULT_JWT_SECRET};
+use models::{account_throttle_key, KeyAlgorithm, LoginThrottle, SigningKey, ThrottleScope};
+use models::{AuditEvent, AuditEventQuery, NewAuditEvent};
+use chrono::Utc;
+use serde_json::json;
 use handlers::*;
 use handlers::users::list_users;
 
@@ -22,7 +26,12 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Start the web server
-    Serve,
+    Serve {
+        /// Development mode: start even with a missing or weak JWT_SECRET, and log
+        /// outgoing email unless MAIL_TRANSPORT says otherwise
+        #[arg(long)]
+        dev: bool,
+    },
     /// Run database migrations
     Migrate,
     /// Create a new user
@@ -30,22 +39,80 @@ enum Commands {
         /// User email address
         #[arg(short, long)]
         email: String,
-        /// User password
+        /// User password; omit for service users that only sign in with API keys
         #[arg(short, long)]
-        password: String,
+        password: Option<String>,
         /// First name (optional)
         #[arg(long)]
         first_name: Option<String>,
         /// Last name (optional)
         #[arg(long)]
         last_name: Option<String>,
+        /// Mark the email address as already verified
+        #[arg(long)]
+        verified: bool,
     },
-    /// Set user as superadmin
+    /// Set user as superadmin by assigning the admin role
     SetSuperadmin {
         /// User email address
         #[arg(short, long)]
         email: String,
     },
+    /// Create an API key for a user, e.g. a service user for CI
+    CreateApiKey {
+        /// Email address of the key's owner
+        #[arg(short, long)]
+        email: String,
+        /// Name to tell the key apart
+        #[arg(short, long)]
+        name: String,
+        /// Permission the key may use (repeatable); must be held by the user
+        #[arg(long = "scope")]
+        scopes: Vec<String>,
+        /// Days until the key expires (default: never)
+        #[arg(long)]
+        expires_in_days: Option<i64>,
+    },
+    /// Clear failed sign-in attempts and lift any lockout on an account
+    UnlockAccount {
+        /// User email address
+        #[arg(short, long)]
+        email: String,
+    },
+    /// Generate a key pair that signs access tokens once verifiers can have fetched it; older keys keep verifying
+    GenerateSigningKey {
+        /// EdDSA or RS256
+        #[arg(short, long, default_value = "EdDSA")]
+        algorithm: String,
+    },
+    /// Generate a new signing key and retire the others once it signs
+    RotateSigningKeys {
+        /// EdDSA or RS256
+        #[arg(short, long, default_value = "EdDSA")]
+        algorithm: String,
+    },
+    /// List signing keys and whether they are still in use
+    ListSigningKeys,
+    /// Print a random secret suitable for JWT_SECRET
+    GenSecret,
+    /// Print audit events as JSON lines, oldest first
+    ExportAuditLog {
+        /// Only events by this user ID
+        #[arg(long)]
+        actor_id: Option<String>,
+        /// Only events with this action, e.g. user.update
+        #[arg(long)]
+        action: Option<String>,
+        /// Only events about this target ID
+        #[arg(long)]
+        target_id: Option<String>,
+        /// Only events at or after this RFC 3339 time
+        #[arg(long)]
+        since: Option<chrono::DateTime<chrono::Utc>>,
+        /// Only events before this RFC 3339 time
+        #[arg(long)]
+        until: Option<chrono::DateTime<chrono::Utc>>,
+    },
 }
 
 #[derive(OpenApi)]
@@ -56,29 +123,168 @@ enum Commands {
         users::get_user,
         users::update_user,
         users::list_users,
+        users::unlock_user,
+        impersonation::impersonate_user,
+        roles::list_roles,
+        roles::create_role,
+        roles::get_role,
+        roles::update_role,
+        roles::delete_role,
+        roles::list_user_roles,
+        roles::assign_role,
+        roles::unassign_role,
+        permissions::list_permissions,
+        permissions::create_permission,
+        permissions::update_permission,
+        permissions::delete_permission,
+        permissions::get_user_permissions,
+        permissions::grant_user_permission,
+        permissions::revoke_user_permission,
+        groups::list_groups,
+        groups::create_group,
+        groups::get_group,
+        groups::update_group,
+        groups::delete_group,
+        groups::list_group_members,
+        groups::add_group_member,
+        groups::remove_group_member,
+        groups::add_group_manager,
+        groups::remove_group_manager,
+        audit::list_audit_events,
         auth::login,
+        auth::refresh,
+        auth::logout,
         auth::google_auth,
+        oauth::start_oauth,
+        oauth::oauth_callback,
+        identities::list_my_identities,
+        identities::start_identity_link,
+        identities::link_identity,
+        identities::unlink_identity,
+        password::request_password_reset,
+        password::confirm_password_reset,
+        magic_link::request_magic_link,
+        magic_link::verify_magic_link,
+        jwks::jwks,
+        verification::verify_email,
+        verification::resend_verification_email,
+        two_factor::verify_two_factor,
+        two_factor::setup_two_factor,
+        two_factor::confirm_two_factor,
+        two_factor::regenerate_recovery_codes,
+        two_factor::disable_two_factor,
+        passkeys::start_passkey_registration,
+        passkeys::finish_passkey_registration,
+        passkeys::list_my_passkeys,
+        passkeys::rename_my_passkey,
+        passkeys::delete_my_passkey,
+        passkeys::start_passkey_login,
+        passkeys::finish_passkey_login,
+        api_keys::create_api_key,
+        api_keys::list_my_api_keys,
+        api_keys::revoke_my_api_key,
+        sessions::list_my_sessions,
+        sessions::revoke_all_my_sessions,
+        sessions::revoke_my_session,
+        sessions::list_user_sessions,
+        sessions::revoke_user_session,
     ),
     components(
         schemas(
             hello::HelloWorldResponse,
             hello::LoadData,
             models::UserResponse,
+            models::UserPage,
+            models::UserSortField,
+            models::SortOrder,
             models::CreateUserRequest,
             models::UpdateUserRequest,
+            models::RoleResponse,
+            models::CreateRoleRequest,
+            models::UpdateRoleRequest,
+            models::PermissionResponse,
+            models::CreatePermissionRequest,
+            models::UpdatePermissionRequest,
+            models::UserPermissionsResponse,
+            models::GroupResponse,
+            models::CreateGroupRequest,
+            models::UpdateGroupRequest,
+            models::AuditEvent,
+            models::AuditEventPage,
+            models::AuditSource,
             models::LoginRequest,
             models::LoginResponse,
+            models::ImpersonationResponse,
+            models::RefreshRequest,
+            models::SessionResponse,
+            models::PasswordResetRequest,
+            models::PasswordResetConfirmRequest,
+            models::MagicLinkRequest,
+            models::MagicLinkVerifyRequest,
+            models::VerifyEmailRequest,
+            models::TwoFactorChallengeResponse,
+            models::TwoFactorVerifyRequest,
+            models::TwoFactorSetupResponse,
+            models::TwoFactorCodeRequest,
+            models::RecoveryCodesResponse,
+            models::PasskeyResponse,
+            models::RenamePasskeyRequest,
+            models::PasskeyRegistrationOptions,
+            models::RelyingParty,
+            models::PasskeyUser,
+            models::CredentialParameter,
+            models::AuthenticatorSelection,
+            models::CredentialDescriptor,
+            models::PasskeyRegistrationRequest,
+            models::RegistrationCredential,
+            models::AttestationResponse,
+            models::PasskeyAuthenticationOptions,
+            models::PasskeyLoginFinishRequest,
+            models::AssertionResponse,
+            models::CreateApiKeyRequest,
+            models::ApiKeyResponse,
+            models::CreatedApiKeyResponse,
             models::GoogleAuthRequest,
+            models::OAuthStartResponse,
+            models::OAuthCallbackRequest,
+            models::IdentityResponse,
+            models::LinkIdentityRequest,
+            models::JwksResponse,
         )
     ),
+    modifiers(&SecurityAddon),
     tags(
         (name = "hello", description = "Hello World API"),
         (name = "users", description = "User management API"),
-        (name = "auth", description = "Authentication API")
+        (name = "roles", description = "Role management API"),
+        (name = "permissions", description = "Permission management API"),
+        (name = "groups", description = "Group management API"),
+        (name = "audit", description = "Audit log API"),
+        (name = "auth", description = "Authentication API"),
+        (name = "sessions", description = "Session management API")
     )
 )]
 struct ApiDoc;
 
+// Registers the bearer token scheme referenced by protected paths
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer_auth",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .description(Some("Access token from login, or an API key"))
+                    .build(),
+            ),
+        );
+    }
+}
+
 #[tokio::main]
 async fn main() {
     dotenv().ok();
@@ -87,38 +293,109 @@ async fn main() {
     let cli = Cli::parse();
     
     match &cli.command {
-        Some(Commands::Serve) => {
+        Some(Commands::Serve { dev }) => {
+            let jwt_secret = match jwt_secret_from_env(*dev) {
+                Ok(jwt_secret) => jwt_secret,
+                Err(e) => {
+                    eprintln!("Refusing to start: {e}");
+                    eprintln!("Set JWT_SECRET to the output of `gen-secret`, or pass --dev for local development");
+                    std::process::exit(1);
+                }
+            };
+            let mailer = match mailer::mailer_from_env(*dev) {
+                Ok(mailer) => mailer,
+                Err(e) => {
+                    eprintln!("Refusing to start: {e}");
+                    eprintln!("Set MAIL_TRANSPORT to smtp, file or maildir, or pass --dev to log messages instead");
+                    std::process::exit(1);
+                }
+            };
             println!("Starting web server...");
-            start_server().await.unwrap();
+            start_server(jwt_secret, mailer).await.unwrap();
         }
         Some(Commands::Migrate) => {
             println!("Running database migrations...");
             run_migrations().await.unwrap();
         }
-        Some(Commands::CreateUser { email, password, first_name, last_name }) => {
+        Some(Commands::CreateUser { email, password, first_name, last_name, verified }) => {
             println!("Creating user: {email}");
-            create_user_cli(email, password, first_name.as_deref(), last_name.as_deref()).await.unwrap();
+            create_user_cli(email, password.as_deref(), first_name.as_deref(), last_name.as_deref(), *verified).await.unwrap();
         }
         Some(Commands::SetSuperadmin { email }) => {
             println!("Setting {email} as superadmin");
             set_superadmin_cli(email).await.unwrap();
         }
+        Some(Commands::CreateApiKey { email, name, scopes, expires_in_days }) => {
+            println!("Creating API key for {email}");
+            create_api_key_cli(email, name, scopes, *expires_in_days).await.unwrap();
+        }
+        Some(Commands::UnlockAccount { email }) => {
+            println!("Unlocking {email}");
+            unlock_account_cli(email).await.unwrap();
+        }
+        Some(Commands::GenerateSigningKey { algorithm }) => {
+            println!("Generating {algorithm} signing key");
+            generate_signing_key_cli(algorithm, false).await.unwrap();
+        }
+        Some(Commands::RotateSigningKeys { algorithm }) => {
+            println!("Rotating signing keys");
+            generate_signing_key_cli(algorithm, true).await.unwrap();
+        }
+        Some(Commands::ListSigningKeys) => {
+            list_signing_keys_cli().await.unwrap();
+        }
+        Some(Commands::GenSecret) => {
+            println!("{}", models::generate_token());
+        }
+        Some(Commands::ExportAuditLog { actor_id, action, target_id, since, until }) => {
+            let query = AuditEventQuery {
+                actor_id: actor_id.clone(),
+                action: action.clone(),
+                target_id: target_id.clone(),
+                since: *since,
+                until: *until,
+                ..Default::default()
+            };
+            export_audit_log_cli(&query).await.unwrap();
+        }
         None => {
             println!("Hello World");
         }
     }
 }
 
-async fn start_server() -> std::io::Result<()> {
+// Production refuses a missing, default or short secret; --dev only warns
+fn jwt_secret_from_env(dev: bool) -> Result<String, JwtSecretError> {
+    let jwt_secret = std::env::var("JWT_SECRET").ok().filter(|secret| !secret.is_empty());
+    match validate_jwt_secret(jwt_secret.as_deref()) {
+        Ok(()) => Ok(jwt_secret.unwrap_or_default()),
+        Err(e) if dev => {
+            eprintln!("{}", "*".repeat(72));
+            eprintln!("WARNING: {e}.");
+            eprintln!("Access tokens signed with it can be forged. Never run like this in production.");
+            eprintln!("{}", "*".repeat(72));
+            Ok(jwt_secret.unwrap_or_else(|| DEFAULT_JWT_SECRET.to_string()))
+        }
+        Err(e) => Err(e),
+    }
+}
+
+async fn start_server(jwt_secret: String, mailer: Arc<dyn mailer::Mailer>) -> std::io::Result<()> {
     let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
-    let jwt_secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "your-secret-key".to_string());
     
     let mut database = Database::new(&database_url).expect("Failed to connect to database");
     database.run_migrations().expect("Failed to run migrations");
     
+    let app_url = std::env::var("APP_URL").unwrap_or_else(|_| "http://localhost:3000".to_string());
     let app_state = AppState {
         database: Arc::new(Mutex::new(database)),
         jwt_secret,
+        google: GoogleConfig::from_env(),
+        oauth_providers: OAuthProviderConfig::all_from_env(&app_url),
+        policy: AuthPolicy::from_env(),
+        webauthn: WebAuthnConfig::from_env(&app_url),
+        mailer,
+        app_url,
     };
     
     HttpServer::new(move || {
@@ -130,8 +407,71 @@ async fn start_server() -> std::io::Result<()> {
             .service(get_user)
             .service(update_user)
             .service(list_users)
+            .service(unlock_user)
+            .service(impersonate_user)
+            .service(list_roles)
+            .service(create_role)
+            .service(get_role)
+            .service(update_role)
+            .service(delete_role)
+            .service(list_user_roles)
+            .service(assign_role)
+            .service(unassign_role)
+            .service(list_permissions)
+            .service(create_permission)
+            .service(update_permission)
+            .service(delete_permission)
+            .service(get_user_permissions)
+            .service(grant_user_permission)
+            .service(revoke_user_permission)
+            .service(list_groups)
+            .service(create_group)
+            .service(get_group)
+            .service(update_group)
+            .service(delete_group)
+            .service(list_group_members)
+            .service(add_group_member)
+            .service(remove_group_member)
+            .service(add_group_manager)
+            .service(remove_group_manager)
+            .service(list_audit_events)
             .service(login)
+            .service(refresh)
+            .service(logout)
             .service(google_auth)
+            .service(start_oauth)
+            .service(oauth_callback)
+            .service(list_my_identities)
+            .service(start_identity_link)
+            .service(link_identity)
+            .service(unlink_identity)
+            .service(request_password_reset)
+            .service(confirm_password_reset)
+            .service(request_magic_link)
+            .service(verify_magic_link)
+            .service(jwks)
+            .service(verify_email)
+            .service(resend_verification_email)
+            .service(verify_two_factor)
+            .service(setup_two_factor)
+            .service(confirm_two_factor)
+            .service(regenerate_recovery_codes)
+            .service(disable_two_factor)
+            .service(start_passkey_registration)
+            .service(finish_passkey_registration)
+            .service(list_my_passkeys)
+            .service(rename_my_passkey)
+            .service(delete_my_passkey)
+            .service(start_passkey_login)
+            .service(finish_passkey_login)
+            .service(create_api_key)
+            .service(list_my_api_keys)
+            .service(revoke_my_api_key)
+            .service(list_my_sessions)
+            .service(revoke_all_my_sessions)
+            .service(revoke_my_session)
+            .service(list_user_sessions)
+            .service(revoke_user_session)
             .service(
                 SwaggerUi::new("/swagger-ui/{_:.*}")
                     .url("/api-docs/openapi.json", ApiDoc::openapi()),
@@ -152,25 +492,27 @@ async fn run_migrations() -> Result<(), Box<dyn std::error::Error>> {
 
 async fn create_user_cli(
     email: &str,
-    password: &str,
+    password: Option<&str>,
     first_name: Option<&str>,
     last_name: Option<&str>,
+    verified: bool,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
     let mut database = Database::new(&database_url)?;
     database.run_migrations()?;
     
     // Hash the password
-    let password_hash = bcrypt::hash(password, bcrypt::DEFAULT_COST)?;
+    let password_hash = password.map(|password| bcrypt::hash(password, bcrypt::DEFAULT_COST)).transpose()?;
     
     // Generate user ID
     let user_id = uuid::Uuid::new_v4().to_string();
     
     // Insert user into database
     let conn = database.get_connection();
+    let now = chrono::Utc::now().to_rfc3339();
     conn.execute(
-        "INSERT INTO users (id, email, password_hash, first_name, last_name, is_active, created_at, updated_at) 
-         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+        "INSERT INTO users (id, email, password_hash, first_name, last_name, is_active, created_at, updated_at, email_verified_at) 
+         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
         rusqlite::params![
             user_id,
             email,
@@ -178,10 +520,13 @@ async fn create_user_cli(
             first_name,
             last_name,
             true,
-            chrono::Utc::now().to_rfc3339(),
-            chrono::Utc::now().to_rfc3339(),
+            now,
+            now,
+            verified.then_some(&now),
         ],
     )?;
+    let event = NewAuditEvent::cli("user.create").target("user", &user_id);
+    AuditEvent::record(conn, event.diff(json!({ "email": email })))?;
     
     println!("User created successfully!");
     println!("User ID: {user_id}");
@@ -209,31 +554,142 @@ async fn set_superadmin_cli(email: &str) -> Result<(), Box<dyn std::error::Error
         row.get(0)
     })?;
     
-    // Get admin permission ID
-    let mut stmt = conn.prepare("SELECT id FROM permissions WHERE name = 'admin'")?;
-    let admin_permission_id: String = stmt.query_row([], |row| {
-        row.get(0)
-    })?;
+    // Assign the admin role (no-op if already assigned)
+    let role = Role::find_by_name(conn, ADMIN_ROLE)?.ok_or("The admin role is missing")?;
+    if Role::assign(conn, &user_id, &role.id)? {
+        let event = NewAuditEvent::cli("role.assign").target("user", &user_id);
+        AuditEvent::record(conn, event.diff(json!({ "role": role.name })))?;
+        println!("Successfully granted admin permissions to {email}");
+    } else {
+        println!("User {email} already has admin permissions");
+    }
     
-    // Add admin permission to user (ignore if already exists)
-    let permission_id = uuid::Uuid::new_v4().to_string();
-    match conn.execute(
-        "INSERT INTO user_permissions (id, user_id, permission_id, granted_at) 
-         VALUES (?1, ?2, ?3, ?4)",
-        rusqlite::params![
-            permission_id,
-            user_id,
-            admin_permission_id,
-            chrono::Utc::now().to_rfc3339(),
-        ],
-    ) {
-        Ok(_) => {
-            println!("Successfully granted admin permissions to {email}");
-        }
-        Err(rusqlite::Error::SqliteFailure(err, _)) if err.code == rusqlite::ErrorCode::ConstraintViolation => {
-            println!("User {email} already has admin permissions");
-        }
-        Err(e) => return Err(e.into()),
+    Ok(())
+}
+
+async fn create_api_key_cli(
+    email: &str,
+    name: &str,
+    scopes: &[String],
+    expires_in_days: Option<i64>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
+    let mut database = Database::new(&database_url)?;
+    database.run_migrations()?;
+    
+    let conn = database.get_connection();
+    
+    let user = User::find_by_email(conn, email)?.ok_or_else(|| format!("No user with email {email}"))?;
+    
+    // Keys never carry more than their owner holds
+    let permissions = Permission::names_for_user(conn, &user.id)?;
+    if let Some(scope) = scopes.iter().find(|scope| !permissions.contains(*scope)) {
+        return Err(format!("User {email} does not have the {scope} permission").into());
+    }
+    
+    let ttl = expires_in_days.map(chrono::Duration::days);
+    let (api_key, key) = ApiKey::create(conn, &user.id, name, scopes, ttl)?;
+    let event = NewAuditEvent::cli("auth.api_key_created").target("user", &user.id);
+    AuditEvent::record(conn, event.diff(json!({ "api_key_id": api_key.id, "name": name, "scopes": scopes })))?;
+    
+    println!("API key created successfully!");
+    println!("Key ID: {}", api_key.id);
+    if let Some(expires_at) = api_key.expires_at {
+        println!("Expires: {}", expires_at.to_rfc3339());
+    }
+    println!("Key (shown only once): {key}");
+    
+    Ok(())
+}
+
+async fn unlock_account_cli(email: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
+    let mut database = Database::new(&database_url)?;
+    database.run_migrations()?;
+    
+    let conn = database.get_connection();
+    
+    // Throttling is keyed by address, so this works even for unknown emails
+    if LoginThrottle::clear(conn, ThrottleScope::Account, &account_throttle_key(email))? {
+        AuditEvent::record(conn, NewAuditEvent::cli("user.unlock").diff(json!({ "email": email })))?;
+        println!("Cleared failed sign-in attempts for {email}");
+    } else {
+        println!("No failed sign-in attempts recorded for {email}");
+    }
+    
+    Ok(())
+}
+
+async fn generate_signing_key_cli(algorithm: &str, rotate: bool) -> Result<(), Box<dyn std::error::Error>> {
+    let algorithm = KeyAlgorithm::parse(algorithm).ok_or_else(|| format!("Unsupported algorithm {algorithm}; use EdDSA or RS256"))?;
+    
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
+    let mut database = Database::new(&database_url)?;
+    database.run_migrations()?;
+    
+    let conn = database.get_connection();
+    
+    let key = SigningKey::generate(conn, algorithm)?;
+    let event = NewAuditEvent::cli("signing_key.generate").target("signing_key", &key.kid);
+    AuditEvent::record(conn, event.diff(json!({ "algorithm": algorithm.as_str() })))?;
+    
+    // Retired keys still verify for as long as any access token lives, so rotating
+    // does not sign anyone out; keys retired before that are deleted
+    if rotate {
+        let retired = SigningKey::retire_all_except(conn, &key.kid)?;
+        AuditEvent::record(conn, NewAuditEvent::cli("signing_key.rotate").target("signing_key", &key.kid))?;
+        println!("Retiring {retired} older key(s) once the new key signs");
+    }
+    
+    // Other keys keep signing until verifiers have had time to fetch the new one
+    if SigningKey::current(conn)?.is_some_and(|current| current.kid == key.kid) {
+        println!("Signing key {} ({}) now signs access tokens", key.kid, algorithm.as_str());
+    } else {
+        println!("Signing key {} ({}) is published and signs access tokens from {}", key.kid, algorithm.as_str(), key.signs_from().to_rfc3339());
+    }
+    
+    Ok(())
+}
+
+async fn list_signing_keys_cli() -> Result<(), Box<dyn std::error::Error>> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
+    let mut database = Database::new(&database_url)?;
+    database.run_migrations()?;
+    
+    let conn = database.get_connection();
+    
+    let now = Utc::now();
+    let current = SigningKey::current(conn)?.map(|key| key.kid);
+    let keys = SigningKey::find_all(conn)?;
+    if keys.is_empty() {
+        println!("No signing keys; access tokens are signed with JWT_SECRET");
+    }
+    for key in keys {
+        let status = match key.retired_at {
+            Some(retired_at) if retired_at <= now => format!("retired {}", retired_at.to_rfc3339()),
+            Some(retired_at) if current.as_deref() == Some(key.kid.as_str()) => {
+                format!("signing until {}", retired_at.to_rfc3339())
+            }
+            Some(retired_at) => format!("verifying, retires {}", retired_at.to_rfc3339()),
+            None if current.as_deref() == Some(key.kid.as_str()) => "signing".to_string(),
+            None if key.signs_from() > now => format!("published, signs from {}", key.signs_from().to_rfc3339()),
+            None => "verifying".to_string(),
+        };
+        println!("{}  {:<5}  created {}  {status}", key.kid, key.algorithm.as_str(), key.created_at.to_rfc3339());
+    }
+    
+    Ok(())
+}
+
+async fn export_audit_log_cli(query: &AuditEventQuery) -> Result<(), Box<dyn std::error::Error>> {
+    let database_url = std::env::var("DATABASE_URL").unwrap_or_else(|_| "surjo.db".to_string());
+    let mut database = Database::new(&database_url)?;
+    database.run_migrations()?;
+    
+    let conn = database.get_connection();
+    
+    for event in AuditEvent::find(conn, query, None)?.iter().rev() {
+        println!("{}", serde_json::to_string(event)?);
     }
     
     Ok(())
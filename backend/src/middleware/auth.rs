@@ -0,0 +1,236 @@
+use std::collections::HashSet;
+use std::future::{ready, Ready};
+
+use actix_web::{
+    body::MessageBody,
+    dev::{Payload, ServiceRequest, ServiceResponse},
+    error::InternalError,
+    http::header,
+    middleware::Next,
+    web, Error, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use rusqlite::Connection;
+use crate::models::{ApiKey, AppState, Claims, Permission, Session, TokenError, TwoFactor, User, ADMIN_PERMISSION, API_KEY_PREFIX};
+use crate::policy::{Actor, Policy};
+
+// The authenticated caller, resolved from an `Authorization: Bearer` header holding
+// either an access token or an API key. Extraction is cached in the request
+// extensions, so handlers behind `require_auth` and repeated extractors only
+// validate the credential once.
+#[derive(Debug, Clone)]
+pub struct AuthUser {
+    pub user: User,
+    // Access token claims; None when the caller used an API key
+    pub claims: Option<Claims>,
+    // Set when the caller used an API key
+    pub api_key_id: Option<String>,
+    pub permissions: HashSet<String>,
+    // Set when `admin` was withheld because the account has no second factor
+    pub admin_requires_two_factor: bool,
+}
+
+impl AuthUser {
+    pub fn has_permission(&self, permission: &str) -> bool {
+        self.permissions.contains(permission)
+    }
+    
+    pub fn is_admin(&self) -> bool {
+        self.has_permission(ADMIN_PERMISSION)
+    }
+    
+    pub fn require_permission(&self, permission: &str) -> Result<(), Error> {
+        if self.has_permission(permission) {
+            Ok(())
+        } else if permission == ADMIN_PERMISSION && self.admin_requires_two_factor {
+            Err(forbidden("Two-factor authentication is required for admin access"))
+        } else {
+            Err(forbidden("Insufficient permissions"))
+        }
+    }
+    
+    pub fn session_id(&self) -> Option<&str> {
+        self.claims.as_ref().map(|claims| claims.sid.as_str())
+    }
+    
+    // Returns the session id, refusing callers that authenticated with an API key.
+    // Used for account changes a leaked key must not be able to make.
+    pub fn require_session(&self) -> Result<&str, Error> {
+        self.session_id()
+            .ok_or_else(|| forbidden("This action requires a signed-in session"))
+    }
+    
+    // The admin behind an impersonation token
+    pub fn impersonator_id(&self) -> Option<&str> {
+        self.claims.as_ref()?.act.as_ref().map(|actor| actor.sub.as_str())
+    }
+    
+    // Refuses impersonation tokens. Used for changes to how the account signs in,
+    // which only the user themselves may make.
+    pub fn require_not_impersonating(&self) -> Result<(), Error> {
+        match self.impersonator_id() {
+            Some(_) => Err(forbidden("Not allowed while impersonating")),
+            None => Ok(()),
+        }
+    }
+    
+    // Runs the policy for `action` on the resource with this id, answering a denial
+    // the same way `require_permission` does
+    pub fn authorize<P: Policy>(&self, conn: &Connection, action: P::Action, resource_id: &str) -> Result<(), Error> {
+        let database_error = |_| error_response(HttpResponse::InternalServerError(), "Database error");
+        let actor = Actor::load(conn, &self.user.id, self.permissions.clone()).map_err(database_error)?;
+        let resource = P::load(conn, resource_id).map_err(database_error)?;
+        
+        if P::can(&actor, action, &resource) {
+            Ok(())
+        } else if self.admin_requires_two_factor {
+            Err(forbidden("Two-factor authentication is required for admin access"))
+        } else {
+            Err(forbidden("Insufficient permissions"))
+        }
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+    
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        if let Some(auth_user) = req.extensions().get::<AuthUser>() {
+            return ready(Ok(auth_user.clone()));
+        }
+        
+        let result = authenticate(req);
+        if let Ok(auth_user) = &result {
+            req.extensions_mut().insert(auth_user.clone());
+        }
+        ready(result)
+    }
+}
+
+fn authenticate(req: &HttpRequest) -> Result<AuthUser, Error> {
+    let state = req
+        .app_data::<web::Data<AppState>>()
+        .ok_or_else(|| error_response(HttpResponse::InternalServerError(), "Application state missing"))?;
+    
+    let token = bearer_token(req).ok_or_else(|| unauthorized("Missing bearer token"))?;
+    
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    let database_error = |_| error_response(HttpResponse::InternalServerError(), "Database error");
+    
+    // Signature and `exp` are both checked by the decoder
+    let claims = if token.starts_with(API_KEY_PREFIX) {
+        None
+    } else {
+        match Claims::decode(token, conn, &state.jwt_secret) {
+            Ok(claims) => Some(claims),
+            Err(TokenError::Database(_)) => {
+                return Err(error_response(HttpResponse::InternalServerError(), "Database error"));
+            }
+            Err(_) => return Err(unauthorized("Invalid or expired token")),
+        }
+    };
+    
+    let (user_id, api_key_id) = match &claims {
+        Some(claims) => {
+            // The token is only good while its session exists, which is what makes logout real
+            match Session::find_active(conn, &claims.sid).map_err(database_error)? {
+                Some(session) if session.user_id == claims.sub => {}
+                _ => return Err(unauthorized("Session expired or revoked")),
+            }
+            Session::touch(conn, &claims.sid).map_err(database_error)?;
+            
+            // Impersonation ends as soon as the admin loses access
+            if let Some(actor) = &claims.act {
+                if !is_active_admin(state, conn, &actor.sub).map_err(database_error)? {
+                    return Err(unauthorized("Impersonation is no longer allowed"));
+                }
+                log::info!(
+                    "Admin {} impersonating user {}: {} {}",
+                    actor.sub,
+                    claims.sub,
+                    req.method(),
+                    req.path()
+                );
+            }
+            (claims.sub.clone(), None)
+        }
+        None => {
+            let api_key = ApiKey::find_active_by_key(conn, token)
+                .map_err(database_error)?
+                .ok_or_else(|| unauthorized("Invalid or expired API key"))?;
+            ApiKey::touch(conn, &api_key.id).map_err(database_error)?;
+            (api_key.user_id, Some(api_key.id))
+        }
+    };
+    
+    let user = match User::find_by_id(conn, &user_id).map_err(database_error)? {
+        Some(user) => user,
+        None => return Err(unauthorized("Invalid or expired token")),
+    };
+    
+    if !user.is_active {
+        return Err(unauthorized("Account is disabled"));
+    }
+    
+    let mut permissions = Permission::names_for_user(conn, &user.id).map_err(database_error)?;
+    
+    // A key only carries the scopes it was created with, and loses any its owner
+    // has since lost
+    if let Some(api_key_id) = &api_key_id {
+        let scopes = ApiKey::scopes(conn, api_key_id).map_err(database_error)?;
+        permissions.retain(|permission| scopes.contains(permission));
+    }
+    
+    let mut admin_requires_two_factor = false;
+    if state.policy.require_admin_2fa
+        && permissions.contains(ADMIN_PERMISSION)
+        && !TwoFactor::is_enabled(conn, &user.id).map_err(database_error)?
+    {
+        permissions.remove(ADMIN_PERMISSION);
+        admin_requires_two_factor = true;
+    }
+    
+    Ok(AuthUser { user, claims, api_key_id, permissions, admin_requires_two_factor })
+}
+
+fn is_active_admin(state: &AppState, conn: &rusqlite::Connection, user_id: &str) -> rusqlite::Result<bool> {
+    if !User::find_by_id(conn, user_id)?.is_some_and(|user| user.is_active) {
+        return Ok(false);
+    }
+    if !Permission::names_for_user(conn, user_id)?.contains(ADMIN_PERMISSION) {
+        return Ok(false);
+    }
+    Ok(!state.policy.require_admin_2fa || TwoFactor::is_enabled(conn, user_id)?)
+}
+
+fn bearer_token(req: &HttpRequest) -> Option<&str> {
+    req.headers()
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+fn unauthorized(message: &'static str) -> Error {
+    error_response(HttpResponse::Unauthorized(), message)
+}
+
+fn forbidden(message: &'static str) -> Error {
+    error_response(HttpResponse::Forbidden(), message)
+}
+
+pub(crate) fn error_response(mut builder: actix_web::HttpResponseBuilder, message: &'static str) -> Error {
+    InternalError::from_response(message, builder.json(message)).into()
+}
+
+// Middleware for `web::scope(..).wrap(from_fn(require_auth))` that rejects every
+// request in the scope unless it carries a valid access token
+pub async fn require_auth(
+    mut req: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, Error> {
+    req.extract::<AuthUser>().await?;
+    next.call(req).await
+}
@@ -0,0 +1,21 @@
+use std::future::{ready, Ready};
+
+use actix_web::{dev::Payload, http::header, Error, FromRequest, HttpRequest};
+use crate::models::ClientInfo;
+
+// Uses the socket peer address rather than forwarding headers, which clients can forge
+impl FromRequest for ClientInfo {
+    type Error = Error;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _payload: &mut Payload) -> Self::Future {
+        ready(Ok(ClientInfo {
+            user_agent: req
+                .headers()
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_string),
+            ip_address: req.peer_addr().map(|addr| addr.ip().to_string()),
+        }))
+    }
+}
@@ -0,0 +1,6 @@
+pub mod auth;
+pub mod client;
+pub mod permissions;
+
+pub use auth::*;
+pub use permissions::*;
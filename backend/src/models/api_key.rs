@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::{generate_token, hash_token};
+
+// Marks a bearer token as an API key rather than a JWT
+pub const API_KEY_PREFIX: &str = "surjo_";
+// Characters of the key kept in plain text for display
+const DISPLAY_PREFIX_LENGTH: usize = API_KEY_PREFIX.len() + 8;
+
+#[derive(Debug, Clone)]
+pub struct ApiKey {
+    pub id: String,
+    pub user_id: String,
+    pub name: String,
+    pub prefix: String,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateApiKeyRequest {
+    pub name: String,
+    /// Permissions the key may use; each must be held by the caller
+    #[serde(default)]
+    pub scopes: Vec<String>,
+    /// Days until the key stops working; omit for a key that does not expire
+    pub expires_in_days: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ApiKeyResponse {
+    pub id: String,
+    pub name: String,
+    /// Start of the key, to tell keys apart
+    pub prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl ApiKeyResponse {
+    pub fn new(api_key: ApiKey, scopes: HashSet<String>) -> Self {
+        let mut scopes: Vec<String> = scopes.into_iter().collect();
+        scopes.sort();
+        ApiKeyResponse {
+            id: api_key.id,
+            name: api_key.name,
+            prefix: api_key.prefix,
+            scopes,
+            expires_at: api_key.expires_at,
+            last_used_at: api_key.last_used_at,
+            created_at: api_key.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatedApiKeyResponse {
+    /// The full key, shown only once
+    pub key: String,
+    pub api_key: ApiKeyResponse,
+}
+
+const API_KEY_COLUMNS: &str = "id, user_id, name, prefix, expires_at, last_used_at, created_at";
+
+fn parse_optional_timestamp(row: &rusqlite::Row, index: usize, column: &str) -> SqliteResult<Option<DateTime<Utc>>> {
+    match row.get::<_, Option<String>>(index)? {
+        Some(_) => Ok(Some(parse_timestamp(row, index, column)?)),
+        None => Ok(None),
+    }
+}
+
+impl ApiKey {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(ApiKey {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            name: row.get(2)?,
+            prefix: row.get(3)?,
+            expires_at: parse_optional_timestamp(row, 4, "expires_at")?,
+            last_used_at: parse_optional_timestamp(row, 5, "last_used_at")?,
+            created_at: parse_timestamp(row, 6, "created_at")?,
+        })
+    }
+    
+    // Stores a new key with the given scopes and returns it with its plaintext value.
+    // Scopes are permission names and must exist; checking them against the owner's
+    // permissions is up to the caller.
+    pub fn create(
+        conn: &Connection,
+        user_id: &str,
+        name: &str,
+        scopes: &[String],
+        ttl: Option<Duration>,
+    ) -> SqliteResult<(Self, String)> {
+        let key = format!("{API_KEY_PREFIX}{}", generate_token());
+        let now = Utc::now();
+        let api_key = ApiKey {
+            id: Uuid::new_v4().to_string(),
+            user_id: user_id.to_string(),
+            name: name.to_string(),
+            prefix: key[..DISPLAY_PREFIX_LENGTH].to_string(),
+            expires_at: ttl.map(|ttl| now + ttl),
+            last_used_at: None,
+            created_at: now,
+        };
+        
+        conn.execute(
+            "INSERT INTO api_keys (id, user_id, name, prefix, key_hash, expires_at, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                api_key.id,
+                user_id,
+                name,
+                api_key.prefix,
+                hash_token(&key),
+                api_key.expires_at.map(|expires_at| expires_at.to_rfc3339()),
+                now.to_rfc3339(),
+            ],
+        )?;
+        for scope in scopes {
+            conn.execute(
+                "INSERT OR IGNORE INTO api_key_permissions (api_key_id, permission_id) 
+                 SELECT ?1, id FROM permissions WHERE name = ?2",
+                [&api_key.id, scope],
+            )?;
+        }
+        
+        Ok((api_key, key))
+    }
+    
+    // Looks up a presented key, returning it only if it has not expired
+    pub fn find_active_by_key(conn: &Connection, key: &str) -> SqliteResult<Option<Self>> {
+        let api_key = conn
+            .query_row(
+                &format!("SELECT {API_KEY_COLUMNS} FROM api_keys WHERE key_hash = ?1"),
+                [hash_token(key)],
+                Self::from_row,
+            )
+            .optional()?;
+        
+        Ok(api_key.filter(|api_key| api_key.expires_at.is_none_or(|expires_at| expires_at > Utc::now())))
+    }
+    
+    pub fn find_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {API_KEY_COLUMNS} FROM api_keys WHERE user_id = ?1 ORDER BY created_at"
+        ))?;
+        
+        let api_keys = stmt.query_map([user_id], Self::from_row)?;
+        api_keys.collect()
+    }
+    
+    pub fn scopes(conn: &Connection, api_key_id: &str) -> SqliteResult<HashSet<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.name FROM permissions p 
+             JOIN api_key_permissions akp ON akp.permission_id = p.id 
+             WHERE akp.api_key_id = ?1"
+        )?;
+        
+        let names = stmt.query_map([api_key_id], |row| row.get(0))?;
+        names.collect()
+    }
+    
+    pub fn touch(conn: &Connection, api_key_id: &str) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE api_keys SET last_used_at = ?1 WHERE id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), api_key_id],
+        )?;
+        Ok(())
+    }
+    
+    // Revokes the key if it belongs to the given user
+    pub fn delete_for_user(conn: &Connection, user_id: &str, api_key_id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM api_keys WHERE id = ?1 AND user_id = ?2",
+            [api_key_id, user_id],
+        )?;
+        if rows_affected > 0 {
+            conn.execute("DELETE FROM api_key_permissions WHERE api_key_id = ?1", [api_key_id])?;
+        }
+        Ok(rows_affected > 0)
+    }
+    
+    pub fn delete_all_for_user(conn: &Connection, user_id: &str) -> SqliteResult<usize> {
+        conn.execute(
+            "DELETE FROM api_key_permissions WHERE api_key_id IN (SELECT id FROM api_keys WHERE user_id = ?1)",
+            [user_id],
+        )?;
+        conn.execute("DELETE FROM api_keys WHERE user_id = ?1", [user_id])
+    }
+}
@@ -0,0 +1,225 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::ClientInfo;
+
+pub const AUDIT_PAGE_SIZE: i64 = 50;
+pub const MAX_AUDIT_PAGE_SIZE: i64 = 200;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditSource {
+    #[default]
+    Api,
+    Cli,
+}
+
+impl AuditSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AuditSource::Api => "api",
+            AuditSource::Cli => "cli",
+        }
+    }
+    
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "api" => Some(AuditSource::Api),
+            "cli" => Some(AuditSource::Cli),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct AuditEvent {
+    pub id: String,
+    pub source: AuditSource,
+    /// The user who acted; absent for anonymous callers and the CLI
+    pub actor_id: Option<String>,
+    /// The admin behind an impersonation token
+    pub impersonator_id: Option<String>,
+    /// What happened, e.g. `user.update` or `auth.login`
+    pub action: String,
+    pub target_type: Option<String>,
+    pub target_id: Option<String>,
+    pub ip_address: Option<String>,
+    pub user_agent: Option<String>,
+    /// Changed fields as `{"field": {"from": .., "to": ..}}`, or other details
+    #[schema(value_type = Option<Object>)]
+    pub diff: Option<Value>,
+    pub created_at: DateTime<Utc>,
+}
+
+// An event about to be recorded
+#[derive(Debug, Default)]
+pub struct NewAuditEvent {
+    pub source: AuditSource,
+    pub actor_id: Option<String>,
+    pub impersonator_id: Option<String>,
+    pub action: &'static str,
+    pub target_type: Option<&'static str>,
+    pub target_id: Option<String>,
+    pub client: ClientInfo,
+    pub diff: Option<Value>,
+}
+
+impl NewAuditEvent {
+    pub fn new(action: &'static str, client: &ClientInfo) -> Self {
+        NewAuditEvent { action, client: client.clone(), ..Default::default() }
+    }
+    
+    pub fn cli(action: &'static str) -> Self {
+        NewAuditEvent { source: AuditSource::Cli, action, ..Default::default() }
+    }
+    
+    pub fn actor(mut self, actor_id: &str) -> Self {
+        self.actor_id = Some(actor_id.to_string());
+        self
+    }
+    
+    pub fn target(mut self, target_type: &'static str, target_id: &str) -> Self {
+        self.target_type = Some(target_type);
+        self.target_id = Some(target_id.to_string());
+        self
+    }
+    
+    pub fn diff(mut self, diff: Value) -> Self {
+        self.diff = Some(diff);
+        self
+    }
+}
+
+// Filters shared by the admin endpoint and the CLI export
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditEventQuery {
+    pub actor_id: Option<String>,
+    pub action: Option<String>,
+    pub target_id: Option<String>,
+    /// Only events at or after this time
+    pub since: Option<DateTime<Utc>>,
+    /// Only events before this time
+    pub until: Option<DateTime<Utc>>,
+    /// Page size, at most 200
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AuditEventPage {
+    /// Newest first
+    pub events: Vec<AuditEvent>,
+    /// Events matching the filters across all pages
+    pub total: i64,
+}
+
+const AUDIT_EVENT_COLUMNS: &str =
+    "id, source, actor_id, impersonator_id, action, target_type, target_id, ip_address, user_agent, diff, created_at";
+
+// Fixed precision keeps the stored text in chronological order, which the
+// `since`/`until` filters and the ordering rely on
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+impl AuditEventQuery {
+    fn where_clause(&self) -> (String, Vec<String>) {
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        
+        let filters = [
+            ("actor_id = ?", self.actor_id.clone()),
+            ("action = ?", self.action.clone()),
+            ("target_id = ?", self.target_id.clone()),
+            ("created_at >= ?", self.since.map(timestamp)),
+            ("created_at < ?", self.until.map(timestamp)),
+        ];
+        for (condition, value) in filters {
+            if let Some(value) = value {
+                conditions.push(condition);
+                params.push(value);
+            }
+        }
+        
+        if conditions.is_empty() {
+            (String::new(), params)
+        } else {
+            (format!("WHERE {}", conditions.join(" AND ")), params)
+        }
+    }
+}
+
+impl AuditEvent {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        let source: String = row.get(1)?;
+        let diff = match row.get::<_, Option<String>>(9)? {
+            Some(diff) => Some(serde_json::from_str(&diff).map_err(|err| {
+                rusqlite::Error::FromSqlConversionFailure(9, rusqlite::types::Type::Text, Box::new(err))
+            })?),
+            None => None,
+        };
+        
+        Ok(AuditEvent {
+            id: row.get(0)?,
+            source: AuditSource::parse(&source)
+                .ok_or_else(|| rusqlite::Error::InvalidColumnType(1, "source".to_string(), rusqlite::types::Type::Text))?,
+            actor_id: row.get(2)?,
+            impersonator_id: row.get(3)?,
+            action: row.get(4)?,
+            target_type: row.get(5)?,
+            target_id: row.get(6)?,
+            ip_address: row.get(7)?,
+            user_agent: row.get(8)?,
+            diff,
+            created_at: parse_timestamp(row, 10, "created_at")?,
+        })
+    }
+    
+    pub fn record(conn: &Connection, event: NewAuditEvent) -> SqliteResult<()> {
+        conn.execute(
+            &format!("INSERT INTO audit_events ({AUDIT_EVENT_COLUMNS}) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)"),
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                event.source.as_str(),
+                event.actor_id,
+                event.impersonator_id,
+                event.action,
+                event.target_type,
+                event.target_id,
+                event.client.ip_address,
+                event.client.user_agent,
+                event.diff.map(|diff| diff.to_string()),
+                timestamp(Utc::now()),
+            ],
+        )?;
+        Ok(())
+    }
+    
+    // Matching events, newest first; every match when `limit` is None
+    pub fn find(conn: &Connection, query: &AuditEventQuery, limit: Option<i64>) -> SqliteResult<Vec<Self>> {
+        let (where_clause, params) = query.where_clause();
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {AUDIT_EVENT_COLUMNS} FROM audit_events {where_clause}
+             ORDER BY created_at DESC, rowid DESC LIMIT {} OFFSET {}",
+            limit.unwrap_or(-1),
+            query.offset.unwrap_or(0).max(0),
+        ))?;
+        let events = stmt.query_map(rusqlite::params_from_iter(params), Self::from_row)?;
+        events.collect()
+    }
+    
+    pub fn count(conn: &Connection, query: &AuditEventQuery) -> SqliteResult<i64> {
+        let (where_clause, params) = query.where_clause();
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM audit_events {where_clause}"),
+            rusqlite::params_from_iter(params),
+            |row| row.get(0),
+        )
+    }
+}
@@ -1,6 +1,14 @@
+use chrono::{Duration, Utc};
+use jsonwebtoken::{decode, decode_header, encode, DecodingKey, EncodingKey, Header, Validation};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use rand::RngCore;
+use rusqlite::Connection;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use utoipa::ToSchema;
 
+use crate::models::signing_key::{SigningKey, TokenError};
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LoginRequest {
     pub email: String,
@@ -9,18 +17,187 @@ pub struct LoginRequest {
 
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct LoginResponse {
+    /// Short-lived access token for the `Authorization: Bearer` header
     pub token: String,
+    /// Single-use token for `/api/auth/refresh`
+    pub refresh_token: String,
+    /// Lifetime of `token` in seconds
+    pub expires_in: i64,
     pub user: crate::models::UserResponse,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct ImpersonationResponse {
+    /// Access token acting as `user`; it cannot be refreshed
+    pub token: String,
+    /// Lifetime of `token` in seconds
+    pub expires_in: i64,
+    pub user: crate::models::UserResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
 #[derive(Debug, Serialize, Deserialize, ToSchema)]
 pub struct GoogleAuthRequest {
     pub code: String,
 }
 
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OAuthStartResponse {
+    // Where to send the browser; the provider redirects back to the web app
+    pub authorization_url: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct OAuthCallbackRequest {
+    pub code: String,
+    pub state: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PasswordResetRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PasswordResetConfirmRequest {
+    pub token: String,
+    pub new_password: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MagicLinkRequest {
+    pub email: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct MagicLinkVerifyRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct VerifyEmailRequest {
+    pub token: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorChallengeResponse {
+    /// Single-use token for `/api/auth/2fa/verify`
+    pub challenge_token: String,
+    /// Lifetime of `challenge_token` in seconds
+    pub expires_in: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorVerifyRequest {
+    pub challenge_token: String,
+    /// Current code from the authenticator app
+    pub code: Option<String>,
+    /// One of the recovery codes, used instead of `code`
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorSetupResponse {
+    /// Base32 secret for manual entry
+    pub secret: String,
+    /// `otpauth://` URI to render as a QR code
+    pub otpauth_uri: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct TwoFactorCodeRequest {
+    pub code: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RecoveryCodesResponse {
+    /// Shown once; each code signs in a single time
+    pub recovery_codes: Vec<String>,
+}
+
+// Access tokens are short-lived; clients stay signed in by rotating refresh tokens
+pub const ACCESS_TOKEN_TTL_MINUTES: i64 = 15;
+// Impersonation tokens cannot be refreshed, so this is as long as support gets
+pub const IMPERSONATION_TTL_MINUTES: i64 = 30;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Claims {
     pub sub: String,
+    // Id of the `sessions` row backing this token
+    pub sid: String,
     pub exp: usize,
     pub iat: usize,
-}
\ No newline at end of file
+    // Set on impersonation tokens: `sub` is the user being impersonated and
+    // `act` the admin doing it (RFC 8693)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub act: Option<Actor>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Actor {
+    pub sub: String,
+}
+
+impl Claims {
+    pub fn new(user_id: &str, session_id: &str, ttl: Duration) -> Self {
+        let now = Utc::now();
+        Claims {
+            sub: user_id.to_string(),
+            sid: session_id.to_string(),
+            exp: (now + ttl).timestamp() as usize,
+            iat: now.timestamp() as usize,
+            act: None,
+        }
+    }
+
+    pub fn impersonated_by(mut self, actor_id: &str) -> Self {
+        self.act = Some(Actor { sub: actor_id.to_string() });
+        self
+    }
+
+    // Signs with the current signing key and names it in the `kid` header. Until a
+    // key has been generated, tokens fall back to HS256 with the shared secret.
+    pub fn encode(&self, conn: &Connection, secret: &str) -> Result<String, TokenError> {
+        match SigningKey::current(conn)? {
+            Some(key) => {
+                let mut header = Header::new(key.algorithm.jwt_algorithm());
+                header.kid = Some(key.kid.clone());
+                Ok(encode(&header, self, &key.encoding_key())?)
+            }
+            None => Ok(encode(&Header::default(), self, &EncodingKey::from_secret(secret.as_bytes()))?),
+        }
+    }
+
+    // Once signing keys exist only tokens naming one of them by `kid` verify,
+    // so the shared secret can no longer mint tokens
+    pub fn decode(token: &str, conn: &Connection, secret: &str) -> Result<Self, TokenError> {
+        let keys = SigningKey::verification_keys(conn)?;
+        if keys.is_empty() {
+            let data = decode::<Claims>(token, &DecodingKey::from_secret(secret.as_bytes()), &Validation::default())?;
+            return Ok(data.claims);
+        }
+
+        let kid = decode_header(token)?.kid.ok_or(TokenError::UnknownKey)?;
+        let key = keys.iter().find(|key| key.kid == kid).ok_or(TokenError::UnknownKey)?;
+        let jwk = key.jwk().map_err(|_| TokenError::UnknownKey)?;
+        let validation = Validation::new(key.algorithm.jwt_algorithm());
+        Ok(decode::<Claims>(token, &DecodingKey::from_jwk(&jwk)?, &validation)?.claims)
+    }
+}
+
+// Random URL-safe token for refresh tokens and other secrets handed to clients
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+// Tokens are high-entropy, so a fast unsalted hash is enough to keep them useless
+// if the database leaks while still allowing lookup by hash
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,235 @@
+use std::{env, fmt};
+
+use chrono::Duration;
+
+#[derive(Debug, Clone)]
+pub struct GoogleConfig {
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub token_url: String,
+    pub userinfo_url: String,
+}
+
+impl GoogleConfig {
+    pub fn from_env() -> Self {
+        GoogleConfig {
+            client_id: env::var("GOOGLE_CLIENT_ID").unwrap_or_default(),
+            client_secret: env::var("GOOGLE_CLIENT_SECRET").unwrap_or_default(),
+            redirect_uri: env::var("GOOGLE_REDIRECT_URI").unwrap_or_default(),
+            token_url: env::var("GOOGLE_TOKEN_URL")
+                .unwrap_or_else(|_| "https://oauth2.googleapis.com/token".to_string()),
+            userinfo_url: env::var("GOOGLE_USERINFO_URL")
+                .unwrap_or_else(|_| "https://openidconnect.googleapis.com/v1/userinfo".to_string()),
+        }
+    }
+}
+
+// An external OpenID Connect or plain OAuth 2.0 provider. With an `issuer` the
+// endpoints come from its discovery document and sign-in requires an ID token;
+// without one (GitHub-style OAuth 2.0) the endpoint URLs must all be configured.
+// Explicit URLs always take precedence over discovered ones.
+#[derive(Debug, Clone)]
+pub struct OAuthProviderConfig {
+    // Used in routes and stored with linked identities, so it must not change
+    pub name: String,
+    pub client_id: String,
+    pub client_secret: String,
+    pub redirect_uri: String,
+    pub scopes: Vec<String>,
+    pub issuer: Option<String>,
+    pub authorization_url: Option<String>,
+    pub token_url: Option<String>,
+    pub userinfo_url: Option<String>,
+}
+
+impl OAuthProviderConfig {
+    // Reads the providers listed in OAUTH_PROVIDERS (comma separated), each
+    // configured through OAUTH_<NAME>_* variables
+    pub fn all_from_env(app_url: &str) -> Vec<Self> {
+        env::var("OAUTH_PROVIDERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|name| name.trim().to_ascii_lowercase())
+            .filter(|name| !name.is_empty())
+            .map(|name| Self::from_env(&name, app_url))
+            .collect()
+    }
+    
+    pub fn from_env(name: &str, app_url: &str) -> Self {
+        let prefix = format!("OAUTH_{}_", name.to_ascii_uppercase().replace('-', "_"));
+        let var = |key: &str| env::var(format!("{prefix}{key}")).ok().filter(|value| !value.is_empty());
+        let issuer = var("ISSUER");
+        let default_scopes = if issuer.is_some() { "openid email profile" } else { "" };
+        
+        OAuthProviderConfig {
+            name: name.to_string(),
+            client_id: var("CLIENT_ID").unwrap_or_default(),
+            client_secret: var("CLIENT_SECRET").unwrap_or_default(),
+            // The web app receives the redirect and posts the code back to the API
+            redirect_uri: var("REDIRECT_URI")
+                .unwrap_or_else(|| format!("{}/oauth/{name}/callback", app_url.trim_end_matches('/'))),
+            scopes: var("SCOPES")
+                .unwrap_or_else(|| default_scopes.to_string())
+                .split([' ', ','])
+                .filter(|scope| !scope.is_empty())
+                .map(str::to_string)
+                .collect(),
+            issuer,
+            authorization_url: var("AUTHORIZATION_URL"),
+            token_url: var("TOKEN_URL"),
+            userinfo_url: var("USERINFO_URL"),
+        }
+    }
+}
+
+// The placeholder the server used to fall back to; anyone who has read the source
+// can forge tokens signed with it
+pub const DEFAULT_JWT_SECRET: &str = "your-secret-key";
+
+// 32 characters, e.g. the 43 printed by `gen-secret`
+pub const MIN_JWT_SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JwtSecretError {
+    Missing,
+    Default,
+    TooShort(usize),
+}
+
+impl fmt::Display for JwtSecretError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JwtSecretError::Missing => f.write_str("JWT_SECRET is not set"),
+            JwtSecretError::Default => write!(f, "JWT_SECRET is the insecure default \"{DEFAULT_JWT_SECRET}\""),
+            JwtSecretError::TooShort(length) => {
+                write!(f, "JWT_SECRET is {length} characters long; use at least {MIN_JWT_SECRET_LENGTH}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for JwtSecretError {}
+
+// Checks the secret that signs access tokens, and verifies them until signing keys exist
+pub fn validate_jwt_secret(secret: Option<&str>) -> Result<(), JwtSecretError> {
+    match secret {
+        None | Some("") => Err(JwtSecretError::Missing),
+        Some(DEFAULT_JWT_SECRET) => Err(JwtSecretError::Default),
+        Some(secret) if secret.chars().count() < MIN_JWT_SECRET_LENGTH => {
+            Err(JwtSecretError::TooShort(secret.chars().count()))
+        }
+        Some(_) => Ok(()),
+    }
+}
+
+// Relying party settings for passkeys. Browsers only hand out credentials for
+// `rp_id` and report the page origin, which must match `origin` exactly.
+#[derive(Debug, Clone)]
+pub struct WebAuthnConfig {
+    pub rp_id: String,
+    pub rp_name: String,
+    pub origin: String,
+}
+
+impl WebAuthnConfig {
+    pub fn from_env(app_url: &str) -> Self {
+        let origin = env::var("WEBAUTHN_ORIGIN").unwrap_or_else(|_| app_url.trim_end_matches('/').to_string());
+        // The RP id defaults to the origin's host name
+        let host = origin
+            .split("://")
+            .nth(1)
+            .and_then(|rest| rest.split([':', '/']).next())
+            .unwrap_or("localhost")
+            .to_string();
+        
+        WebAuthnConfig {
+            rp_id: env::var("WEBAUTHN_RP_ID").unwrap_or(host),
+            rp_name: env::var("WEBAUTHN_RP_NAME").unwrap_or_else(|_| "Surjo".to_string()),
+            origin,
+        }
+    }
+}
+
+// How many failed logins are tolerated before further attempts are refused
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleLimits {
+    pub max_failures: u32,
+    // How long the lockout lasts, and how long failures are remembered
+    pub lockout: Duration,
+    // Make each failure past the second wait twice as long as the one before
+    pub progressive_delay: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LoginThrottlePolicy {
+    pub account: ThrottleLimits,
+    pub ip: ThrottleLimits,
+}
+
+impl Default for LoginThrottlePolicy {
+    fn default() -> Self {
+        LoginThrottlePolicy {
+            account: ThrottleLimits {
+                max_failures: 5,
+                lockout: Duration::minutes(15),
+                progressive_delay: true,
+            },
+            // Higher, since many users can share an address
+            ip: ThrottleLimits {
+                max_failures: 50,
+                lockout: Duration::minutes(15),
+                progressive_delay: false,
+            },
+        }
+    }
+}
+
+impl LoginThrottlePolicy {
+    pub fn from_env() -> Self {
+        let defaults = Self::default();
+        LoginThrottlePolicy {
+            account: ThrottleLimits {
+                max_failures: env_number("LOGIN_MAX_FAILURES").unwrap_or(defaults.account.max_failures),
+                lockout: env_number("LOGIN_LOCKOUT_MINUTES").map(Duration::minutes).unwrap_or(defaults.account.lockout),
+                ..defaults.account
+            },
+            ip: ThrottleLimits {
+                max_failures: env_number("LOGIN_MAX_FAILURES_PER_IP").unwrap_or(defaults.ip.max_failures),
+                lockout: env_number("LOGIN_IP_LOCKOUT_MINUTES").map(Duration::minutes).unwrap_or(defaults.ip.lockout),
+                ..defaults.ip
+            },
+        }
+    }
+}
+
+// Switches that tighten who may sign in
+#[derive(Debug, Clone, Default)]
+pub struct AuthPolicy {
+    // Refuse logins until the user has verified their email address
+    pub require_email_verification: bool,
+    // Withhold the `admin` permission from accounts without two-factor authentication
+    pub require_admin_2fa: bool,
+    pub login_throttle: LoginThrottlePolicy,
+}
+
+impl AuthPolicy {
+    pub fn from_env() -> Self {
+        AuthPolicy {
+            require_email_verification: env_flag("REQUIRE_EMAIL_VERIFICATION"),
+            require_admin_2fa: env_flag("REQUIRE_ADMIN_2FA"),
+            login_throttle: LoginThrottlePolicy::from_env(),
+        }
+    }
+}
+
+fn env_flag(name: &str) -> bool {
+    matches!(
+        env::var(name).map(|value| value.to_ascii_lowercase()).as_deref(),
+        Ok("1" | "true" | "yes" | "on")
+    )
+}
+
+fn env_number<T: std::str::FromStr>(name: &str) -> Option<T> {
+    env::var(name).ok()?.trim().parse().ok()
+}
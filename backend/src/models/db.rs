@@ -1,5 +1,7 @@
 use rusqlite::{Connection, Result};
 use refinery::embed_migrations;
+use crate::mailer::Mailer;
+use crate::models::{AuthPolicy, GoogleConfig, OAuthProviderConfig, WebAuthnConfig};
 
 embed_migrations!("migrations");
 
@@ -27,6 +29,12 @@ impl Database {
 #[derive(Debug, Clone)]
 pub struct AppState {
     pub database: std::sync::Arc<std::sync::Mutex<Database>>,
-    #[allow(dead_code)] // TODO: Will be used when JWT authentication is implemented
     pub jwt_secret: String,
+    pub google: GoogleConfig,
+    pub oauth_providers: Vec<OAuthProviderConfig>,
+    pub policy: AuthPolicy,
+    pub webauthn: WebAuthnConfig,
+    pub mailer: std::sync::Arc<dyn Mailer>,
+    // Base URL of the web app, used to build links in emails
+    pub app_url: String,
 }
\ No newline at end of file
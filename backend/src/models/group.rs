@@ -0,0 +1,187 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::user::USER_COLUMNS;
+use crate::models::User;
+
+// A set of users who all receive the group's roles
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateGroupRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Names of the roles members receive
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateGroupRequest {
+    pub description: Option<String>,
+    /// Replaces the group's roles when present
+    pub roles: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct GroupResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub roles: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl GroupResponse {
+    pub fn new(group: Group, roles: Vec<String>) -> Self {
+        GroupResponse {
+            id: group.id,
+            name: group.name,
+            description: group.description,
+            roles,
+            created_at: group.created_at,
+        }
+    }
+}
+
+const GROUP_COLUMNS: &str = "id, name, description, created_at";
+
+impl Group {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(Group {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            created_at: parse_timestamp(row, 3, "created_at")?,
+        })
+    }
+    
+    pub fn create(conn: &Connection, name: &str, description: Option<&str>) -> SqliteResult<Self> {
+        let group = Group {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        
+        conn.execute(
+            "INSERT INTO user_groups (id, name, description, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![group.id, group.name, group.description, group.created_at.to_rfc3339()],
+        )?;
+        
+        Ok(group)
+    }
+    
+    pub fn find_all(conn: &Connection) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {GROUP_COLUMNS} FROM user_groups ORDER BY name"))?;
+        let groups = stmt.query_map([], Self::from_row)?;
+        groups.collect()
+    }
+    
+    pub fn find_by_id(conn: &Connection, group_id: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(&format!("SELECT {GROUP_COLUMNS} FROM user_groups WHERE id = ?1"), [group_id], Self::from_row)
+            .optional()
+    }
+    
+    pub fn find_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT g.id, g.name, g.description, g.created_at FROM user_groups g
+             JOIN group_members gm ON gm.group_id = g.id
+             WHERE gm.user_id = ?1 ORDER BY g.name"
+        )?;
+        let groups = stmt.query_map([user_id], Self::from_row)?;
+        groups.collect()
+    }
+    
+    pub fn set_description(conn: &Connection, group_id: &str, description: Option<&str>) -> SqliteResult<()> {
+        conn.execute("UPDATE user_groups SET description = ?1 WHERE id = ?2", rusqlite::params![description, group_id])?;
+        Ok(())
+    }
+    
+    pub fn roles(conn: &Connection, group_id: &str) -> SqliteResult<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT r.name FROM roles r
+             JOIN group_roles gr ON gr.role_id = r.id
+             WHERE gr.group_id = ?1 ORDER BY r.name"
+        )?;
+        let names = stmt.query_map([group_id], |row| row.get(0))?;
+        names.collect()
+    }
+    
+    // Replaces the group's roles; names that do not exist are skipped
+    pub fn set_roles(conn: &Connection, group_id: &str, roles: &[String]) -> SqliteResult<()> {
+        conn.execute("DELETE FROM group_roles WHERE group_id = ?1", [group_id])?;
+        for role in roles {
+            conn.execute(
+                "INSERT OR IGNORE INTO group_roles (group_id, role_id)
+                 SELECT ?1, id FROM roles WHERE name = ?2",
+                [group_id, role],
+            )?;
+        }
+        Ok(())
+    }
+    
+    // Deletes the group; its members keep any roles they were given directly
+    pub fn delete(conn: &Connection, group_id: &str) -> SqliteResult<bool> {
+        conn.execute("DELETE FROM group_roles WHERE group_id = ?1", [group_id])?;
+        conn.execute("DELETE FROM group_members WHERE group_id = ?1", [group_id])?;
+        let rows_affected = conn.execute("DELETE FROM user_groups WHERE id = ?1", [group_id])?;
+        Ok(rows_affected > 0)
+    }
+    
+    pub fn members(conn: &Connection, group_id: &str) -> SqliteResult<Vec<User>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {USER_COLUMNS} FROM users
+             WHERE id IN (SELECT user_id FROM group_members WHERE group_id = ?1) ORDER BY email"
+        ))?;
+        let users = stmt.query_map([group_id], User::from_row)?;
+        users.collect()
+    }
+    
+    // Returns false if the user was already a member
+    pub fn add_member(conn: &Connection, group_id: &str, user_id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "INSERT OR IGNORE INTO group_members (group_id, user_id, added_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![group_id, user_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Returns false if the user is not a member
+    pub fn set_manager(conn: &Connection, group_id: &str, user_id: &str, is_manager: bool) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "UPDATE group_members SET is_manager = ?1 WHERE group_id = ?2 AND user_id = ?3",
+            rusqlite::params![is_manager, group_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Ids of the groups the user belongs to, or only those they manage
+    pub fn ids_for_user(conn: &Connection, user_id: &str, managed_only: bool) -> SqliteResult<HashSet<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT group_id FROM group_members WHERE user_id = ?1 AND (is_manager OR NOT ?2)"
+        )?;
+        let ids = stmt.query_map(rusqlite::params![user_id, managed_only], |row| row.get(0))?;
+        ids.collect()
+    }
+    
+    pub fn remove_member(conn: &Connection, group_id: &str, user_id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM group_members WHERE group_id = ?1 AND user_id = ?2",
+            [group_id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,139 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+
+use crate::models::session::parse_timestamp;
+use crate::models::ThrottleLimits;
+
+// Longest wait imposed by the progressive delay before the lockout takes over
+const MAX_PROGRESSIVE_DELAY_SECONDS: i64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ThrottleScope {
+    Account,
+    Ip,
+}
+
+impl ThrottleScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ThrottleScope::Account => "account",
+            ThrottleScope::Ip => "ip",
+        }
+    }
+}
+
+// Why a login attempt is being refused, and for how long
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Throttled {
+    Locked(Duration),
+    Delayed(Duration),
+}
+
+#[derive(Debug, Clone)]
+struct ThrottleState {
+    failures: u32,
+    last_failure_at: DateTime<Utc>,
+    locked_until: Option<DateTime<Utc>>,
+}
+
+impl ThrottleState {
+    // Failures are forgotten once a lockout has run out, or after a quiet period
+    // as long as the lockout
+    fn is_stale(&self, limits: &ThrottleLimits, now: DateTime<Utc>) -> bool {
+        match self.locked_until {
+            Some(locked_until) => locked_until <= now,
+            None => self.last_failure_at + limits.lockout <= now,
+        }
+    }
+}
+
+// Account keys are normalized emails, so unknown addresses are throttled the same
+// way as real ones and responses do not reveal which accounts exist
+pub fn account_throttle_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+pub struct LoginThrottle;
+
+impl LoginThrottle {
+    fn find(conn: &Connection, scope: ThrottleScope, key: &str) -> SqliteResult<Option<ThrottleState>> {
+        conn.query_row(
+            "SELECT failures, last_failure_at, locked_until FROM login_throttles
+             WHERE scope = ?1 AND throttle_key = ?2",
+            [scope.as_str(), key],
+            |row| {
+                let locked_until = match row.get::<_, Option<String>>(2)? {
+                    Some(_) => Some(parse_timestamp(row, 2, "locked_until")?),
+                    None => None,
+                };
+                Ok(ThrottleState {
+                    failures: row.get(0)?,
+                    last_failure_at: parse_timestamp(row, 1, "last_failure_at")?,
+                    locked_until,
+                })
+            },
+        )
+        .optional()
+    }
+    
+    // Returns whether another attempt has to wait, and how long
+    pub fn check(
+        conn: &Connection,
+        scope: ThrottleScope,
+        key: &str,
+        limits: &ThrottleLimits,
+    ) -> SqliteResult<Option<Throttled>> {
+        let now = Utc::now();
+        let state = match Self::find(conn, scope, key)? {
+            Some(state) if !state.is_stale(limits, now) => state,
+            _ => return Ok(None),
+        };
+        
+        if let Some(locked_until) = state.locked_until {
+            return Ok(Some(Throttled::Locked(locked_until - now)));
+        }
+        
+        // 1s after the second failure, then 2s, 4s, ... up to the cap
+        if limits.progressive_delay && state.failures >= 2 {
+            let delay = Duration::seconds((1i64 << (state.failures - 2).min(16)).min(MAX_PROGRESSIVE_DELAY_SECONDS));
+            let ready_at = state.last_failure_at + delay;
+            if ready_at > now {
+                return Ok(Some(Throttled::Delayed(ready_at - now)));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    // Counts a failed attempt, locking the key once it reaches the limit
+    pub fn record_failure(
+        conn: &Connection,
+        scope: ThrottleScope,
+        key: &str,
+        limits: &ThrottleLimits,
+    ) -> SqliteResult<()> {
+        let now = Utc::now();
+        let failures = match Self::find(conn, scope, key)? {
+            Some(state) if !state.is_stale(limits, now) => state.failures + 1,
+            _ => 1,
+        };
+        let locked_until = (failures >= limits.max_failures).then(|| (now + limits.lockout).to_rfc3339());
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO login_throttles (scope, throttle_key, failures, last_failure_at, locked_until)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![scope.as_str(), key, failures, now.to_rfc3339(), locked_until],
+        )?;
+        
+        Ok(())
+    }
+    
+    // Forgets all failures for the key, returning whether there were any
+    pub fn clear(conn: &Connection, scope: ThrottleScope, key: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM login_throttles WHERE scope = ?1 AND throttle_key = ?2",
+            [scope.as_str(), key],
+        )?;
+        Ok(rows_affected > 0)
+    }
+}
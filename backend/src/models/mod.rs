@@ -1,7 +1,35 @@
 pub mod user;
+pub mod role;
+pub mod group;
 pub mod auth;
 pub mod db;
+pub mod config;
+pub mod oauth;
+pub mod permission;
+pub mod session;
+pub mod refresh_token;
+pub mod one_time_token;
+pub mod two_factor;
+pub mod passkey;
+pub mod api_key;
+pub mod login_throttle;
+pub mod signing_key;
+pub mod audit;
 
 pub use user::*;
+pub use role::*;
+pub use group::*;
 pub use auth::*;
-pub use db::*;
\ No newline at end of file
+pub use db::*;
+pub use config::*;
+pub use oauth::*;
+pub use permission::*;
+pub use session::*;
+pub use refresh_token::*;
+pub use one_time_token::*;
+pub use two_factor::*;
+pub use passkey::*;
+pub use api_key::*;
+pub use login_throttle::*;
+pub use signing_key::*;
+pub use audit::*;
\ No newline at end of file
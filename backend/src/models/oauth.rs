@@ -0,0 +1,239 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::{generate_token, hash_token, AuditEvent, ClientInfo, NewAuditEvent, User};
+
+// What an external provider tells us about the account that signed in
+#[derive(Debug, Clone)]
+pub struct ExternalProfile {
+    pub subject: String,
+    pub email: String,
+    pub email_verified: bool,
+    pub first_name: Option<String>,
+    pub last_name: Option<String>,
+}
+
+// A row in `oauth_providers`, linking a local user to an external account
+#[derive(Debug, Clone)]
+pub struct OAuthIdentity {
+    pub id: String,
+    pub user_id: String,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct IdentityResponse {
+    pub id: String,
+    pub provider: String,
+    pub provider_user_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<OAuthIdentity> for IdentityResponse {
+    fn from(identity: OAuthIdentity) -> Self {
+        IdentityResponse {
+            id: identity.id,
+            provider: identity.provider,
+            provider_user_id: identity.provider_user_id,
+            created_at: identity.created_at,
+        }
+    }
+}
+
+// Finishes linking an identity. `state` comes from `/api/me/identities/{provider}/start`
+// and is required for every provider except Google, which only exchanges a code.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct LinkIdentityRequest {
+    pub code: String,
+    pub state: Option<String>,
+}
+
+const IDENTITY_COLUMNS: &str = "id, user_id, provider, provider_user_id, created_at";
+
+impl OAuthIdentity {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(OAuthIdentity {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            provider: row.get(2)?,
+            provider_user_id: row.get(3)?,
+            created_at: parse_timestamp(row, 4, "created_at")?,
+        })
+    }
+    
+    pub fn create(
+        conn: &Connection,
+        user_id: &str,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> SqliteResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        
+        conn.execute(
+            "INSERT INTO oauth_providers (id, user_id, provider, provider_user_id, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![id, user_id, provider, provider_user_id, now.to_rfc3339()],
+        )?;
+        
+        Ok(OAuthIdentity {
+            id,
+            user_id: user_id.to_string(),
+            provider: provider.to_string(),
+            provider_user_id: provider_user_id.to_string(),
+            created_at: now,
+        })
+    }
+    
+    pub fn find_user_id(
+        conn: &Connection,
+        provider: &str,
+        provider_user_id: &str,
+    ) -> SqliteResult<Option<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT user_id FROM oauth_providers WHERE provider = ?1 AND provider_user_id = ?2"
+        )?;
+        
+        match stmt.query_row([provider, provider_user_id], |row| row.get(0)) {
+            Ok(user_id) => Ok(Some(user_id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub fn find_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {IDENTITY_COLUMNS} FROM oauth_providers WHERE user_id = ?1 ORDER BY created_at"
+        ))?;
+        
+        let identities = stmt.query_map([user_id], Self::from_row)?;
+        identities.collect()
+    }
+    
+    pub fn delete_for_user(conn: &Connection, user_id: &str, id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM oauth_providers WHERE id = ?1 AND user_id = ?2",
+            [id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Resolves the external account to a local user, linking by verified email or
+    // creating a password-less account. Returns None when the email belongs to an
+    // existing user and either side has not verified it: linking would then let
+    // whoever controls the external account take over the local one, or let
+    // whoever registered the address first keep a password into the owner's account. Creating and linking are audited here since the
+    // caller cannot tell which happened.
+    pub fn find_or_create_user(
+        conn: &Connection,
+        provider: &str,
+        profile: &ExternalProfile,
+        client: &ClientInfo,
+    ) -> SqliteResult<Option<User>> {
+        if let Some(user_id) = Self::find_user_id(conn, provider, &profile.subject)? {
+            return User::find_by_id(conn, &user_id);
+        }
+        
+        let user = match User::find_by_email(conn, &profile.email)? {
+            Some(user) if profile.email_verified && user.email_verified_at.is_some() => user,
+            Some(_) => return Ok(None),
+            None => {
+                let user = User::create(
+                    conn,
+                    &profile.email,
+                    None,
+                    profile.first_name.as_deref(),
+                    profile.last_name.as_deref(),
+                )?;
+                let event = NewAuditEvent::new("user.create", client).target("user", &user.id);
+                AuditEvent::record(conn, event.diff(json!({ "email": user.email, "via": provider })))?;
+                user
+            }
+        };
+        
+        let identity = Self::create(conn, &user.id, provider, &profile.subject)?;
+        let event = NewAuditEvent::new("auth.identity_linked", client).actor(&user.id).target("user", &user.id);
+        AuditEvent::record(conn, event.diff(json!({ "identity_id": identity.id, "provider": provider })))?;
+        if profile.email_verified {
+            User::mark_email_verified(conn, &user.id)?;
+        }
+        User::find_by_id(conn, &user.id)
+    }
+}
+
+// Secrets kept between sending the user to a provider and the provider sending
+// them back: the PKCE code verifier and the OpenID Connect nonce
+#[derive(Debug, Clone)]
+pub struct PendingAuthorization {
+    pub code_verifier: String,
+    pub nonce: String,
+}
+
+pub struct OAuthState;
+
+impl OAuthState {
+    // Starts an authorization request and returns the `state` to send along with
+    // the secrets that go with it. `user_id` is set when a signed-in user is
+    // linking the identity rather than signing in with it.
+    pub fn issue(
+        conn: &Connection,
+        provider: &str,
+        user_id: Option<&str>,
+        ttl: Duration,
+    ) -> SqliteResult<(String, PendingAuthorization)> {
+        let state = generate_token();
+        let pending = PendingAuthorization {
+            code_verifier: generate_token(),
+            nonce: generate_token(),
+        };
+        let now = Utc::now();
+        
+        conn.execute("DELETE FROM oauth_states WHERE expires_at <= ?1", [now.to_rfc3339()])?;
+        conn.execute(
+            "INSERT INTO oauth_states (id, provider, user_id, state_hash, code_verifier, nonce, expires_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                provider,
+                user_id,
+                hash_token(&state),
+                pending.code_verifier,
+                pending.nonce,
+                (now + ttl).to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        
+        Ok((state, pending))
+    }
+    
+    // Removes the request and returns its secrets, or None if the state is unknown,
+    // was issued for another provider or user, or has expired
+    pub fn consume(
+        conn: &Connection,
+        provider: &str,
+        user_id: Option<&str>,
+        state: &str,
+    ) -> SqliteResult<Option<PendingAuthorization>> {
+        conn.query_row(
+            "DELETE FROM oauth_states
+             WHERE state_hash = ?1 AND provider = ?2 AND user_id IS ?3 AND expires_at > ?4
+             RETURNING code_verifier, nonce",
+            rusqlite::params![hash_token(state), provider, user_id, Utc::now().to_rfc3339()],
+            |row| {
+                Ok(PendingAuthorization {
+                    code_verifier: row.get(0)?,
+                    nonce: row.get(1)?,
+                })
+            },
+        )
+        .optional()
+    }
+}
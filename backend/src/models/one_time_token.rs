@@ -0,0 +1,73 @@
+use chrono::{Duration, Utc};
+use rusqlite::{Connection, Result as SqliteResult};
+use uuid::Uuid;
+
+use crate::models::{generate_token, hash_token};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenPurpose {
+    PasswordReset,
+    EmailVerification,
+    TwoFactorChallenge,
+    MagicLink,
+}
+
+impl TokenPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TokenPurpose::PasswordReset => "password_reset",
+            TokenPurpose::EmailVerification => "email_verification",
+            TokenPurpose::TwoFactorChallenge => "two_factor_challenge",
+            TokenPurpose::MagicLink => "magic_link",
+        }
+    }
+}
+
+pub struct OneTimeToken;
+
+impl OneTimeToken {
+    // Stores a new token and returns its plaintext value. Any earlier unused token
+    // for the same user and purpose stops working, so only the latest email is valid.
+    pub fn issue(conn: &Connection, user_id: &str, purpose: TokenPurpose, ttl: Duration) -> SqliteResult<String> {
+        let token = generate_token();
+        let now = Utc::now();
+        
+        conn.execute(
+            "DELETE FROM one_time_tokens WHERE user_id = ?1 AND purpose = ?2 AND used_at IS NULL",
+            [user_id, purpose.as_str()],
+        )?;
+        conn.execute(
+            "INSERT INTO one_time_tokens (id, user_id, purpose, token_hash, expires_at, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                user_id,
+                purpose.as_str(),
+                hash_token(&token),
+                (now + ttl).to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        
+        Ok(token)
+    }
+    
+    // Marks the token used and returns its user id, or None if it is unknown,
+    // meant for another purpose, expired or already used
+    pub fn consume(conn: &Connection, token: &str, purpose: TokenPurpose) -> SqliteResult<Option<String>> {
+        let now = Utc::now().to_rfc3339();
+        let result = conn.query_row(
+            "UPDATE one_time_tokens SET used_at = ?1 
+             WHERE token_hash = ?2 AND purpose = ?3 AND used_at IS NULL AND expires_at > ?1 
+             RETURNING user_id",
+            rusqlite::params![now, hash_token(token), purpose.as_str()],
+            |row| row.get(0),
+        );
+        
+        match result {
+            Ok(user_id) => Ok(Some(user_id)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
@@ -0,0 +1,318 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::{generate_token, hash_token};
+
+#[derive(Debug, Clone)]
+pub struct Passkey {
+    pub id: String,
+    pub user_id: String,
+    // Authenticator-chosen credential id, base64url encoded
+    pub credential_id: String,
+    // Uncompressed SEC1 P-256 point
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PasskeyResponse {
+    pub id: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+}
+
+impl From<Passkey> for PasskeyResponse {
+    fn from(passkey: Passkey) -> Self {
+        PasskeyResponse {
+            id: passkey.id,
+            name: passkey.name,
+            created_at: passkey.created_at,
+            last_used_at: passkey.last_used_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RenamePasskeyRequest {
+    pub name: String,
+}
+
+// Mirrors `PublicKeyCredentialCreationOptionsJSON`, so browsers can pass it to
+// `PublicKeyCredential.parseCreationOptionsFromJSON()`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyRegistrationOptions {
+    pub challenge: String,
+    pub rp: RelyingParty,
+    pub user: PasskeyUser,
+    pub pub_key_cred_params: Vec<CredentialParameter>,
+    /// Milliseconds the browser should wait for the authenticator
+    pub timeout: u32,
+    pub attestation: String,
+    pub authenticator_selection: AuthenticatorSelection,
+    /// Passkeys the user already has, so an authenticator is not registered twice
+    pub exclude_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RelyingParty {
+    pub id: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyUser {
+    /// Base64url user handle, returned by the authenticator on sign-in
+    pub id: String,
+    pub name: String,
+    pub display_name: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CredentialParameter {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    pub alg: i64,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct AuthenticatorSelection {
+    pub resident_key: String,
+    pub user_verification: String,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CredentialDescriptor {
+    #[serde(rename = "type")]
+    pub credential_type: String,
+    /// Base64url credential id
+    pub id: String,
+}
+
+// Mirrors `PublicKeyCredentialRequestOptionsJSON`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct PasskeyAuthenticationOptions {
+    pub challenge: String,
+    pub rp_id: String,
+    pub timeout: u32,
+    pub user_verification: String,
+    /// Always empty, letting the authenticator offer any passkey it holds for this site
+    pub allow_credentials: Vec<CredentialDescriptor>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PasskeyRegistrationRequest {
+    /// Label shown in the passkey list
+    pub name: Option<String>,
+    /// `PublicKeyCredential.toJSON()` from `navigator.credentials.create()`
+    pub credential: RegistrationCredential,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RegistrationCredential {
+    pub id: String,
+    pub response: AttestationResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AttestationResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "attestationObject")]
+    pub attestation_object: String,
+}
+
+/// `PublicKeyCredential.toJSON()` from `navigator.credentials.get()`
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PasskeyLoginFinishRequest {
+    pub id: String,
+    pub response: AssertionResponse,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct AssertionResponse {
+    #[serde(rename = "clientDataJSON")]
+    pub client_data_json: String,
+    #[serde(rename = "authenticatorData")]
+    pub authenticator_data: String,
+    pub signature: String,
+    #[serde(rename = "userHandle")]
+    pub user_handle: Option<String>,
+}
+
+const PASSKEY_COLUMNS: &str = "id, user_id, credential_id, public_key, sign_count, name, created_at, last_used_at";
+
+impl Passkey {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        let last_used_at = match row.get::<_, Option<String>>(7)? {
+            Some(_) => Some(parse_timestamp(row, 7, "last_used_at")?),
+            None => None,
+        };
+        
+        Ok(Passkey {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            credential_id: row.get(2)?,
+            public_key: row.get(3)?,
+            sign_count: row.get(4)?,
+            name: row.get(5)?,
+            created_at: parse_timestamp(row, 6, "created_at")?,
+            last_used_at,
+        })
+    }
+    
+    pub fn create(
+        conn: &Connection,
+        user_id: &str,
+        credential_id: &str,
+        public_key: &[u8],
+        sign_count: u32,
+        name: &str,
+    ) -> SqliteResult<Self> {
+        let id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        
+        conn.execute(
+            "INSERT INTO webauthn_credentials (id, user_id, credential_id, public_key, sign_count, name, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![id, user_id, credential_id, public_key, sign_count, name, now.to_rfc3339()],
+        )?;
+        
+        Ok(Passkey {
+            id,
+            user_id: user_id.to_string(),
+            credential_id: credential_id.to_string(),
+            public_key: public_key.to_vec(),
+            sign_count,
+            name: name.to_string(),
+            created_at: now,
+            last_used_at: None,
+        })
+    }
+    
+    pub fn find_by_credential_id(conn: &Connection, credential_id: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT {PASSKEY_COLUMNS} FROM webauthn_credentials WHERE credential_id = ?1"),
+            [credential_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+    
+    pub fn find_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {PASSKEY_COLUMNS} FROM webauthn_credentials WHERE user_id = ?1 ORDER BY created_at"
+        ))?;
+        
+        let passkeys = stmt.query_map([user_id], Self::from_row)?;
+        passkeys.collect()
+    }
+    
+    pub fn rename(conn: &Connection, user_id: &str, id: &str, name: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(
+            &format!(
+                "UPDATE webauthn_credentials SET name = ?1 WHERE id = ?2 AND user_id = ?3
+                 RETURNING {PASSKEY_COLUMNS}"
+            ),
+            [name, id, user_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+    
+    pub fn delete_for_user(conn: &Connection, user_id: &str, id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM webauthn_credentials WHERE id = ?1 AND user_id = ?2",
+            [id, user_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+    
+    pub fn delete_all_for_user(conn: &Connection, user_id: &str) -> SqliteResult<usize> {
+        conn.execute("DELETE FROM webauthn_credentials WHERE user_id = ?1", [user_id])
+    }
+    
+    pub fn record_use(conn: &Connection, id: &str, sign_count: u32) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE webauthn_credentials SET sign_count = ?1, last_used_at = ?2 WHERE id = ?3",
+            rusqlite::params![sign_count, Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CeremonyPurpose {
+    Registration,
+    Authentication,
+}
+
+impl CeremonyPurpose {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CeremonyPurpose::Registration => "registration",
+            CeremonyPurpose::Authentication => "authentication",
+        }
+    }
+}
+
+pub struct WebAuthnChallenge;
+
+impl WebAuthnChallenge {
+    // Stores a new random challenge and returns it base64url encoded, as it will
+    // come back in the client data
+    pub fn issue(
+        conn: &Connection,
+        user_id: Option<&str>,
+        purpose: CeremonyPurpose,
+        ttl: Duration,
+    ) -> SqliteResult<String> {
+        let challenge = generate_token();
+        let now = Utc::now();
+        
+        conn.execute("DELETE FROM webauthn_challenges WHERE expires_at <= ?1", [now.to_rfc3339()])?;
+        conn.execute(
+            "INSERT INTO webauthn_challenges (id, user_id, purpose, challenge_hash, expires_at, created_at)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                user_id,
+                purpose.as_str(),
+                hash_token(&challenge),
+                (now + ttl).to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        
+        Ok(challenge)
+    }
+    
+    // Removes the challenge and returns the user it was issued for (None for a
+    // sign-in that did not name an account). The outer None means the challenge
+    // is unknown, meant for the other ceremony or expired.
+    pub fn consume(
+        conn: &Connection,
+        challenge: &str,
+        purpose: CeremonyPurpose,
+    ) -> SqliteResult<Option<Option<String>>> {
+        conn.query_row(
+            "DELETE FROM webauthn_challenges
+             WHERE challenge_hash = ?1 AND purpose = ?2 AND expires_at > ?3
+             RETURNING user_id",
+            rusqlite::params![hash_token(challenge), purpose.as_str(), Utc::now().to_rfc3339()],
+            |row| row.get(0),
+        )
+        .optional()
+    }
+}
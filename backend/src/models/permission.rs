@@ -0,0 +1,222 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+
+pub const ADMIN_PERMISSION: &str = "admin";
+pub const USER_PERMISSION: &str = "user";
+
+#[derive(Debug, Clone)]
+pub struct Permission {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreatePermissionRequest {
+    /// Identifier checked by handlers, e.g. `reports.read`
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdatePermissionRequest {
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PermissionResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Permission> for PermissionResponse {
+    fn from(permission: Permission) -> Self {
+        PermissionResponse {
+            id: permission.id,
+            name: permission.name,
+            description: permission.description,
+            created_at: permission.created_at,
+        }
+    }
+}
+
+// Companion to `UserResponse`: what a user may do and where it comes from
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserPermissionsResponse {
+    pub user_id: String,
+    /// Effective permissions: direct grants plus those of roles and groups
+    pub permissions: Vec<String>,
+    /// Permissions granted to the user directly
+    pub direct: Vec<String>,
+    /// Roles assigned to the user directly
+    pub roles: Vec<String>,
+    /// Groups the user belongs to
+    pub groups: Vec<String>,
+}
+
+const PERMISSION_COLUMNS: &str = "id, name, description, created_at";
+
+impl Permission {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(Permission {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            created_at: parse_timestamp(row, 3, "created_at")?,
+        })
+    }
+    
+    pub fn create(conn: &Connection, name: &str, description: Option<&str>) -> SqliteResult<Self> {
+        let permission = Permission {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        
+        conn.execute(
+            "INSERT INTO permissions (id, name, description, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![permission.id, permission.name, permission.description, permission.created_at.to_rfc3339()],
+        )?;
+        
+        Ok(permission)
+    }
+    
+    pub fn find_all(conn: &Connection) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {PERMISSION_COLUMNS} FROM permissions ORDER BY name"))?;
+        let permissions = stmt.query_map([], Self::from_row)?;
+        permissions.collect()
+    }
+    
+    pub fn find_by_id(conn: &Connection, permission_id: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT {PERMISSION_COLUMNS} FROM permissions WHERE id = ?1"),
+            [permission_id],
+            Self::from_row,
+        )
+        .optional()
+    }
+    
+    pub fn set_description(conn: &Connection, permission_id: &str, description: Option<&str>) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE permissions SET description = ?1 WHERE id = ?2",
+            rusqlite::params![description, permission_id],
+        )?;
+        Ok(())
+    }
+    
+    // Deletes the permission and takes it away from every user, role and API key
+    pub fn delete(conn: &Connection, permission_id: &str) -> SqliteResult<bool> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM user_permissions WHERE permission_id = ?1", [permission_id])?;
+        tx.execute("DELETE FROM role_permissions WHERE permission_id = ?1", [permission_id])?;
+        tx.execute("DELETE FROM api_key_permissions WHERE permission_id = ?1", [permission_id])?;
+        let rows_affected = tx.execute("DELETE FROM permissions WHERE id = ?1", [permission_id])?;
+        tx.commit()?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Permissions granted to the user directly, not through roles or groups
+    pub fn direct_names_for_user(conn: &Connection, user_id: &str) -> SqliteResult<HashSet<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.name FROM permissions p 
+             JOIN user_permissions up ON up.permission_id = p.id 
+             WHERE up.user_id = ?1"
+        )?;
+        
+        let names = stmt.query_map([user_id], |row| row.get(0))?;
+        names.collect()
+    }
+    
+    // Subquery selecting the ids of users holding the permission bound to each of
+    // its three `?` placeholders, by any of the routes `names_for_user` follows
+    pub(crate) const HOLDERS_SQL: &str = "SELECT up.user_id FROM user_permissions up 
+         JOIN permissions p ON p.id = up.permission_id 
+         WHERE p.name = ?
+         UNION
+         SELECT ur.user_id FROM user_roles ur 
+         JOIN role_permissions rp ON rp.role_id = ur.role_id 
+         JOIN permissions p ON p.id = rp.permission_id 
+         WHERE p.name = ?
+         UNION
+         SELECT gm.user_id FROM group_members gm 
+         JOIN group_roles gr ON gr.group_id = gm.group_id 
+         JOIN role_permissions rp ON rp.role_id = gr.role_id 
+         JOIN permissions p ON p.id = rp.permission_id 
+         WHERE p.name = ?";
+    
+    // Effective permissions: direct grants, plus those of the user's roles and of
+    // the roles of every group the user belongs to
+    pub fn names_for_user(conn: &Connection, user_id: &str) -> SqliteResult<HashSet<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.name FROM permissions p 
+             JOIN user_permissions up ON up.permission_id = p.id 
+             WHERE up.user_id = ?1
+             UNION
+             SELECT p.name FROM permissions p 
+             JOIN role_permissions rp ON rp.permission_id = p.id 
+             JOIN user_roles ur ON ur.role_id = rp.role_id 
+             WHERE ur.user_id = ?1
+             UNION
+             SELECT p.name FROM permissions p 
+             JOIN role_permissions rp ON rp.permission_id = p.id 
+             JOIN group_roles gr ON gr.role_id = rp.role_id 
+             JOIN group_members gm ON gm.group_id = gr.group_id 
+             WHERE gm.user_id = ?1"
+        )?;
+        
+        let names = stmt.query_map([user_id], |row| row.get(0))?;
+        names.collect()
+    }
+    
+    // Grants the named permission, returning false if the user already had it
+    pub fn grant(conn: &Connection, user_id: &str, permission_name: &str) -> SqliteResult<bool> {
+        let permission_id: String = conn.query_row(
+            "SELECT id FROM permissions WHERE name = ?1",
+            [permission_name],
+            |row| row.get(0),
+        )?;
+        
+        let rows_affected = conn.execute(
+            "INSERT OR IGNORE INTO user_permissions (id, user_id, permission_id, granted_at) 
+             VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                user_id,
+                permission_id,
+                Utc::now().to_rfc3339(),
+            ],
+        )?;
+        
+        Ok(rows_affected > 0)
+    }
+    
+    // Removes a direct grant, returning false if the user did not have it. Grants
+    // through roles and groups are unaffected.
+    pub fn revoke(conn: &Connection, user_id: &str, permission_name: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM user_permissions 
+             WHERE user_id = ?1 AND permission_id IN (SELECT id FROM permissions WHERE name = ?2)",
+            [user_id, permission_name],
+        )?;
+        
+        Ok(rows_affected > 0)
+    }
+    
+    // Every defined permission name
+    pub fn all_names(conn: &Connection) -> SqliteResult<HashSet<String>> {
+        let mut stmt = conn.prepare("SELECT name FROM permissions")?;
+        let names = stmt.query_map([], |row| row.get(0))?;
+        names.collect()
+    }
+}
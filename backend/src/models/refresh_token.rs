@@ -0,0 +1,74 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, Result as SqliteResult};
+use uuid::Uuid;
+
+use crate::models::{generate_token, hash_token};
+
+#[derive(Debug, Clone)]
+pub struct RefreshToken {
+    pub id: String,
+    pub session_id: String,
+    pub user_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+impl RefreshToken {
+    // Stores a new refresh token for the session and returns its plaintext value,
+    // which is never persisted
+    pub fn issue(conn: &Connection, session_id: &str, user_id: &str, ttl: Duration) -> SqliteResult<String> {
+        let token = generate_token();
+        let now = Utc::now();
+        
+        conn.execute(
+            "INSERT INTO refresh_tokens (id, session_id, user_id, token_hash, expires_at, created_at) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            rusqlite::params![
+                Uuid::new_v4().to_string(),
+                session_id,
+                user_id,
+                hash_token(&token),
+                (now + ttl).to_rfc3339(),
+                now.to_rfc3339(),
+            ],
+        )?;
+        
+        Ok(token)
+    }
+    
+    pub fn find_by_token(conn: &Connection, token: &str) -> SqliteResult<Option<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT id, session_id, user_id, expires_at, used_at FROM refresh_tokens WHERE token_hash = ?1"
+        )?;
+        
+        let result = stmt.query_row([hash_token(token)], |row| {
+            let used_at: Option<String> = row.get(4)?;
+            Ok(RefreshToken {
+                id: row.get(0)?,
+                session_id: row.get(1)?,
+                user_id: row.get(2)?,
+                expires_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(3)?)
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(3, "expires_at".to_string(), rusqlite::types::Type::Text))?
+                    .with_timezone(&Utc),
+                used_at: used_at
+                    .map(|value| DateTime::parse_from_rfc3339(&value).map(|dt| dt.with_timezone(&Utc)))
+                    .transpose()
+                    .map_err(|_| rusqlite::Error::InvalidColumnType(4, "used_at".to_string(), rusqlite::types::Type::Text))?,
+            })
+        });
+        
+        match result {
+            Ok(refresh_token) => Ok(Some(refresh_token)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub fn mark_used(conn: &Connection, id: &str) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE refresh_tokens SET used_at = ?1 WHERE id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), id],
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+use chrono::{DateTime, Utc};
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+
+// Seeded roles bundling the seeded permissions of the same name
+pub const ADMIN_ROLE: &str = "admin";
+pub const USER_ROLE: &str = "user";
+
+#[derive(Debug, Clone)]
+pub struct Role {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct CreateRoleRequest {
+    pub name: String,
+    pub description: Option<String>,
+    /// Names of the permissions the role grants
+    #[serde(default)]
+    pub permissions: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UpdateRoleRequest {
+    pub description: Option<String>,
+    /// Replaces the role's permissions when present
+    pub permissions: Option<Vec<String>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct RoleResponse {
+    pub id: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub permissions: Vec<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+impl RoleResponse {
+    pub fn new(role: Role, permissions: Vec<String>) -> Self {
+        RoleResponse {
+            id: role.id,
+            name: role.name,
+            description: role.description,
+            permissions,
+            created_at: role.created_at,
+        }
+    }
+}
+
+const ROLE_COLUMNS: &str = "id, name, description, created_at";
+
+impl Role {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(Role {
+            id: row.get(0)?,
+            name: row.get(1)?,
+            description: row.get(2)?,
+            created_at: parse_timestamp(row, 3, "created_at")?,
+        })
+    }
+    
+    // Creates the role together with its permissions, or nothing at all
+    pub fn create(conn: &Connection, name: &str, description: Option<&str>, permissions: &[String]) -> SqliteResult<Self> {
+        let role = Role {
+            id: Uuid::new_v4().to_string(),
+            name: name.to_string(),
+            description: description.map(str::to_string),
+            created_at: Utc::now(),
+        };
+        
+        let tx = conn.unchecked_transaction()?;
+        tx.execute(
+            "INSERT INTO roles (id, name, description, created_at) VALUES (?1, ?2, ?3, ?4)",
+            rusqlite::params![role.id, role.name, role.description, role.created_at.to_rfc3339()],
+        )?;
+        Self::replace_permissions(&tx, &role.id, permissions)?;
+        tx.commit()?;
+        
+        Ok(role)
+    }
+    
+    pub fn find_all(conn: &Connection) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {ROLE_COLUMNS} FROM roles ORDER BY name"))?;
+        let roles = stmt.query_map([], Self::from_row)?;
+        roles.collect()
+    }
+    
+    pub fn find_by_id(conn: &Connection, role_id: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(&format!("SELECT {ROLE_COLUMNS} FROM roles WHERE id = ?1"), [role_id], Self::from_row)
+            .optional()
+    }
+    
+    pub fn find_by_name(conn: &Connection, name: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(&format!("SELECT {ROLE_COLUMNS} FROM roles WHERE name = ?1"), [name], Self::from_row)
+            .optional()
+    }
+    
+    // Roles assigned to the user directly, not through groups
+    pub fn find_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(
+            "SELECT r.id, r.name, r.description, r.created_at FROM roles r
+             JOIN user_roles ur ON ur.role_id = r.id
+             WHERE ur.user_id = ?1 ORDER BY r.name"
+        )?;
+        let roles = stmt.query_map([user_id], Self::from_row)?;
+        roles.collect()
+    }
+    
+    pub fn set_description(conn: &Connection, role_id: &str, description: Option<&str>) -> SqliteResult<()> {
+        conn.execute("UPDATE roles SET description = ?1 WHERE id = ?2", rusqlite::params![description, role_id])?;
+        Ok(())
+    }
+    
+    // Applies whichever of the description and permissions are given, all or nothing
+    pub fn update(
+        conn: &Connection,
+        role_id: &str,
+        description: Option<&str>,
+        permissions: Option<&[String]>,
+    ) -> SqliteResult<()> {
+        let tx = conn.unchecked_transaction()?;
+        if let Some(description) = description {
+            Self::set_description(&tx, role_id, Some(description))?;
+        }
+        if let Some(permissions) = permissions {
+            Self::replace_permissions(&tx, role_id, permissions)?;
+        }
+        tx.commit()
+    }
+    
+    pub fn permissions(conn: &Connection, role_id: &str) -> SqliteResult<Vec<String>> {
+        let mut stmt = conn.prepare(
+            "SELECT p.name FROM permissions p
+             JOIN role_permissions rp ON rp.permission_id = p.id
+             WHERE rp.role_id = ?1 ORDER BY p.name"
+        )?;
+        let names = stmt.query_map([role_id], |row| row.get(0))?;
+        names.collect()
+    }
+    
+    // Replaces the role's permissions; names that do not exist are skipped
+    pub fn set_permissions(conn: &Connection, role_id: &str, permissions: &[String]) -> SqliteResult<()> {
+        let tx = conn.unchecked_transaction()?;
+        Self::replace_permissions(&tx, role_id, permissions)?;
+        tx.commit()
+    }
+    
+    // Callers run this inside a transaction so a failed insert cannot leave the
+    // role with only some of its permissions
+    fn replace_permissions(conn: &Connection, role_id: &str, permissions: &[String]) -> SqliteResult<()> {
+        conn.execute("DELETE FROM role_permissions WHERE role_id = ?1", [role_id])?;
+        for permission in permissions {
+            conn.execute(
+                "INSERT OR IGNORE INTO role_permissions (role_id, permission_id)
+                 SELECT ?1, id FROM permissions WHERE name = ?2",
+                [role_id, permission],
+            )?;
+        }
+        Ok(())
+    }
+    
+    // Deletes the role along with every assignment of it
+    pub fn delete(conn: &Connection, role_id: &str) -> SqliteResult<bool> {
+        let tx = conn.unchecked_transaction()?;
+        tx.execute("DELETE FROM role_permissions WHERE role_id = ?1", [role_id])?;
+        tx.execute("DELETE FROM user_roles WHERE role_id = ?1", [role_id])?;
+        tx.execute("DELETE FROM group_roles WHERE role_id = ?1", [role_id])?;
+        let rows_affected = tx.execute("DELETE FROM roles WHERE id = ?1", [role_id])?;
+        tx.commit()?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Returns false if the user already had the role
+    pub fn assign(conn: &Connection, user_id: &str, role_id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "INSERT OR IGNORE INTO user_roles (user_id, role_id, granted_at) VALUES (?1, ?2, ?3)",
+            rusqlite::params![user_id, role_id, Utc::now().to_rfc3339()],
+        )?;
+        Ok(rows_affected > 0)
+    }
+    
+    pub fn unassign(conn: &Connection, user_id: &str, role_id: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "DELETE FROM user_roles WHERE user_id = ?1 AND role_id = ?2",
+            [user_id, role_id],
+        )?;
+        Ok(rows_affected > 0)
+    }
+}
@@ -0,0 +1,184 @@
+use chrono::{DateTime, Duration, Utc};
+use rusqlite::{Connection, Result as SqliteResult};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+#[derive(Debug, Clone)]
+pub struct Session {
+    pub id: String,
+    pub user_id: String,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+// Where a session was opened from, captured at login
+#[derive(Debug, Clone, Default)]
+pub struct ClientInfo {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct SessionResponse {
+    pub id: String,
+    pub created_at: DateTime<Utc>,
+    pub last_seen_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    /// Whether this is the session the request was made with
+    pub current: bool,
+}
+
+impl SessionResponse {
+    pub fn new(session: Session, current_session_id: &str) -> Self {
+        SessionResponse {
+            current: session.id == current_session_id,
+            id: session.id,
+            created_at: session.created_at,
+            last_seen_at: session.last_seen_at,
+            expires_at: session.expires_at,
+            user_agent: session.user_agent,
+            ip_address: session.ip_address,
+        }
+    }
+}
+
+pub(crate) fn parse_timestamp(row: &rusqlite::Row, index: usize, column: &str) -> SqliteResult<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(&row.get::<_, String>(index)?)
+        .map_err(|_| rusqlite::Error::InvalidColumnType(index, column.to_string(), rusqlite::types::Type::Text))
+        .map(|dt| dt.with_timezone(&Utc))
+}
+
+const SESSION_COLUMNS: &str = "id, user_id, expires_at, created_at, last_seen_at, user_agent, ip_address";
+
+impl Session {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        let last_seen_at = match row.get::<_, Option<String>>(4)? {
+            Some(_) => Some(parse_timestamp(row, 4, "last_seen_at")?),
+            None => None,
+        };
+        
+        Ok(Session {
+            id: row.get(0)?,
+            user_id: row.get(1)?,
+            expires_at: parse_timestamp(row, 2, "expires_at")?,
+            created_at: parse_timestamp(row, 3, "created_at")?,
+            last_seen_at,
+            user_agent: row.get(5)?,
+            ip_address: row.get(6)?,
+        })
+    }
+
+    pub fn create(conn: &Connection, user_id: &str, ttl: Duration, client: &ClientInfo) -> SqliteResult<Self> {
+        let session_id = Uuid::new_v4().to_string();
+        let now = Utc::now();
+        let expires_at = now + ttl;
+        
+        conn.execute(
+            "INSERT INTO sessions (id, user_id, expires_at, created_at, last_seen_at, user_agent, ip_address) 
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+            rusqlite::params![
+                session_id,
+                user_id,
+                expires_at.to_rfc3339(),
+                now.to_rfc3339(),
+                now.to_rfc3339(),
+                client.user_agent,
+                client.ip_address,
+            ],
+        )?;
+        
+        Ok(Session {
+            id: session_id,
+            user_id: user_id.to_string(),
+            expires_at,
+            created_at: now,
+            last_seen_at: Some(now),
+            user_agent: client.user_agent.clone(),
+            ip_address: client.ip_address.clone(),
+        })
+    }
+    
+    // Returns the session only if it exists and has not expired yet
+    pub fn find_active(conn: &Connection, session_id: &str) -> SqliteResult<Option<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {SESSION_COLUMNS} FROM sessions WHERE id = ?1"))?;
+        
+        match stmt.query_row([session_id], Self::from_row) {
+            Ok(session) if session.expires_at > Utc::now() => Ok(Some(session)),
+            Ok(_) | Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    pub fn find_active_for_user(conn: &Connection, user_id: &str) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SESSION_COLUMNS} FROM sessions 
+             WHERE user_id = ?1 AND expires_at > ?2 ORDER BY created_at DESC"
+        ))?;
+        
+        let sessions = stmt.query_map(rusqlite::params![user_id, Utc::now().to_rfc3339()], Self::from_row)?;
+        sessions.collect()
+    }
+    
+    pub fn touch(conn: &Connection, session_id: &str) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE sessions SET last_seen_at = ?1 WHERE id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), session_id],
+        )?;
+        Ok(())
+    }
+    
+    // Pushes the expiry out, used when the session's refresh token is rotated
+    pub fn extend(conn: &Connection, session_id: &str, ttl: Duration) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE sessions SET expires_at = ?1 WHERE id = ?2",
+            rusqlite::params![(Utc::now() + ttl).to_rfc3339(), session_id],
+        )?;
+        Ok(())
+    }
+    
+    // Deleting a session also drops its refresh token family
+    pub fn delete(conn: &Connection, session_id: &str) -> SqliteResult<bool> {
+        conn.execute("DELETE FROM refresh_tokens WHERE session_id = ?1", [session_id])?;
+        let rows_affected = conn.execute("DELETE FROM sessions WHERE id = ?1", [session_id])?;
+        Ok(rows_affected > 0)
+    }
+    
+    // Like `delete`, but only if the session belongs to the given user
+    pub fn delete_for_user(conn: &Connection, user_id: &str, session_id: &str) -> SqliteResult<bool> {
+        let owned: bool = conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM sessions WHERE id = ?1 AND user_id = ?2)",
+            [session_id, user_id],
+            |row| row.get(0),
+        )?;
+        
+        if !owned {
+            return Ok(false);
+        }
+        Self::delete(conn, session_id)
+    }
+    
+    // Signs the user out everywhere
+    pub fn delete_all_for_user(conn: &Connection, user_id: &str) -> SqliteResult<usize> {
+        conn.execute("DELETE FROM refresh_tokens WHERE user_id = ?1", [user_id])?;
+        conn.execute("DELETE FROM sessions WHERE user_id = ?1", [user_id])
+    }
+    
+    pub fn delete_expired_for_user(conn: &Connection, user_id: &str) -> SqliteResult<usize> {
+        let now = Utc::now().to_rfc3339();
+        conn.execute(
+            "DELETE FROM refresh_tokens WHERE session_id IN 
+             (SELECT id FROM sessions WHERE user_id = ?1 AND expires_at <= ?2)",
+            rusqlite::params![user_id, now],
+        )?;
+        conn.execute(
+            "DELETE FROM sessions WHERE user_id = ?1 AND expires_at <= ?2",
+            rusqlite::params![user_id, now],
+        )
+    }
+}
@@ -0,0 +1,260 @@
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, Duration, Utc};
+use ed25519_dalek::pkcs8::EncodePrivateKey;
+use jsonwebtoken::jwk::Jwk;
+use jsonwebtoken::{Algorithm, EncodingKey};
+use rand::RngCore;
+use rsa::pkcs1::EncodeRsaPrivateKey;
+use rsa::traits::PublicKeyParts;
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use serde::Serialize;
+use serde_json::json;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::models::session::parse_timestamp;
+use crate::models::{ACCESS_TOKEN_TTL_MINUTES, IMPERSONATION_TTL_MINUTES};
+
+// How long a retired key keeps verifying: the lifetime of the longest-lived token
+// it may have signed
+pub const RETIRED_KEY_GRACE_MINUTES: i64 = if ACCESS_TOKEN_TTL_MINUTES > IMPERSONATION_TTL_MINUTES {
+    ACCESS_TOKEN_TTL_MINUTES
+} else {
+    IMPERSONATION_TTL_MINUTES
+};
+
+// How long verifiers may cache the JWKS. A new key is published for this long
+// before it signs, so nobody sees a token whose key they have not fetched yet.
+pub const JWKS_MAX_AGE_SECONDS: i64 = 300;
+
+const RSA_KEY_BITS: usize = 2048;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyAlgorithm {
+    EdDsa,
+    Rs256,
+}
+
+impl KeyAlgorithm {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            KeyAlgorithm::EdDsa => "EdDSA",
+            KeyAlgorithm::Rs256 => "RS256",
+        }
+    }
+    
+    // Accepts the JOSE names case-insensitively, e.g. for the CLI
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_uppercase().as_str() {
+            "EDDSA" => Some(KeyAlgorithm::EdDsa),
+            "RS256" => Some(KeyAlgorithm::Rs256),
+            _ => None,
+        }
+    }
+    
+    pub(crate) fn jwt_algorithm(&self) -> Algorithm {
+        match self {
+            KeyAlgorithm::EdDsa => Algorithm::EdDSA,
+            KeyAlgorithm::Rs256 => Algorithm::RS256,
+        }
+    }
+}
+
+// Why a token could not be signed or verified
+#[derive(Debug)]
+pub enum TokenError {
+    Database(rusqlite::Error),
+    Jwt(jsonwebtoken::errors::Error),
+    UnknownKey,
+}
+
+impl fmt::Display for TokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenError::Database(e) => write!(f, "database error: {e}"),
+            TokenError::Jwt(e) => write!(f, "invalid token: {e}"),
+            TokenError::UnknownKey => f.write_str("token was signed with an unknown key"),
+        }
+    }
+}
+
+impl std::error::Error for TokenError {}
+
+impl From<rusqlite::Error> for TokenError {
+    fn from(e: rusqlite::Error) -> Self {
+        TokenError::Database(e)
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for TokenError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        TokenError::Jwt(e)
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct JwksResponse {
+    /// Public keys as JSON Web Keys, matched to tokens by `kid`
+    #[schema(value_type = Vec<Object>)]
+    pub keys: Vec<serde_json::Value>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SigningKey {
+    pub kid: String,
+    pub algorithm: KeyAlgorithm,
+    pub private_key: Vec<u8>,
+    pub public_jwk: String,
+    pub created_at: DateTime<Utc>,
+    pub retired_at: Option<DateTime<Utc>>,
+}
+
+const SIGNING_KEY_COLUMNS: &str = "kid, algorithm, private_key, public_jwk, created_at, retired_at";
+
+impl SigningKey {
+    fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        let algorithm = KeyAlgorithm::parse(&row.get::<_, String>(1)?)
+            .ok_or_else(|| rusqlite::Error::InvalidColumnType(1, "algorithm".to_string(), rusqlite::types::Type::Text))?;
+        let retired_at = match row.get::<_, Option<String>>(5)? {
+            Some(_) => Some(parse_timestamp(row, 5, "retired_at")?),
+            None => None,
+        };
+        
+        Ok(SigningKey {
+            kid: row.get(0)?,
+            algorithm,
+            private_key: row.get(2)?,
+            public_jwk: row.get(3)?,
+            created_at: parse_timestamp(row, 4, "created_at")?,
+            retired_at,
+        })
+    }
+    
+    // Generates a key pair and stores it. It is published straight away and signs
+    // once it has been for `JWKS_MAX_AGE_SECONDS`, or at once if it is the only key.
+    pub fn generate(conn: &Connection, algorithm: KeyAlgorithm) -> Result<Self, Box<dyn std::error::Error>> {
+        let kid = Uuid::new_v4().to_string();
+        let (private_key, public_jwk) = match algorithm {
+            KeyAlgorithm::EdDsa => {
+                let mut seed = [0u8; 32];
+                rand::rng().fill_bytes(&mut seed);
+                let key = ed25519_dalek::SigningKey::from_bytes(&seed);
+                let jwk = json!({
+                    "kty": "OKP",
+                    "crv": "Ed25519",
+                    "x": URL_SAFE_NO_PAD.encode(key.verifying_key().to_bytes()),
+                });
+                (key.to_pkcs8_der()?.as_bytes().to_vec(), jwk)
+            }
+            KeyAlgorithm::Rs256 => {
+                let key = rsa::RsaPrivateKey::new(&mut rsa::rand_core::OsRng, RSA_KEY_BITS)?;
+                let jwk = json!({
+                    "kty": "RSA",
+                    "n": URL_SAFE_NO_PAD.encode(key.n().to_bytes_be()),
+                    "e": URL_SAFE_NO_PAD.encode(key.e().to_bytes_be()),
+                });
+                (key.to_pkcs1_der()?.as_bytes().to_vec(), jwk)
+            }
+        };
+        
+        let mut public_jwk = public_jwk;
+        public_jwk["kid"] = json!(kid);
+        public_jwk["alg"] = json!(algorithm.as_str());
+        public_jwk["use"] = json!("sig");
+        let public_jwk = public_jwk.to_string();
+        let now = Utc::now();
+        
+        conn.execute(
+            "INSERT INTO signing_keys (kid, algorithm, private_key, public_jwk, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            rusqlite::params![kid, algorithm.as_str(), private_key, public_jwk, now.to_rfc3339()],
+        )?;
+        
+        Ok(SigningKey {
+            kid,
+            algorithm,
+            private_key,
+            public_jwk,
+            created_at: now,
+            retired_at: None,
+        })
+    }
+    
+    pub fn find_by_kid(conn: &Connection, kid: &str) -> SqliteResult<Option<Self>> {
+        conn.query_row(
+            &format!("SELECT {SIGNING_KEY_COLUMNS} FROM signing_keys WHERE kid = ?1"),
+            [kid],
+            Self::from_row,
+        )
+        .optional()
+    }
+    
+    pub fn find_all(conn: &Connection) -> SqliteResult<Vec<Self>> {
+        let mut stmt = conn.prepare(&format!("SELECT {SIGNING_KEY_COLUMNS} FROM signing_keys ORDER BY created_at"))?;
+        let keys = stmt.query_map([], Self::from_row)?;
+        keys.collect()
+    }
+    
+    // When a key starts signing, unless it is the only one
+    pub fn signs_from(&self) -> DateTime<Utc> {
+        self.created_at + Duration::seconds(JWKS_MAX_AGE_SECONDS)
+    }
+    
+    // The key new tokens are signed with, if any keys have been generated: the
+    // newest unretired key that has been published long enough, falling back to
+    // the newest one while none has. Keys retired by a rotation that has not
+    // taken effect yet still count as unretired.
+    pub fn current(conn: &Connection) -> SqliteResult<Option<Self>> {
+        let now = Utc::now();
+        let published = now - Duration::seconds(JWKS_MAX_AGE_SECONDS);
+        conn.query_row(
+            &format!(
+                "SELECT {SIGNING_KEY_COLUMNS} FROM signing_keys WHERE retired_at IS NULL OR retired_at > ?1
+                 ORDER BY created_at <= ?2 DESC, created_at DESC LIMIT 1"
+            ),
+            [now.to_rfc3339(), published.to_rfc3339()],
+            Self::from_row,
+        )
+        .optional()
+    }
+    
+    // Keys tokens may still carry: unretired ones and those retired within the grace period
+    pub fn verification_keys(conn: &Connection) -> SqliteResult<Vec<Self>> {
+        let cutoff = Utc::now() - Duration::minutes(RETIRED_KEY_GRACE_MINUTES);
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {SIGNING_KEY_COLUMNS} FROM signing_keys WHERE retired_at IS NULL OR retired_at > ?1
+             ORDER BY created_at DESC"
+        ))?;
+        let keys = stmt.query_map([cutoff.to_rfc3339()], Self::from_row)?;
+        keys.collect()
+    }
+    
+    // Retires every key but `kid` once `kid` starts signing, and deletes keys whose
+    // grace period has passed. Returns how many keys were retired.
+    pub fn retire_all_except(conn: &Connection, kid: &str) -> SqliteResult<usize> {
+        let now = Utc::now();
+        let cutoff = now - Duration::minutes(RETIRED_KEY_GRACE_MINUTES);
+        conn.execute("DELETE FROM signing_keys WHERE retired_at <= ?1", [cutoff.to_rfc3339()])?;
+        
+        let Some(key) = Self::find_by_kid(conn, kid)? else {
+            return Ok(0);
+        };
+        let retired_at = key.signs_from().max(now);
+        conn.execute(
+            "UPDATE signing_keys SET retired_at = ?1 WHERE kid != ?2 AND (retired_at IS NULL OR retired_at > ?1)",
+            [retired_at.to_rfc3339().as_str(), kid],
+        )
+    }
+    
+    pub fn jwk(&self) -> Result<Jwk, serde_json::Error> {
+        serde_json::from_str(&self.public_jwk)
+    }
+    
+    pub(crate) fn encoding_key(&self) -> EncodingKey {
+        match self.algorithm {
+            KeyAlgorithm::EdDsa => EncodingKey::from_ed_der(&self.private_key),
+            KeyAlgorithm::Rs256 => EncodingKey::from_rsa_der(&self.private_key),
+        }
+    }
+}
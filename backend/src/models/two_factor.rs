@@ -0,0 +1,165 @@
+use chrono::Utc;
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use percent_encoding::{utf8_percent_encode, NON_ALPHANUMERIC};
+use rand::RngCore;
+use rusqlite::{Connection, OptionalExtension, Result as SqliteResult};
+use sha1::Sha1;
+use uuid::Uuid;
+
+use crate::models::hash_token;
+
+pub const TOTP_ISSUER: &str = "Surjo";
+const TOTP_STEP_SECONDS: i64 = 30;
+const TOTP_DIGITS: u32 = 6;
+// Codes from one step either side are accepted to allow for clock drift
+const TOTP_ALLOWED_DRIFT_STEPS: i64 = 1;
+const RECOVERY_CODE_COUNT: usize = 10;
+
+// RFC 6238 time-based one-time password over HMAC-SHA1, as used by common authenticator apps
+pub fn totp_code(secret: &[u8], step: i64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(secret).expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+    
+    // Dynamic truncation from RFC 4226
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = u32::from_be_bytes([digest[offset], digest[offset + 1], digest[offset + 2], digest[offset + 3]])
+        & 0x7fff_ffff;
+    binary % 10u32.pow(TOTP_DIGITS)
+}
+
+pub fn current_totp_step() -> i64 {
+    Utc::now().timestamp() / TOTP_STEP_SECONDS
+}
+
+// Returns the step the code matches, if any, within the allowed drift
+fn matching_step(secret: &[u8], code: &str, now_step: i64) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != TOTP_DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let code: u32 = code.parse().ok()?;
+    
+    (now_step - TOTP_ALLOWED_DRIFT_STEPS..=now_step + TOTP_ALLOWED_DRIFT_STEPS)
+        .find(|step| totp_code(secret, *step) == code)
+}
+
+pub fn otpauth_uri(secret: &str, account: &str) -> String {
+    let label = utf8_percent_encode(&format!("{TOTP_ISSUER}:{account}"), NON_ALPHANUMERIC).to_string();
+    format!(
+        "otpauth://totp/{label}?secret={secret}&issuer={TOTP_ISSUER}&algorithm=SHA1&digits={TOTP_DIGITS}&period={TOTP_STEP_SECONDS}"
+    )
+}
+
+pub struct TwoFactor;
+
+impl TwoFactor {
+    // Starts (or restarts) enrollment with a fresh base32 secret. The secret only
+    // takes effect once `confirm` sees a valid code for it.
+    pub fn begin_enrollment(conn: &Connection, user_id: &str) -> SqliteResult<String> {
+        let mut bytes = [0u8; 20];
+        rand::rng().fill_bytes(&mut bytes);
+        let secret = BASE32_NOPAD.encode(&bytes);
+        
+        conn.execute(
+            "INSERT OR REPLACE INTO user_totp (user_id, secret, confirmed_at, last_used_step, created_at) 
+             VALUES (?1, ?2, NULL, NULL, ?3)",
+            rusqlite::params![user_id, secret, Utc::now().to_rfc3339()],
+        )?;
+        
+        Ok(secret)
+    }
+    
+    pub fn is_enabled(conn: &Connection, user_id: &str) -> SqliteResult<bool> {
+        conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM user_totp WHERE user_id = ?1 AND confirmed_at IS NOT NULL)",
+            [user_id],
+            |row| row.get(0),
+        )
+    }
+    
+    // Checks a code against the pending secret and enables 2FA if it matches
+    pub fn confirm(conn: &Connection, user_id: &str, code: &str) -> SqliteResult<bool> {
+        if !Self::check_code(conn, user_id, code, false)? {
+            return Ok(false);
+        }
+        
+        conn.execute(
+            "UPDATE user_totp SET confirmed_at = ?1 WHERE user_id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), user_id],
+        )?;
+        Ok(true)
+    }
+    
+    // Verifies a code for an enabled secret; each code can only be used once
+    pub fn verify(conn: &Connection, user_id: &str, code: &str) -> SqliteResult<bool> {
+        Self::check_code(conn, user_id, code, true)
+    }
+    
+    fn check_code(conn: &Connection, user_id: &str, code: &str, confirmed: bool) -> SqliteResult<bool> {
+        let row: Option<(String, Option<i64>)> = conn
+            .query_row(
+                "SELECT secret, last_used_step FROM user_totp 
+                 WHERE user_id = ?1 AND (confirmed_at IS NOT NULL) = ?2",
+                rusqlite::params![user_id, confirmed],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+        
+        let Some((secret, last_used_step)) = row else {
+            return Ok(false);
+        };
+        let Ok(secret) = BASE32_NOPAD.decode(secret.as_bytes()) else {
+            return Ok(false);
+        };
+        
+        match matching_step(&secret, code, current_totp_step()) {
+            Some(step) if last_used_step.is_none_or(|last| step > last) => {
+                conn.execute(
+                    "UPDATE user_totp SET last_used_step = ?1 WHERE user_id = ?2",
+                    rusqlite::params![step, user_id],
+                )?;
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+    
+    pub fn disable(conn: &Connection, user_id: &str) -> SqliteResult<()> {
+        conn.execute("DELETE FROM user_totp WHERE user_id = ?1", [user_id])?;
+        conn.execute("DELETE FROM recovery_codes WHERE user_id = ?1", [user_id])?;
+        Ok(())
+    }
+    
+    // Replaces the user's recovery codes and returns the new plaintext codes
+    pub fn generate_recovery_codes(conn: &Connection, user_id: &str) -> SqliteResult<Vec<String>> {
+        conn.execute("DELETE FROM recovery_codes WHERE user_id = ?1", [user_id])?;
+        
+        let now = Utc::now().to_rfc3339();
+        let mut codes = Vec::with_capacity(RECOVERY_CODE_COUNT);
+        for _ in 0..RECOVERY_CODE_COUNT {
+            let mut bytes = [0u8; 10];
+            rand::rng().fill_bytes(&mut bytes);
+            let raw = BASE32_NOPAD.encode(&bytes).to_lowercase();
+            let code = format!("{}-{}-{}-{}", &raw[0..4], &raw[4..8], &raw[8..12], &raw[12..16]);
+            
+            conn.execute(
+                "INSERT INTO recovery_codes (id, user_id, code_hash, created_at) VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![Uuid::new_v4().to_string(), user_id, hash_token(&code), now],
+            )?;
+            codes.push(code);
+        }
+        
+        Ok(codes)
+    }
+    
+    pub fn consume_recovery_code(conn: &Connection, user_id: &str, code: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "UPDATE recovery_codes SET used_at = ?1 
+             WHERE user_id = ?2 AND code_hash = ?3 AND used_at IS NULL",
+            rusqlite::params![Utc::now().to_rfc3339(), user_id, hash_token(&code.trim().to_lowercase())],
+        )?;
+        Ok(rows_affected > 0)
+    }
+}
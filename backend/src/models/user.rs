@@ -1,9 +1,15 @@
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use utoipa::ToSchema;
-use rusqlite::{Connection, Result as SqliteResult};
+use utoipa::{IntoParams, ToSchema};
+use rusqlite::{types::ToSql, Connection, Result as SqliteResult};
 use uuid::Uuid;
 
+use crate::models::{ApiKey, Passkey, Permission, Session, TwoFactor};
+
+pub const USER_PAGE_SIZE: i64 = 50;
+pub const MAX_USER_PAGE_SIZE: i64 = 200;
+
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct User {
     pub id: String,
@@ -11,6 +17,7 @@ pub struct User {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub is_active: bool,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -36,6 +43,8 @@ pub struct UserResponse {
     pub first_name: Option<String>,
     pub last_name: Option<String>,
     pub is_active: bool,
+    pub email_verified: bool,
+    pub email_verified_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -48,17 +57,178 @@ impl From<User> for UserResponse {
             first_name: user.first_name,
             last_name: user.last_name,
             is_active: user.is_active,
+            email_verified: user.email_verified_at.is_some(),
+            email_verified_at: user.email_verified_at,
             created_at: user.created_at,
             updated_at: user.updated_at,
         }
     }
 }
 
+// Columns `GET /api/users` may sort by. Only non-null columns qualify, since the
+// cursor compares against the last row's value.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UserSortField {
+    #[default]
+    CreatedAt,
+    UpdatedAt,
+    Email,
+}
+
+impl UserSortField {
+    fn column(&self) -> &'static str {
+        match self {
+            UserSortField::CreatedAt => "created_at",
+            UserSortField::UpdatedAt => "updated_at",
+            UserSortField::Email => "email",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    Asc,
+    #[default]
+    Desc,
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct UserListQuery {
+    /// Page size, at most 200
+    pub limit: Option<i64>,
+    /// `next_cursor` from the previous page, requested with the same sort and order
+    pub cursor: Option<String>,
+    pub is_active: Option<bool>,
+    /// Case-insensitive substring of the email address
+    pub email: Option<String>,
+    /// Only users created at or after this time
+    pub created_after: Option<DateTime<Utc>>,
+    /// Only users created before this time
+    pub created_before: Option<DateTime<Utc>>,
+    /// Only users holding this permission, directly or through a role or group
+    pub permission: Option<String>,
+    #[serde(default)]
+    #[param(inline)]
+    pub sort: UserSortField,
+    #[serde(default)]
+    #[param(inline)]
+    pub order: SortOrder,
+    /// Also count every matching user
+    #[serde(default)]
+    pub include_total: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct UserPage {
+    pub users: Vec<UserResponse>,
+    /// Pass as `cursor` to fetch the next page; absent on the last page
+    pub next_cursor: Option<String>,
+    /// Number of users matching the filters, when `include_total` was set
+    pub total: Option<i64>,
+}
+
+// Position after the last row of a page. Opaque to clients; it carries the sort
+// so a cursor cannot be replayed against a different ordering.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UserCursor {
+    sort: UserSortField,
+    order: SortOrder,
+    value: String,
+    id: String,
+}
+
+impl UserCursor {
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).unwrap_or_default())
+    }
+    
+    // None for a malformed cursor or one issued for a different sort
+    pub fn decode(cursor: &str, query: &UserListQuery) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let cursor: UserCursor = serde_json::from_slice(&bytes).ok()?;
+        (cursor.sort == query.sort && cursor.order == query.order).then_some(cursor)
+    }
+}
+
+impl UserListQuery {
+    fn where_clause(&self, cursor: Option<&UserCursor>) -> (String, Vec<Box<dyn ToSql>>) {
+        let mut conditions: Vec<String> = Vec::new();
+        let mut params: Vec<Box<dyn ToSql>> = Vec::new();
+        
+        if let Some(is_active) = self.is_active {
+            conditions.push("is_active = ?".to_string());
+            params.push(Box::new(is_active));
+        }
+        if let Some(email) = &self.email {
+            let escaped = email.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_");
+            conditions.push("email LIKE ? ESCAPE '\\'".to_string());
+            params.push(Box::new(format!("%{escaped}%")));
+        }
+        // Compared as instants rather than text, which differs in precision
+        if let Some(created_after) = self.created_after {
+            conditions.push("julianday(created_at) >= julianday(?)".to_string());
+            params.push(Box::new(created_after.to_rfc3339()));
+        }
+        if let Some(created_before) = self.created_before {
+            conditions.push("julianday(created_at) < julianday(?)".to_string());
+            params.push(Box::new(created_before.to_rfc3339()));
+        }
+        if let Some(permission) = &self.permission {
+            conditions.push(format!("id IN ({})", Permission::HOLDERS_SQL));
+            for _ in 0..3 {
+                params.push(Box::new(permission.clone()));
+            }
+        }
+        if let Some(cursor) = cursor {
+            let operator = match self.order {
+                SortOrder::Asc => ">",
+                SortOrder::Desc => "<",
+            };
+            conditions.push(format!("({}, id) {operator} (?, ?)", self.sort.column()));
+            params.push(Box::new(cursor.value.clone()));
+            params.push(Box::new(cursor.id.clone()));
+        }
+        
+        if conditions.is_empty() {
+            (String::new(), params)
+        } else {
+            (format!("WHERE {}", conditions.join(" AND ")), params)
+        }
+    }
+}
+
+// Column list understood by `User::from_row`
+pub(crate) const USER_COLUMNS: &str = "id, email, first_name, last_name, is_active, created_at, updated_at, email_verified_at";
+
 impl User {
+    pub(crate) fn from_row(row: &rusqlite::Row) -> SqliteResult<Self> {
+        Ok(User {
+            id: row.get(0)?,
+            email: row.get(1)?,
+            first_name: row.get(2)?,
+            last_name: row.get(3)?,
+            is_active: row.get(4)?,
+            created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
+                .map_err(|_| rusqlite::Error::InvalidColumnType(5, "created_at".to_string(), rusqlite::types::Type::Text))?
+                .with_timezone(&Utc),
+            updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
+                .map_err(|_| rusqlite::Error::InvalidColumnType(6, "updated_at".to_string(), rusqlite::types::Type::Text))?
+                .with_timezone(&Utc),
+            email_verified_at: row
+                .get::<_, Option<String>>(7)?
+                .map(|value| DateTime::parse_from_rfc3339(&value).map(|dt| dt.with_timezone(&Utc)))
+                .transpose()
+                .map_err(|_| rusqlite::Error::InvalidColumnType(7, "email_verified_at".to_string(), rusqlite::types::Type::Text))?,
+        })
+    }
+
     pub fn create(
         conn: &Connection,
         email: &str,
-        password_hash: &str,
+        password_hash: Option<&str>,
         first_name: Option<&str>,
         last_name: Option<&str>,
     ) -> SqliteResult<Self> {
@@ -86,32 +256,16 @@ impl User {
             first_name: first_name.map(|s| s.to_string()),
             last_name: last_name.map(|s| s.to_string()),
             is_active: true,
+            email_verified_at: None,
             created_at: now,
             updated_at: now,
         })
     }
     
     pub fn find_by_id(conn: &Connection, user_id: &str) -> SqliteResult<Option<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, email, first_name, last_name, is_active, created_at, updated_at 
-             FROM users WHERE id = ?1"
-        )?;
+        let mut stmt = conn.prepare(&format!("SELECT {USER_COLUMNS} FROM users WHERE id = ?1"))?;
         
-        let user_result = stmt.query_row([user_id], |row| {
-            Ok(User {
-                id: row.get(0)?,
-                email: row.get(1)?,
-                first_name: row.get(2)?,
-                last_name: row.get(3)?,
-                is_active: row.get(4)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "created_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(6, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-            })
-        });
+        let user_result = stmt.query_row([user_id], Self::from_row);
         
         match user_result {
             Ok(user) => Ok(Some(user)),
@@ -120,33 +274,127 @@ impl User {
         }
     }
     
-    pub fn find_all(conn: &Connection) -> SqliteResult<Vec<Self>> {
-        let mut stmt = conn.prepare(
-            "SELECT id, email, first_name, last_name, is_active, created_at, updated_at 
-             FROM users ORDER BY created_at DESC"
-        )?;
+    pub fn find_by_email(conn: &Connection, email: &str) -> SqliteResult<Option<Self>> {
+        Ok(Self::find_by_email_with_password(conn, email)?.map(|(user, _)| user))
+    }
+    
+    // Returns the user together with their password hash, which is NULL for
+    // accounts that only sign in through an external provider
+    pub fn find_by_email_with_password(
+        conn: &Connection,
+        email: &str,
+    ) -> SqliteResult<Option<(Self, Option<String>)>> {
+        let mut stmt = conn.prepare(&format!("SELECT {USER_COLUMNS}, password_hash FROM users WHERE email = ?1"))?;
         
-        let user_iter = stmt.query_map([], |row| {
-            Ok(User {
-                id: row.get(0)?,
-                email: row.get(1)?,
-                first_name: row.get(2)?,
-                last_name: row.get(3)?,
-                is_active: row.get(4)?,
-                created_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(5)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(5, "created_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-                updated_at: DateTime::parse_from_rfc3339(&row.get::<_, String>(6)?)
-                    .map_err(|_| rusqlite::Error::InvalidColumnType(6, "updated_at".to_string(), rusqlite::types::Type::Text))?
-                    .with_timezone(&Utc),
-            })
+        let result = stmt.query_row([email], |row| {
+            Ok((Self::from_row(row)?, row.get::<_, Option<String>>(8)?))
+        });
+        
+        match result {
+            Ok(found) => Ok(Some(found)),
+            Err(rusqlite::Error::QueryReturnedNoRows) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+    
+    // One page of users matching the query, and the cursor for the next page if
+    // there is one. Ties on the sort column are broken by id.
+    pub fn find_page(
+        conn: &Connection,
+        query: &UserListQuery,
+        cursor: Option<&UserCursor>,
+        limit: i64,
+    ) -> SqliteResult<(Vec<Self>, Option<UserCursor>)> {
+        let (where_clause, params) = query.where_clause(cursor);
+        let column = query.sort.column();
+        let direction = match query.order {
+            SortOrder::Asc => "ASC",
+            SortOrder::Desc => "DESC",
+        };
+        
+        // One extra row tells whether another page follows
+        let mut stmt = conn.prepare(&format!(
+            "SELECT {USER_COLUMNS}, {column} FROM users {where_clause}
+             ORDER BY {column} {direction}, id {direction} LIMIT {}",
+            limit + 1
+        ))?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(params), |row| {
+            Ok((Self::from_row(row)?, row.get::<_, String>(8)?))
         })?;
+        let mut rows = rows.collect::<SqliteResult<Vec<_>>>()?;
+        
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+        let next_cursor = match rows.last() {
+            Some((user, value)) if has_more => Some(UserCursor {
+                sort: query.sort,
+                order: query.order,
+                value: value.clone(),
+                id: user.id.clone(),
+            }),
+            _ => None,
+        };
         
-        let mut users = Vec::new();
-        for user in user_iter {
-            users.push(user?);
+        Ok((rows.into_iter().map(|(user, _)| user).collect(), next_cursor))
+    }
+    
+    // Number of users matching the query's filters, across all pages
+    pub fn count(conn: &Connection, query: &UserListQuery) -> SqliteResult<i64> {
+        let (where_clause, params) = query.where_clause(None);
+        conn.query_row(
+            &format!("SELECT COUNT(*) FROM users {where_clause}"),
+            rusqlite::params_from_iter(params),
+            |row| row.get(0),
+        )
+    }
+    
+    // Records that the user proved ownership of their email; keeps the first timestamp
+    pub fn mark_email_verified(conn: &Connection, user_id: &str) -> SqliteResult<()> {
+        conn.execute(
+            "UPDATE users SET email_verified_at = COALESCE(email_verified_at, ?1) WHERE id = ?2",
+            rusqlite::params![Utc::now().to_rfc3339(), user_id],
+        )?;
+        Ok(())
+    }
+    
+    // Verifies the address for someone who has just proved they own it. If it was
+    // not verified yet, whoever set the account up never did, so every way they
+    // could have kept into it goes: password, sessions, API keys, passkeys, 2FA
+    // and linked identities. Returns whether anything had to be taken away.
+    pub fn claim_unverified(conn: &Connection, user_id: &str) -> SqliteResult<bool> {
+        let tx = conn.unchecked_transaction()?;
+        let now = Utc::now().to_rfc3339();
+        let claimed = tx.execute(
+            "UPDATE users SET email_verified_at = ?1, password_hash = NULL, updated_at = ?1
+             WHERE id = ?2 AND email_verified_at IS NULL",
+            rusqlite::params![now, user_id],
+        )? > 0;
+        if claimed {
+            Session::delete_all_for_user(&tx, user_id)?;
+            ApiKey::delete_all_for_user(&tx, user_id)?;
+            Passkey::delete_all_for_user(&tx, user_id)?;
+            TwoFactor::disable(&tx, user_id)?;
+            tx.execute("DELETE FROM oauth_providers WHERE user_id = ?1", [user_id])?;
         }
-        Ok(users)
+        tx.commit()?;
+        Ok(claimed)
+    }
+    
+    // False for accounts that only sign in through external providers or passkeys
+    pub fn has_password(conn: &Connection, user_id: &str) -> SqliteResult<bool> {
+        conn.query_row(
+            "SELECT password_hash IS NOT NULL FROM users WHERE id = ?1",
+            [user_id],
+            |row| row.get(0),
+        )
+    }
+    
+    pub fn set_password_hash(conn: &Connection, user_id: &str, password_hash: &str) -> SqliteResult<bool> {
+        let rows_affected = conn.execute(
+            "UPDATE users SET password_hash = ?1, updated_at = ?2 WHERE id = ?3",
+            rusqlite::params![password_hash, Utc::now().to_rfc3339(), user_id],
+        )?;
+        Ok(rows_affected > 0)
     }
     
     pub fn update(
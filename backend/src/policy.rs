@@ -0,0 +1,83 @@
+use std::collections::HashSet;
+
+use rusqlite::{Connection, Result as SqliteResult};
+
+use crate::models::{Group, ADMIN_PERMISSION};
+
+// Authorization rules that depend on who is acting on which record, layered over
+// the global permissions checked by `AuthUser::require_permission`. A policy is a
+// pure function of an `Actor` and a loaded resource so it can be tested without a
+// request; handlers go through `AuthUser::authorize`, which loads both and turns a
+// denial into the usual 403.
+pub trait Policy {
+    type Action: Copy;
+    type Resource;
+    
+    fn load(conn: &Connection, resource_id: &str) -> SqliteResult<Self::Resource>;
+    
+    fn can(actor: &Actor, action: Self::Action, resource: &Self::Resource) -> bool;
+}
+
+// The caller as policies see it
+#[derive(Debug, Clone, Default)]
+pub struct Actor {
+    pub user_id: String,
+    pub permissions: HashSet<String>,
+    // Groups the caller manages
+    pub managed_groups: HashSet<String>,
+}
+
+impl Actor {
+    pub fn load(conn: &Connection, user_id: &str, permissions: HashSet<String>) -> SqliteResult<Self> {
+        Ok(Actor {
+            user_id: user_id.to_string(),
+            permissions,
+            managed_groups: Group::ids_for_user(conn, user_id, true)?,
+        })
+    }
+    
+    pub fn is_admin(&self) -> bool {
+        self.permissions.contains(ADMIN_PERMISSION)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserAction {
+    Read,
+    Update,
+}
+
+// A user account and the groups it belongs to. Loading never fails for a missing
+// user, so callers who may not see the account get a 403 rather than a 404 that
+// would reveal whether it exists.
+#[derive(Debug, Clone, Default)]
+pub struct UserResource {
+    pub id: String,
+    pub groups: HashSet<String>,
+}
+
+// Admins may act on anyone, users on themselves, and group managers on the
+// members of the groups they manage
+pub struct UserPolicy;
+
+impl Policy for UserPolicy {
+    type Action = UserAction;
+    type Resource = UserResource;
+    
+    fn load(conn: &Connection, user_id: &str) -> SqliteResult<UserResource> {
+        Ok(UserResource {
+            id: user_id.to_string(),
+            groups: Group::ids_for_user(conn, user_id, false)?,
+        })
+    }
+    
+    fn can(actor: &Actor, action: UserAction, user: &UserResource) -> bool {
+        match action {
+            UserAction::Read | UserAction::Update => {
+                actor.is_admin()
+                    || actor.user_id == user.id
+                    || !actor.managed_groups.is_disjoint(&user.groups)
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+// Server side of the WebAuthn registration and authentication ceremonies.
+//
+// Only what passkeys need is supported: attestation is requested as "none" and not
+// verified, and credentials must use ES256 (COSE algorithm -7), which every
+// platform authenticator offers.
+
+use std::fmt;
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::models::WebAuthnConfig;
+
+pub const ES256: i64 = -7;
+
+const FLAG_USER_PRESENT: u8 = 0x01;
+const FLAG_USER_VERIFIED: u8 = 0x04;
+const FLAG_ATTESTED_CREDENTIAL_DATA: u8 = 0x40;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WebAuthnError {
+    Encoding,
+    ClientDataType,
+    Origin,
+    RelyingParty,
+    UserNotVerified,
+    MissingCredential,
+    UnsupportedKey,
+    Signature,
+    SignCount,
+}
+
+impl fmt::Display for WebAuthnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            WebAuthnError::Encoding => "Malformed WebAuthn response",
+            WebAuthnError::ClientDataType => "Unexpected client data type",
+            WebAuthnError::Origin => "Origin does not match",
+            WebAuthnError::RelyingParty => "Relying party does not match",
+            WebAuthnError::UserNotVerified => "User verification is required",
+            WebAuthnError::MissingCredential => "No credential in attestation",
+            WebAuthnError::UnsupportedKey => "Only ES256 credentials are supported",
+            WebAuthnError::Signature => "Invalid signature",
+            WebAuthnError::SignCount => "Signature counter did not increase",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for WebAuthnError {}
+
+pub fn decode_base64url(value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    URL_SAFE_NO_PAD
+        .decode(value.trim_end_matches('='))
+        .map_err(|_| WebAuthnError::Encoding)
+}
+
+// The `CollectedClientData` the browser signs over
+#[derive(Debug, Deserialize)]
+pub struct ClientData {
+    #[serde(rename = "type")]
+    pub ceremony: String,
+    pub challenge: String,
+    pub origin: String,
+}
+
+impl ClientData {
+    // Parses the client data and checks its type and origin. The challenge is left
+    // to the caller, which has to redeem it against what it issued.
+    pub fn parse(client_data_json: &[u8], expected_type: &str, config: &WebAuthnConfig) -> Result<Self, WebAuthnError> {
+        let client_data: ClientData =
+            serde_json::from_slice(client_data_json).map_err(|_| WebAuthnError::Encoding)?;
+        
+        if client_data.ceremony != expected_type {
+            return Err(WebAuthnError::ClientDataType);
+        }
+        if client_data.origin != config.origin {
+            return Err(WebAuthnError::Origin);
+        }
+        Ok(client_data)
+    }
+}
+
+pub const CREATE_CEREMONY: &str = "webauthn.create";
+pub const GET_CEREMONY: &str = "webauthn.get";
+
+struct AuthenticatorData {
+    sign_count: u32,
+    // Credential id and COSE public key, present on registration
+    attested_credential: Option<(Vec<u8>, Value)>,
+}
+
+impl AuthenticatorData {
+    fn parse(data: &[u8], config: &WebAuthnConfig) -> Result<Self, WebAuthnError> {
+        if data.len() < 37 {
+            return Err(WebAuthnError::Encoding);
+        }
+        
+        if data[..32] != Sha256::digest(config.rp_id.as_bytes())[..] {
+            return Err(WebAuthnError::RelyingParty);
+        }
+        
+        let flags = data[32];
+        if flags & FLAG_USER_PRESENT == 0 || flags & FLAG_USER_VERIFIED == 0 {
+            return Err(WebAuthnError::UserNotVerified);
+        }
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+        
+        let attested_credential = if flags & FLAG_ATTESTED_CREDENTIAL_DATA != 0 {
+            // 16-byte AAGUID, then a 2-byte length, the credential id and a CBOR key
+            let rest = data.get(37 + 16..).ok_or(WebAuthnError::Encoding)?;
+            let length = u16::from_be_bytes([
+                *rest.first().ok_or(WebAuthnError::Encoding)?,
+                *rest.get(1).ok_or(WebAuthnError::Encoding)?,
+            ]) as usize;
+            let credential_id = rest.get(2..2 + length).ok_or(WebAuthnError::Encoding)?.to_vec();
+            let mut key_bytes = &rest[2 + length..];
+            let key: Value = ciborium::de::from_reader(&mut key_bytes).map_err(|_| WebAuthnError::Encoding)?;
+            Some((credential_id, key))
+        } else {
+            None
+        };
+        
+        Ok(AuthenticatorData { sign_count, attested_credential })
+    }
+}
+
+// A credential that passed the registration ceremony, ready to be stored
+#[derive(Debug, Clone)]
+pub struct NewCredential {
+    pub credential_id: String,
+    pub public_key: Vec<u8>,
+    pub sign_count: u32,
+}
+
+// Checks the attestation object from `navigator.credentials.create()` and extracts
+// the new credential. The client data must already have been checked with `ClientData::parse`.
+pub fn verify_registration(attestation_object: &[u8], config: &WebAuthnConfig) -> Result<NewCredential, WebAuthnError> {
+    let attestation: Value = ciborium::de::from_reader(attestation_object).map_err(|_| WebAuthnError::Encoding)?;
+    let auth_data = map_get(&attestation, &Value::Text("authData".to_string()))
+        .and_then(Value::as_bytes)
+        .ok_or(WebAuthnError::Encoding)?;
+    
+    let auth_data = AuthenticatorData::parse(auth_data, config)?;
+    let (credential_id, key) = auth_data.attested_credential.ok_or(WebAuthnError::MissingCredential)?;
+    
+    Ok(NewCredential {
+        credential_id: URL_SAFE_NO_PAD.encode(credential_id),
+        public_key: es256_public_key(&key)?,
+        sign_count: auth_data.sign_count,
+    })
+}
+
+// Checks an assertion from `navigator.credentials.get()` against the stored key
+// and returns the authenticator's new signature counter
+pub fn verify_assertion(
+    public_key: &[u8],
+    stored_sign_count: u32,
+    authenticator_data: &[u8],
+    client_data_json: &[u8],
+    signature: &[u8],
+    config: &WebAuthnConfig,
+) -> Result<u32, WebAuthnError> {
+    let auth_data = AuthenticatorData::parse(authenticator_data, config)?;
+    
+    let key = VerifyingKey::from_sec1_bytes(public_key).map_err(|_| WebAuthnError::UnsupportedKey)?;
+    let signature = Signature::from_der(signature).map_err(|_| WebAuthnError::Signature)?;
+    let mut signed = authenticator_data.to_vec();
+    signed.extend_from_slice(&Sha256::digest(client_data_json));
+    key.verify(&signed, &signature).map_err(|_| WebAuthnError::Signature)?;
+    
+    // Authenticators without a counter always report zero. Otherwise the counter
+    // must move forward, or the credential may have been cloned.
+    if (auth_data.sign_count != 0 || stored_sign_count != 0) && auth_data.sign_count <= stored_sign_count {
+        return Err(WebAuthnError::SignCount);
+    }
+    
+    Ok(auth_data.sign_count)
+}
+
+// Converts a COSE_Key for ES256 on P-256 into an uncompressed SEC1 point
+fn es256_public_key(key: &Value) -> Result<Vec<u8>, WebAuthnError> {
+    let field = |label: i64| map_get(key, &Value::Integer(label.into()));
+    let integer = |label: i64| field(label).and_then(Value::as_integer).map(i128::from);
+    
+    // kty EC2, alg ES256, crv P-256
+    if integer(1) != Some(2) || integer(3) != Some(ES256.into()) || integer(-1) != Some(1) {
+        return Err(WebAuthnError::UnsupportedKey);
+    }
+    let x = field(-2).and_then(Value::as_bytes).ok_or(WebAuthnError::UnsupportedKey)?;
+    let y = field(-3).and_then(Value::as_bytes).ok_or(WebAuthnError::UnsupportedKey)?;
+    if x.len() != 32 || y.len() != 32 {
+        return Err(WebAuthnError::UnsupportedKey);
+    }
+    
+    let mut point = Vec::with_capacity(65);
+    point.push(0x04);
+    point.extend_from_slice(x);
+    point.extend_from_slice(y);
+    VerifyingKey::from_sec1_bytes(&point).map_err(|_| WebAuthnError::UnsupportedKey)?;
+    Ok(point)
+}
+
+fn map_get<'a>(value: &'a Value, key: &Value) -> Option<&'a Value> {
+    value.as_map()?.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+}
@@ -0,0 +1,250 @@
+use actix_web::{test, App, web};
+use chrono::Duration;
+use serde_json::json;
+use surjo_backend::handlers::api_keys::{create_api_key, list_my_api_keys, revoke_my_api_key};
+use surjo_backend::handlers::auth::logout;
+use surjo_backend::handlers::passkeys::{delete_my_passkey, finish_passkey_registration, start_passkey_registration};
+use surjo_backend::handlers::two_factor::{
+    confirm_two_factor, disable_two_factor, regenerate_recovery_codes, setup_two_factor,
+};
+use surjo_backend::handlers::users::{get_user, list_users};
+use surjo_backend::models::{ApiKey, AppState};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+macro_rules! api_key_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(create_api_key)
+                .service(list_my_api_keys)
+                .service(revoke_my_api_key)
+                .service(get_user)
+                .service(list_users)
+                .service(logout)
+        )
+        .await
+    };
+}
+
+fn insert_api_key(state: &AppState, user_id: &str, scopes: &[&str], ttl: Option<Duration>) -> String {
+    let scopes: Vec<String> = scopes.iter().map(|scope| scope.to_string()).collect();
+    let database = state.database.lock().unwrap();
+    ApiKey::create(database.get_connection(), user_id, "test key", &scopes, ttl)
+        .expect("Failed to create API key")
+        .1
+}
+
+#[actix_rt::test]
+async fn test_create_and_use_api_key() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "ci@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = api_key_app!(app_state);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "CI", "expires_in_days": 30 }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let created: serde_json::Value = test::read_body_json(resp).await;
+    let key = created["key"].as_str().unwrap().to_string();
+    assert!(key.starts_with("surjo_"));
+    assert!(key.starts_with(created["api_key"]["prefix"].as_str().unwrap()));
+    assert!(created["api_key"]["expires_at"].is_string());
+    assert!(created["api_key"]["last_used_at"].is_null());
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&key))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(&token))
+        .to_request();
+    let api_keys: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let api_keys = api_keys.as_array().unwrap();
+    assert_eq!(api_keys.len(), 1);
+    assert_eq!(api_keys[0]["name"], "CI");
+    assert!(api_keys[0]["last_used_at"].is_string());
+    assert!(api_keys[0].get("key").is_none());
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer("surjo_not-a-real-key"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_api_key_scopes_limit_permissions() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let plain = insert_user(&app_state, "plain@example.com", "password123");
+    let admin_token = access_token(&app_state, &admin.id);
+    let plain_token = access_token(&app_state, &plain.id);
+    let app = api_key_app!(app_state.clone());
+    
+    // Keys cannot be granted permissions the caller does not hold
+    let req = test::TestRequest::post()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(&plain_token))
+        .set_json(json!({ "name": "Escalate", "scopes": ["admin"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let mut keys = Vec::new();
+    for scopes in [json!([]), json!(["admin"])] {
+        let req = test::TestRequest::post()
+            .uri("/api/me/api-keys")
+            .insert_header(bearer(&admin_token))
+            .set_json(json!({ "name": "Admin script", "scopes": scopes }))
+            .to_request();
+        let created: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(created["api_key"]["scopes"], scopes);
+        keys.push(created["key"].as_str().unwrap().to_string());
+    }
+    
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&keys[0]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&keys[1]))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    // A key loses scopes its owner no longer holds
+    {
+        let database = app_state.database.lock().unwrap();
+        database
+            .get_connection()
+            .execute("DELETE FROM user_permissions WHERE user_id = ?1", [&admin.id])
+            .unwrap();
+    }
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&keys[1]))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[actix_rt::test]
+async fn test_api_keys_cannot_manage_sessions_or_keys() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "bot@example.com", "password123");
+    let key = insert_api_key(&app_state, &user.id, &[], None);
+    let app = api_key_app!(app_state);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(&key))
+        .set_json(json!({ "name": "Another" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/logout")
+        .insert_header(bearer(&key))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+// A key of any scope could otherwise enrol a passkey or swap out 2FA, and sign
+// in with full permissions from then on
+#[actix_rt::test]
+async fn test_api_keys_cannot_change_sign_in_credentials() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "bot@example.com", "password123");
+    let key = insert_api_key(&app_state, &user.id, &[], None);
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(start_passkey_registration)
+            .service(finish_passkey_registration)
+            .service(delete_my_passkey)
+            .service(setup_two_factor)
+            .service(confirm_two_factor)
+            .service(regenerate_recovery_codes)
+            .service(disable_two_factor)
+    ).await;
+    
+    let code = json!({ "code": "123456" });
+    let registration = json!({
+        "credential": { "id": "x", "response": { "clientDataJSON": "x", "attestationObject": "x" } }
+    });
+    let requests = [
+        test::TestRequest::post().uri("/api/me/passkeys/register/start"),
+        test::TestRequest::post().uri("/api/me/passkeys/register/finish").set_json(&registration),
+        test::TestRequest::delete().uri("/api/me/passkeys/some-passkey"),
+        test::TestRequest::post().uri("/api/me/2fa/setup"),
+        test::TestRequest::post().uri("/api/me/2fa/confirm").set_json(&code),
+        test::TestRequest::post().uri("/api/me/2fa/recovery-codes").set_json(&code),
+        test::TestRequest::delete().uri("/api/me/2fa").set_json(&code),
+    ];
+    for req in requests {
+        let resp = test::call_service(&app, req.insert_header(bearer(&key)).to_request()).await;
+        assert_eq!(resp.status(), 403);
+    }
+}
+
+#[actix_rt::test]
+async fn test_expired_and_revoked_keys_are_rejected() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "keys@example.com", "password123");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    let expired = insert_api_key(&app_state, &user.id, &[], Some(Duration::seconds(-1)));
+    let key = insert_api_key(&app_state, &user.id, &[], None);
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &other.id);
+    let app = api_key_app!(app_state);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&expired))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(&token))
+        .to_request();
+    let api_keys: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let key_id = api_keys
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|api_key| key.starts_with(api_key["prefix"].as_str().unwrap()))
+        .unwrap()["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/api-keys/{key_id}"))
+        .insert_header(bearer(&other_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/api-keys/{key_id}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&key))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
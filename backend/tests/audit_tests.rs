@@ -0,0 +1,177 @@
+use actix_web::{test, App, web};
+use serde_json::json;
+use surjo_backend::handlers::audit::list_audit_events;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::groups::{add_group_member, create_group};
+use surjo_backend::handlers::password::request_password_reset;
+use surjo_backend::handlers::roles::{create_role, delete_role};
+use surjo_backend::handlers::users::update_user;
+use surjo_backend::models::{AuditEvent, NewAuditEvent};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+macro_rules! audit_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(list_audit_events)
+                .service(login)
+                .service(update_user)
+                .service(create_role)
+                .service(delete_role)
+                .service(create_group)
+                .service(add_group_member)
+                .service(request_password_reset)
+        )
+        .await
+    };
+}
+
+#[actix_rt::test]
+async fn test_mutations_and_sign_ins_are_audited() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let token = access_token(&app_state, &admin.id);
+    let app = audit_app!(app_state.clone());
+    
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&token))
+        .insert_header(("User-Agent", "audit-test"))
+        .set_json(json!({ "first_name": "Renamed" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "user@example.com", "password": "wrong-password" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/audit-events?target_id={}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+    let events = page["events"].as_array().unwrap();
+    assert_eq!(events[0]["action"], "auth.login_failed");
+    assert!(events[0]["actor_id"].is_null());
+    assert_eq!(events[1]["action"], "user.update");
+    assert_eq!(events[1]["actor_id"], admin.id);
+    assert_eq!(events[1]["user_agent"], "audit-test");
+    assert_eq!(events[1]["diff"], json!({ "first_name": { "from": null, "to": "Renamed" } }));
+    
+    // Filters combine, and pages report the total across all of them
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/audit-events?target_id={}&action=user.update", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 1);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/audit-events?target_id={}&limit=1&offset=1", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["events"].as_array().unwrap().len(), 1);
+    assert_eq!(page["events"][0]["action"], "user.update");
+    
+    let req = test::TestRequest::get()
+        .uri("/api/audit-events")
+        .insert_header(bearer(&access_token(&app_state, &user.id)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[actix_rt::test]
+async fn test_access_control_changes_and_reset_requests_are_audited() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let token = access_token(&app_state, &admin.id);
+    let app = audit_app!(app_state.clone());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/roles")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "auditor", "permissions": ["user"] }))
+        .to_request();
+    let role: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let role_id = role["id"].as_str().unwrap();
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/roles/{role_id}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/groups")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "support" }))
+        .to_request();
+    let group: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let group_id = group["id"].as_str().unwrap();
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/groups/{group_id}/members/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    for email in ["user@example.com", "nobody@example.com"] {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/password-reset/request")
+            .set_json(json!({ "email": email }))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 202);
+    }
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/audit-events?target_id={role_id}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let actions: Vec<&str> = page["events"].as_array().unwrap().iter().map(|event| event["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["role.delete", "role.create"]);
+    assert_eq!(page["events"][1]["actor_id"], admin.id);
+    assert_eq!(page["events"][1]["diff"], json!({ "name": "auditor", "permissions": ["user"] }));
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/audit-events?target_id={}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let actions: Vec<&str> = page["events"].as_array().unwrap().iter().map(|event| event["action"].as_str().unwrap()).collect();
+    assert_eq!(actions, ["auth.password_reset_requested", "group.member_added"]);
+    assert_eq!(page["events"][1]["diff"], json!({ "group_id": group_id }));
+    
+    // Requests for unknown addresses are kept too
+    let req = test::TestRequest::get()
+        .uri("/api/audit-events?action=auth.password_reset_requested")
+        .insert_header(bearer(&token))
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["total"], 2);
+    assert_eq!(page["events"][0]["diff"], json!({ "email": "nobody@example.com" }));
+}
+
+#[actix_rt::test]
+async fn test_audit_log_is_append_only() {
+    let app_state = create_test_app_state();
+    let database = app_state.database.lock().unwrap();
+    let conn = database.get_connection();
+    
+    AuditEvent::record(conn, NewAuditEvent::cli("signing_key.rotate")).unwrap();
+    
+    assert!(conn.execute("UPDATE audit_events SET action = 'nothing'", []).is_err());
+    assert!(conn.execute("DELETE FROM audit_events", []).is_err());
+    let events = AuditEvent::find(conn, &Default::default(), None).unwrap();
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].action, "signing_key.rotate");
+}
@@ -0,0 +1,361 @@
+use actix_web::{middleware::from_fn, test, App, HttpResponse, web};
+use chrono::Duration;
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use surjo_backend::handlers::auth::{login, refresh};
+use surjo_backend::handlers::users::{get_user, list_users};
+use surjo_backend::middleware::require_auth;
+use surjo_backend::models::{Claims, ClientInfo, LoginRequest, RefreshRequest, Session};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, deactivate_user, insert_user, TEST_JWT_SECRET};
+
+#[actix_rt::test]
+async fn test_login_success() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "login@example.com", "password123");
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(login)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(&LoginRequest {
+            email: "login@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["user"]["id"], user.id);
+    assert_eq!(body["user"]["email"], "login@example.com");
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(body["expires_in"], 900);
+    
+    // The token must be signed with the configured secret and name the user
+    let token = body["token"].as_str().unwrap();
+    let decoded = decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(TEST_JWT_SECRET.as_bytes()),
+        &Validation::default(),
+    ).expect("Token should be valid");
+    assert_eq!(decoded.claims.sub, user.id);
+    assert!(decoded.claims.exp > decoded.claims.iat);
+    
+    // Login opens a session that the token refers to
+    let database = app_state.database.lock().unwrap();
+    let session = Session::find_active(database.get_connection(), &decoded.claims.sid)
+        .unwrap()
+        .expect("Session should exist");
+    assert_eq!(session.user_id, user.id);
+}
+
+#[actix_rt::test]
+async fn test_login_wrong_password() {
+    let app_state = create_test_app_state();
+    insert_user(&app_state, "wrong@example.com", "password123");
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(&LoginRequest {
+            email: "wrong@example.com".to_string(),
+            password: "not-the-password".to_string(),
+        })
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_login_unknown_email() {
+    let app_state = create_test_app_state();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(&LoginRequest {
+            email: "nobody@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_login_inactive_user() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "inactive@example.com", "password123");
+    deactivate_user(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(&LoginRequest {
+            email: "inactive@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+}
+
+#[actix_rt::test]
+async fn test_protected_route_requires_token() {
+    let app_state = create_test_app_state();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(list_users)
+    ).await;
+    
+    let req = test::TestRequest::get().uri("/api/users").to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_protected_route_rejects_bad_tokens() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "tokens@example.com", "password123");
+    
+    let (expired, forged) = {
+        let database = app_state.database.lock().unwrap();
+        let conn = database.get_connection();
+        let session_id = Session::create(conn, &user.id, Duration::hours(1), &ClientInfo::default()).unwrap().id;
+        let expired = Claims::new(&user.id, &session_id, Duration::hours(-2))
+            .encode(conn, TEST_JWT_SECRET)
+            .unwrap();
+        let forged = Claims::new(&user.id, &session_id, Duration::hours(1))
+            .encode(conn, "some-other-secret")
+            .unwrap();
+        (expired, forged)
+    };
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(get_user)
+    ).await;
+    
+    for token in [expired.as_str(), forged.as_str(), "not-a-jwt"] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}", user.id))
+            .insert_header(bearer(token))
+            .to_request();
+        let resp = test::call_service(&app, req).await;
+        assert_eq!(resp.status(), 401);
+    }
+}
+
+#[actix_rt::test]
+async fn test_protected_route_rejects_inactive_user() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "disabled@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    deactivate_user(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(get_user)
+    ).await;
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_require_auth_scope() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "scope@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(
+                web::scope("/private")
+                    .wrap(from_fn(require_auth))
+                    .route("/ping", web::get().to(|| async { HttpResponse::Ok().json("pong") }))
+            )
+    ).await;
+    
+    // Middleware errors surface from the service call rather than as a response
+    let req = test::TestRequest::get().uri("/private/ping").to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(err.as_response_error().status_code(), 401);
+    
+    let req = test::TestRequest::get()
+        .uri("/private/ping")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_revoked_session_invalidates_token() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "revoked@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(get_user)
+    ).await;
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    // Signing out everywhere kills every token, even unexpired ones
+    {
+        let database = app_state.database.lock().unwrap();
+        Session::delete_all_for_user(database.get_connection(), &user.id).unwrap();
+    }
+    
+    for token in [&token, &other_token] {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users/{}", user.id))
+            .insert_header(bearer(token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
+
+#[actix_rt::test]
+async fn test_expired_session_invalidates_token() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "stale@example.com", "password123");
+    let token = {
+        let database = app_state.database.lock().unwrap();
+        let conn = database.get_connection();
+        let session = Session::create(conn, &user.id, Duration::hours(-1), &ClientInfo::default()).unwrap();
+        Claims::new(&user.id, &session.id, Duration::hours(1)).encode(conn, TEST_JWT_SECRET).unwrap()
+    };
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(get_user)
+    ).await;
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_refresh_token_rotation_and_reuse_detection() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "refresh@example.com", "password123");
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(refresh)
+            .service(get_user)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(&LoginRequest {
+            email: "refresh@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let first_refresh = body["refresh_token"].as_str().unwrap().to_string();
+    
+    // Rotating hands out a new pair for the same session
+    let req = test::TestRequest::post()
+        .uri("/api/auth/refresh")
+        .set_json(&RefreshRequest { refresh_token: first_refresh.clone() })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let rotated: serde_json::Value = test::read_body_json(resp).await;
+    let second_refresh = rotated["refresh_token"].as_str().unwrap().to_string();
+    let second_access = rotated["token"].as_str().unwrap().to_string();
+    assert_ne!(first_refresh, second_refresh);
+    assert_eq!(rotated["user"]["id"], user.id);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&second_access))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    // Replaying the first token revokes the whole family
+    let req = test::TestRequest::post()
+        .uri("/api/auth/refresh")
+        .set_json(&RefreshRequest { refresh_token: first_refresh })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/refresh")
+        .set_json(&RefreshRequest { refresh_token: second_refresh })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(&second_access))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_refresh_with_unknown_token() {
+    let app_state = create_test_app_state();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(refresh)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/refresh")
+        .set_json(&RefreshRequest { refresh_token: "made-up".to_string() })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
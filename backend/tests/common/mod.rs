@@ -0,0 +1,93 @@
+#![allow(dead_code)]
+
+pub mod oidc;
+
+use chrono::Duration;
+use surjo_backend::mailer::MemoryMailer;
+use surjo_backend::models::{Database, AppState, AuthPolicy, Claims, ClientInfo, GoogleConfig, Permission, Session, User, WebAuthnConfig};
+use std::sync::{Arc, Mutex};
+
+pub const TEST_JWT_SECRET: &str = "test-secret";
+
+pub fn create_test_app_state() -> AppState {
+    create_test_app_state_with_mailer().0
+}
+
+// Also hands back the mailer so tests can read what was sent
+pub fn create_test_app_state_with_mailer() -> (AppState, Arc<MemoryMailer>) {
+    let mailer = Arc::new(MemoryMailer::new());
+    let mut database = Database::new(":memory:").expect("Failed to create in-memory database");
+    database.run_migrations().expect("Failed to run migrations");
+    
+    let state = AppState {
+        database: Arc::new(Mutex::new(database)),
+        jwt_secret: TEST_JWT_SECRET.to_string(),
+        google: GoogleConfig {
+            client_id: "test-client-id".to_string(),
+            client_secret: "test-client-secret".to_string(),
+            redirect_uri: "http://localhost/callback".to_string(),
+            token_url: "http://127.0.0.1:9/token".to_string(),
+            userinfo_url: "http://127.0.0.1:9/userinfo".to_string(),
+        },
+        oauth_providers: Vec::new(),
+        policy: AuthPolicy::default(),
+        webauthn: WebAuthnConfig {
+            rp_id: "app.test".to_string(),
+            rp_name: "Surjo".to_string(),
+            origin: "http://app.test".to_string(),
+        },
+        mailer: mailer.clone(),
+        app_url: "http://app.test".to_string(),
+    };
+    (state, mailer)
+}
+
+// Pulls the `token=` query parameter out of the link in an email body
+pub fn token_from_email(body: &str) -> String {
+    let start = body.find("token=").expect("Email should contain a token") + "token=".len();
+    body[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric() || *c == '-' || *c == '_')
+        .collect()
+}
+
+// Inserts a user directly, hashing with the minimum bcrypt cost to keep tests fast
+pub fn insert_user(state: &AppState, email: &str, password: &str) -> User {
+    let password_hash = bcrypt::hash(password, 4).expect("Failed to hash password");
+    let database = state.database.lock().unwrap();
+    User::create(database.get_connection(), email, Some(&password_hash), None, None)
+        .expect("Failed to create user")
+}
+
+pub fn verify_email(state: &AppState, user_id: &str) {
+    let database = state.database.lock().unwrap();
+    User::mark_email_verified(database.get_connection(), user_id).expect("Failed to verify email");
+}
+
+pub fn deactivate_user(state: &AppState, user_id: &str) {
+    let database = state.database.lock().unwrap();
+    database
+        .get_connection()
+        .execute("UPDATE users SET is_active = 0 WHERE id = ?1", [user_id])
+        .expect("Failed to deactivate user");
+}
+
+// Opens a session and signs an access token for it, the same way login does
+pub fn access_token(state: &AppState, user_id: &str) -> String {
+    let database = state.database.lock().unwrap();
+    let session = Session::create(database.get_connection(), user_id, Duration::hours(1), &ClientInfo::default())
+        .expect("Failed to create session");
+    Claims::new(user_id, &session.id, Duration::hours(1))
+        .encode(database.get_connection(), &state.jwt_secret)
+        .expect("Failed to sign token")
+}
+
+pub fn bearer(token: &str) -> (&'static str, String) {
+    ("Authorization", format!("Bearer {token}"))
+}
+
+pub fn grant_permission(state: &AppState, user_id: &str, permission: &str) {
+    let database = state.database.lock().unwrap();
+    Permission::grant(database.get_connection(), user_id, permission)
+        .expect("Failed to grant permission");
+}
@@ -0,0 +1,177 @@
+// A mock OpenID Connect issuer, which can also pose as a GitHub-style plain
+// OAuth 2.0 provider
+
+use std::collections::HashMap;
+use std::net::TcpListener;
+use std::sync::Mutex;
+
+use actix_web::{web, App, HttpResponse, HttpServer};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use surjo_backend::models::{AppState, OAuthProviderConfig};
+
+pub const MOCK_CLIENT_ID: &str = "mock-client";
+
+// Accounts known to the mock provider, picked with `login_hint`:
+// (hint, subject, email, email_verified)
+pub const MOCK_ACCOUNTS: [(&str, &str, &str, bool); 3] = [
+    ("new", "subject-1", "new@example.com", true),
+    ("existing", "subject-2", "existing@example.com", true),
+    ("unverified", "subject-3", "unverified@example.com", false),
+];
+
+// An authorization code handed out by the mock, with the PKCE challenge, nonce
+// and account it was issued for
+type IssuedCode = (String, Option<String>, usize);
+
+#[derive(Default)]
+struct MockProvider {
+    base_url: String,
+    codes: Mutex<HashMap<String, IssuedCode>>,
+}
+
+async fn mock_discovery(provider: web::Data<MockProvider>) -> HttpResponse {
+    HttpResponse::Ok().json(json!({
+        "issuer": provider.base_url,
+        "authorization_endpoint": format!("{}/authorize", provider.base_url),
+        "token_endpoint": format!("{}/token", provider.base_url),
+        "userinfo_endpoint": format!("{}/userinfo", provider.base_url),
+    }))
+}
+
+// Stands in for the user signing in at the provider: instead of redirecting back
+// to the web app it returns the code and state as JSON
+async fn mock_authorize(
+    query: web::Query<HashMap<String, String>>,
+    provider: web::Data<MockProvider>,
+) -> HttpResponse {
+    let hint = query.get("login_hint").cloned().unwrap_or_default();
+    let Some(account) = MOCK_ACCOUNTS.iter().position(|(h, ..)| *h == hint) else {
+        return HttpResponse::BadRequest().finish();
+    };
+    if query.get("code_challenge_method").map(String::as_str) != Some("S256") {
+        return HttpResponse::BadRequest().finish();
+    }
+    
+    let code = format!("code-{}", uuid::Uuid::new_v4());
+    provider.codes.lock().unwrap().insert(
+        code.clone(),
+        (query["code_challenge"].clone(), query.get("nonce").cloned(), account),
+    );
+    HttpResponse::Ok().json(json!({ "code": code, "state": query["state"] }))
+}
+
+async fn mock_token(
+    form: web::Form<HashMap<String, String>>,
+    provider: web::Data<MockProvider>,
+) -> HttpResponse {
+    let code = form.get("code").cloned().unwrap_or_default();
+    let Some((challenge, nonce, account)) = provider.codes.lock().unwrap().remove(&code) else {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    };
+    let verifier = form.get("code_verifier").cloned().unwrap_or_default();
+    if URL_SAFE_NO_PAD.encode(Sha256::digest(verifier.as_bytes())) != challenge {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    }
+    
+    let now = chrono::Utc::now().timestamp();
+    let id_token = encode(
+        &Header::default(),
+        &json!({
+            "iss": provider.base_url,
+            "aud": MOCK_CLIENT_ID,
+            "sub": MOCK_ACCOUNTS[account].1,
+            "nonce": nonce,
+            "iat": now,
+            "exp": now + 3600,
+        }),
+        &EncodingKey::from_secret(b"mock-issuer-key"),
+    ).unwrap();
+    
+    HttpResponse::Ok().json(json!({
+        "access_token": format!("access-{account}"),
+        "id_token": id_token,
+        "token_type": "Bearer",
+    }))
+}
+
+// Answers like a standards-based issuer, or like GitHub's `/user` for the plain
+// OAuth 2.0 provider: a numeric `id` and no verification flag
+async fn mock_userinfo(req: actix_web::HttpRequest, github_style: web::Data<bool>) -> HttpResponse {
+    let auth = req.headers().get("Authorization").and_then(|h| h.to_str().ok()).unwrap_or_default();
+    let Some(account) = auth
+        .strip_prefix("Bearer access-")
+        .and_then(|account| account.parse::<usize>().ok())
+        .filter(|account| *account < MOCK_ACCOUNTS.len())
+    else {
+        return HttpResponse::Unauthorized().finish();
+    };
+    let (_, sub, email, verified) = MOCK_ACCOUNTS[account];
+    
+    if **github_style {
+        HttpResponse::Ok().json(json!({ "id": 4200 + account, "login": "octocat", "email": email }))
+    } else {
+        HttpResponse::Ok().json(json!({
+            "sub": sub,
+            "email": email,
+            "email_verified": verified,
+            "given_name": "Issuer",
+            "family_name": "User",
+        }))
+    }
+}
+
+// Starts a mock provider and registers it with the app state under `name`
+pub fn start_mock_provider(state: &mut AppState, name: &str, github_style: bool) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let provider = web::Data::new(MockProvider { base_url: base_url.clone(), ..Default::default() });
+    
+    let server = HttpServer::new(move || {
+        App::new()
+            .app_data(provider.clone())
+            .app_data(web::Data::new(github_style))
+            .route("/.well-known/openid-configuration", web::get().to(mock_discovery))
+            .route("/authorize", web::get().to(mock_authorize))
+            .route("/token", web::post().to(mock_token))
+            .route("/userinfo", web::get().to(mock_userinfo))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    actix_rt::spawn(server);
+    
+    let mut config = OAuthProviderConfig {
+        name: name.to_string(),
+        client_id: MOCK_CLIENT_ID.to_string(),
+        client_secret: "mock-secret".to_string(),
+        redirect_uri: format!("http://app.test/oauth/{name}/callback"),
+        scopes: vec!["openid".to_string(), "email".to_string()],
+        issuer: Some(base_url.clone()),
+        authorization_url: None,
+        token_url: None,
+        userinfo_url: None,
+    };
+    if github_style {
+        config.issuer = None;
+        config.scopes = vec!["user:email".to_string()];
+        config.authorization_url = Some(format!("{base_url}/authorize"));
+        config.token_url = Some(format!("{base_url}/token"));
+        config.userinfo_url = Some(format!("{base_url}/userinfo"));
+    }
+    state.oauth_providers.push(config);
+}
+
+// Stands in for the browser visiting the authorization URL and the user picking
+// the account named by `hint`; returns the code and state the web app would get
+pub async fn grant(authorization_url: &str, hint: &str) -> (String, String) {
+    let url = format!("{authorization_url}&login_hint={hint}");
+    let granted: serde_json::Value = reqwest::get(url).await.unwrap().json().await.unwrap();
+    (
+        granted["code"].as_str().unwrap().to_string(),
+        granted["state"].as_str().unwrap().to_string(),
+    )
+}
@@ -0,0 +1,17 @@
+use surjo_backend::models::{generate_token, validate_jwt_secret, JwtSecretError, DEFAULT_JWT_SECRET};
+
+#[test]
+fn test_jwt_secret_validation() {
+    assert_eq!(validate_jwt_secret(None), Err(JwtSecretError::Missing));
+    assert_eq!(validate_jwt_secret(Some("")), Err(JwtSecretError::Missing));
+    assert_eq!(validate_jwt_secret(Some(DEFAULT_JWT_SECRET)), Err(JwtSecretError::Default));
+    assert_eq!(validate_jwt_secret(Some("short-secret")), Err(JwtSecretError::TooShort(12)));
+    assert_eq!(validate_jwt_secret(Some(&"x".repeat(32))), Ok(()));
+}
+
+#[test]
+fn test_generated_secrets_are_accepted() {
+    let secret = generate_token();
+    assert_eq!(validate_jwt_secret(Some(&secret)), Ok(()));
+    assert_ne!(secret, generate_token());
+}
@@ -0,0 +1,158 @@
+use actix_web::{test, web, App};
+use serde_json::json;
+use surjo_backend::handlers::identities::{link_identity, list_my_identities, start_identity_link, unlink_identity};
+use surjo_backend::handlers::oauth::start_oauth;
+use surjo_backend::models::{AppState, OAuthIdentity, User};
+
+mod common;
+use common::oidc::{grant, start_mock_provider};
+use common::{access_token, bearer, create_test_app_state, insert_user};
+
+macro_rules! identity_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(start_oauth)
+                .service(list_my_identities)
+                .service(start_identity_link)
+                .service(link_identity)
+                .service(unlink_identity)
+        )
+        .await
+    };
+}
+
+// Starts a link for the token's user and has the mock provider authorize `hint`
+async fn start_link<S>(app: &S, token: &str, hint: &str) -> (String, String)
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::get()
+        .uri("/api/me/identities/acme/start")
+        .insert_header(bearer(token))
+        .to_request();
+    let started: serde_json::Value = test::call_and_read_body_json(app, req).await;
+    grant(started["authorization_url"].as_str().unwrap(), hint).await
+}
+
+async fn link<S>(app: &S, token: &str, provider: &str, body: serde_json::Value) -> u16
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/me/identities/{provider}"))
+        .insert_header(bearer(token))
+        .set_json(body)
+        .to_request();
+    test::call_service(app, req).await.status().as_u16()
+}
+
+fn insert_passwordless_user(state: &AppState, email: &str) -> User {
+    let database = state.database.lock().unwrap();
+    User::create(database.get_connection(), email, None, None, None).expect("Failed to create user")
+}
+
+fn insert_identity(state: &AppState, user_id: &str, provider_user_id: &str) -> String {
+    let database = state.database.lock().unwrap();
+    OAuthIdentity::create(database.get_connection(), user_id, "acme", provider_user_id)
+        .expect("Failed to link identity")
+        .id
+}
+
+#[actix_rt::test]
+async fn test_link_and_list_identities() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    let user = insert_user(&app_state, "linker@example.com", "password123");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &other.id);
+    let app = identity_app!(app_state);
+    
+    // The provider account's email does not have to match the user's
+    let (code, state) = start_link(&app, &token, "new").await;
+    let req = test::TestRequest::post()
+        .uri("/api/me/identities/acme")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "code": code, "state": state }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let linked: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(linked["provider"], "acme");
+    assert_eq!(linked["provider_user_id"], "subject-1");
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/identities")
+        .insert_header(bearer(&token))
+        .to_request();
+    let identities: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(identities.as_array().unwrap().len(), 1);
+    assert_eq!(identities[0]["id"], linked["id"]);
+    
+    let (code, state) = start_link(&app, &token, "new").await;
+    assert_eq!(link(&app, &token, "acme", json!({ "code": code, "state": state })).await, 409);
+    
+    let (code, state) = start_link(&app, &other_token, "new").await;
+    assert_eq!(link(&app, &other_token, "acme", json!({ "code": code, "state": state })).await, 409);
+}
+
+#[actix_rt::test]
+async fn test_link_requires_state_started_by_user() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    let user = insert_user(&app_state, "linker@example.com", "password123");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &other.id);
+    let app = identity_app!(app_state);
+    
+    assert_eq!(link(&app, &token, "missing", json!({ "code": "code" })).await, 404);
+    assert_eq!(link(&app, &token, "acme", json!({ "code": "code" })).await, 400);
+    
+    // A sign-in state cannot finish a link
+    let req = test::TestRequest::get().uri("/api/auth/oauth/acme/start").to_request();
+    let started: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let (code, state) = grant(started["authorization_url"].as_str().unwrap(), "new").await;
+    assert_eq!(link(&app, &token, "acme", json!({ "code": code, "state": state })).await, 400);
+    
+    // Nor can a link someone else started
+    let (code, state) = start_link(&app, &other_token, "new").await;
+    assert_eq!(link(&app, &token, "acme", json!({ "code": code, "state": state })).await, 400);
+}
+
+#[actix_rt::test]
+async fn test_unlink_keeps_a_sign_in_method() {
+    let app_state = create_test_app_state();
+    let user = insert_passwordless_user(&app_state, "social@example.com");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    let first = insert_identity(&app_state, &user.id, "subject-a");
+    let second = insert_identity(&app_state, &user.id, "subject-b");
+    let others = insert_identity(&app_state, &other.id, "subject-c");
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &other.id);
+    let app = identity_app!(app_state.clone());
+    
+    let unlink = |token: String, id: String| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/me/identities/{id}"))
+            .insert_header(bearer(&token))
+            .to_request()
+    };
+    
+    assert_eq!(test::call_service(&app, unlink(token.clone(), others.clone())).await.status(), 404);
+    assert_eq!(test::call_service(&app, unlink(token.clone(), first)).await.status(), 204);
+    
+    // The last identity of an account without a password stays
+    assert_eq!(test::call_service(&app, unlink(token.clone(), second.clone())).await.status(), 409);
+    
+    {
+        let database = app_state.database.lock().unwrap();
+        User::set_password_hash(database.get_connection(), &user.id, "hash").unwrap();
+    }
+    assert_eq!(test::call_service(&app, unlink(token, second)).await.status(), 204);
+    
+    // Accounts with a password can drop every identity
+    assert_eq!(test::call_service(&app, unlink(other_token, others)).await.status(), 204);
+}
@@ -0,0 +1,142 @@
+use actix_web::{test, App, web};
+use jsonwebtoken::{decode, DecodingKey, Validation};
+use surjo_backend::handlers::api_keys::create_api_key;
+use surjo_backend::handlers::impersonation::impersonate_user;
+use surjo_backend::handlers::sessions::list_my_sessions;
+use surjo_backend::handlers::two_factor::setup_two_factor;
+use surjo_backend::handlers::users::get_user;
+use surjo_backend::models::{Claims, ADMIN_PERMISSION};
+use serde_json::json;
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, deactivate_user, grant_permission, insert_user, TEST_JWT_SECRET};
+
+macro_rules! impersonation_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(impersonate_user)
+                .service(get_user)
+                .service(list_my_sessions)
+                .service(setup_two_factor)
+                .service(create_api_key)
+        )
+        .await
+    };
+}
+
+async fn impersonate<S>(app: &S, token: &str, user_id: &str) -> actix_web::dev::ServiceResponse
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/admin/users/{user_id}/impersonate"))
+        .insert_header(bearer(token))
+        .to_request();
+    test::call_service(app, req).await
+}
+
+#[actix_rt::test]
+async fn test_admin_impersonates_user() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    grant_permission(&app_state, &admin.id, ADMIN_PERMISSION);
+    let admin_token = access_token(&app_state, &admin.id);
+    let app = impersonation_app!(app_state);
+    
+    let resp = impersonate(&app, &admin_token, &user.id).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["user"]["id"], user.id);
+    assert_eq!(body["expires_in"], 1800);
+    
+    // The token names the user as subject and the admin as actor
+    let token = body["token"].as_str().unwrap();
+    let claims = decode::<Claims>(token, &DecodingKey::from_secret(TEST_JWT_SECRET.as_bytes()), &Validation::default())
+        .unwrap()
+        .claims;
+    assert_eq!(claims.sub, user.id);
+    assert_eq!(claims.act.unwrap().sub, admin.id);
+    
+    // The app then behaves as it does for the user
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", admin.id))
+        .insert_header(bearer(token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    // The user can see the impersonation session
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(token))
+        .to_request();
+    let sessions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(sessions.as_array().unwrap().len(), 1);
+}
+
+#[actix_rt::test]
+async fn test_impersonation_cannot_change_credentials() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    grant_permission(&app_state, &admin.id, ADMIN_PERMISSION);
+    grant_permission(&app_state, &user.id, ADMIN_PERMISSION);
+    let admin_token = access_token(&app_state, &admin.id);
+    let app = impersonation_app!(app_state);
+    
+    let body: serde_json::Value = test::read_body_json(impersonate(&app, &admin_token, &user.id).await).await;
+    let token = body["token"].as_str().unwrap();
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/setup")
+        .insert_header(bearer(token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/api-keys")
+        .insert_header(bearer(token))
+        .set_json(json!({ "name": "escape hatch", "scopes": [] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    // Impersonating an admin does not allow chaining to someone else
+    assert_eq!(impersonate(&app, token, &other.id).await.status(), 403);
+}
+
+#[actix_rt::test]
+async fn test_impersonation_requires_active_admin() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    let disabled = insert_user(&app_state, "disabled@example.com", "password123");
+    deactivate_user(&app_state, &disabled.id);
+    grant_permission(&app_state, &admin.id, ADMIN_PERMISSION);
+    let admin_token = access_token(&app_state, &admin.id);
+    let user_token = access_token(&app_state, &user.id);
+    let app = impersonation_app!(app_state.clone());
+    
+    assert_eq!(impersonate(&app, &user_token, &admin.id).await.status(), 403);
+    assert_eq!(impersonate(&app, &admin_token, &admin.id).await.status(), 400);
+    assert_eq!(impersonate(&app, &admin_token, "missing").await.status(), 404);
+    assert_eq!(impersonate(&app, &admin_token, &disabled.id).await.status(), 409);
+    
+    let body: serde_json::Value = test::read_body_json(impersonate(&app, &admin_token, &user.id).await).await;
+    let token = body["token"].as_str().unwrap();
+    
+    // Disabling the admin ends impersonation immediately
+    deactivate_user(&app_state, &admin.id);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", user.id))
+        .insert_header(bearer(token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
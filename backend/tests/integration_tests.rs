@@ -1,7 +1,9 @@
 use actix_web::{test, App, web};
 use surjo_backend::handlers::{hello_world, users::{create_user, get_user, list_users, update_user}};
-use surjo_backend::models::{Database, AppState, CreateUserRequest, UpdateUserRequest};
-use std::sync::{Arc, Mutex};
+use surjo_backend::models::{CreateUserRequest, UpdateUserRequest};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
 
 #[actix_rt::test]
 async fn test_hello_world_endpoint() {
@@ -43,16 +45,6 @@ async fn test_hello_world_response_structure() {
     assert!(load_data["used_memory"].is_number());
 }
 
-fn create_test_app_state() -> AppState {
-    let mut database = Database::new(":memory:").expect("Failed to create in-memory database");
-    database.run_migrations().expect("Failed to run migrations");
-    
-    AppState {
-        database: Arc::new(Mutex::new(database)),
-        jwt_secret: "test-secret".to_string(),
-    }
-}
-
 #[actix_rt::test]
 async fn test_create_user() {
     let app_state = create_test_app_state();
@@ -83,6 +75,7 @@ async fn test_create_user() {
     assert_eq!(body["first_name"], "Test");
     assert_eq!(body["last_name"], "User");
     assert_eq!(body["is_active"], true);
+    assert_eq!(body["email_verified"], false);
     assert!(body["id"].is_string());
     assert!(body["created_at"].is_string());
     assert!(body["updated_at"].is_string());
@@ -130,7 +123,7 @@ async fn test_list_users() {
     
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(app_state))
+            .app_data(web::Data::new(app_state.clone()))
             .service(create_user)
             .service(list_users)
     ).await;
@@ -148,20 +141,25 @@ async fn test_list_users() {
         .set_json(&user_data)
         .to_request();
     
-    test::call_service(&app, create_req).await;
+    let create_resp = test::call_service(&app, create_req).await;
+    let create_body: serde_json::Value = test::read_body_json(create_resp).await;
+    let user_id = create_body["id"].as_str().unwrap();
+    grant_permission(&app_state, user_id, "admin");
+    let token = access_token(&app_state, user_id);
 
     // List users
     let list_req = test::TestRequest::get()
         .uri("/api/users")
+        .insert_header(bearer(&token))
         .to_request();
     
     let resp = test::call_service(&app, list_req).await;
     assert!(resp.status().is_success());
     
     let body: serde_json::Value = test::read_body_json(resp).await;
-    assert!(body.is_array());
-    let users = body.as_array().unwrap();
+    let users = body["users"].as_array().unwrap();
     assert_eq!(users.len(), 1);
+    assert!(body["next_cursor"].is_null());
     assert_eq!(users[0]["email"], "list_test@example.com");
 }
 
@@ -171,7 +169,7 @@ async fn test_get_user_by_id() {
     
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(app_state))
+            .app_data(web::Data::new(app_state.clone()))
             .service(create_user)
             .service(get_user)
     ).await;
@@ -193,9 +191,12 @@ async fn test_get_user_by_id() {
     let create_body: serde_json::Value = test::read_body_json(create_resp).await;
     let user_id = create_body["id"].as_str().unwrap();
 
+    let token = access_token(&app_state, user_id);
+
     // Get user by ID
     let get_req = test::TestRequest::get()
         .uri(&format!("/api/users/{}", user_id))
+        .insert_header(bearer(&token))
         .to_request();
     
     let resp = test::call_service(&app, get_req).await;
@@ -209,6 +210,9 @@ async fn test_get_user_by_id() {
 #[actix_rt::test]
 async fn test_get_nonexistent_user() {
     let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "caller@example.com", "password123");
+    grant_permission(&app_state, &user.id, "admin");
+    let token = access_token(&app_state, &user.id);
     
     let app = test::init_service(
         App::new()
@@ -218,6 +222,7 @@ async fn test_get_nonexistent_user() {
 
     let req = test::TestRequest::get()
         .uri("/api/users/nonexistent-id")
+        .insert_header(bearer(&token))
         .to_request();
     
     let resp = test::call_service(&app, req).await;
@@ -230,7 +235,7 @@ async fn test_update_user() {
     
     let app = test::init_service(
         App::new()
-            .app_data(web::Data::new(app_state))
+            .app_data(web::Data::new(app_state.clone()))
             .service(create_user)
             .service(update_user)
     ).await;
@@ -258,8 +263,10 @@ async fn test_update_user() {
         last_name: Some("Name".to_string()),
     };
 
+    let token = access_token(&app_state, user_id);
     let update_req = test::TestRequest::put()
         .uri(&format!("/api/users/{}", user_id))
+        .insert_header(bearer(&token))
         .set_json(&update_data)
         .to_request();
     
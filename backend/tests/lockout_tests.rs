@@ -0,0 +1,157 @@
+use std::net::SocketAddr;
+
+use actix_web::{dev::ServiceResponse, test, App, web};
+use serde_json::json;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::users::unlock_user;
+use surjo_backend::models::AppState;
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+macro_rules! lockout_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(login)
+                .service(unlock_user)
+        )
+        .await
+    };
+}
+
+async fn attempt<S>(app: &S, email: &str, password: &str, peer: Option<&str>) -> ServiceResponse
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = ServiceResponse, Error = actix_web::Error>,
+{
+    let mut req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": email, "password": password }));
+    if let Some(peer) = peer {
+        req = req.peer_addr(peer.parse::<SocketAddr>().unwrap());
+    }
+    test::call_service(app, req.to_request()).await
+}
+
+fn retry_after(resp: &ServiceResponse) -> i64 {
+    resp.headers()
+        .get("Retry-After")
+        .expect("Retry-After header")
+        .to_str()
+        .unwrap()
+        .parse()
+        .unwrap()
+}
+
+fn throttle_rows(state: &AppState) -> i64 {
+    let database = state.database.lock().unwrap();
+    database
+        .get_connection()
+        .query_row("SELECT COUNT(*) FROM login_throttles", [], |row| row.get(0))
+        .unwrap()
+}
+
+#[actix_rt::test]
+async fn test_repeated_failures_are_delayed() {
+    let app_state = create_test_app_state();
+    insert_user(&app_state, "slow@example.com", "password123");
+    let app = lockout_app!(app_state);
+    
+    for _ in 0..2 {
+        assert_eq!(attempt(&app, "slow@example.com", "guess", None).await.status(), 401);
+    }
+    
+    // Even the right password has to wait out the delay
+    let resp = attempt(&app, "slow@example.com", "password123", None).await;
+    assert_eq!(resp.status(), 429);
+    assert_eq!(retry_after(&resp), 1);
+}
+
+#[actix_rt::test]
+async fn test_account_locks_and_admin_unlocks() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.login_throttle.account.max_failures = 3;
+    app_state.policy.login_throttle.account.progressive_delay = false;
+    let user = insert_user(&app_state, "locked@example.com", "password123");
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let user_token = access_token(&app_state, &user.id);
+    let admin_token = access_token(&app_state, &admin.id);
+    let app = lockout_app!(app_state);
+    
+    for _ in 0..3 {
+        assert_eq!(attempt(&app, "locked@example.com", "guess", None).await.status(), 401);
+    }
+    
+    // The lockout applies however the address is spelled
+    let resp = attempt(&app, " Locked@Example.com", "password123", None).await;
+    assert_eq!(resp.status(), 423);
+    let wait = retry_after(&resp);
+    assert!(wait > 14 * 60 && wait <= 15 * 60);
+    
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/users/{}/unlock", user.id))
+        .insert_header(bearer(&user_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/users/missing/unlock")
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/users/{}/unlock", user.id))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    assert!(attempt(&app, "locked@example.com", "password123", None).await.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_unknown_accounts_lock_like_real_ones() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.login_throttle.account.max_failures = 2;
+    app_state.policy.login_throttle.account.progressive_delay = false;
+    let app = lockout_app!(app_state);
+    
+    for _ in 0..2 {
+        assert_eq!(attempt(&app, "nobody@example.com", "guess", None).await.status(), 401);
+    }
+    assert_eq!(attempt(&app, "nobody@example.com", "guess", None).await.status(), 423);
+}
+
+#[actix_rt::test]
+async fn test_failures_are_limited_per_address() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.login_throttle.ip.max_failures = 3;
+    insert_user(&app_state, "victim@example.com", "password123");
+    let app = lockout_app!(app_state);
+    
+    // Spreading guesses over accounts does not get around the per-address limit
+    for email in ["a@example.com", "b@example.com", "c@example.com"] {
+        assert_eq!(attempt(&app, email, "guess", Some("203.0.113.7:4000")).await.status(), 401);
+    }
+    let resp = attempt(&app, "victim@example.com", "password123", Some("203.0.113.7:4001")).await;
+    assert_eq!(resp.status(), 429);
+    assert!(retry_after(&resp) > 0);
+    
+    let resp = attempt(&app, "victim@example.com", "password123", Some("198.51.100.2:4000")).await;
+    assert!(resp.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_successful_login_resets_account_failures() {
+    let app_state = create_test_app_state();
+    insert_user(&app_state, "reset@example.com", "password123");
+    let app = lockout_app!(app_state.clone());
+    
+    assert_eq!(attempt(&app, "reset@example.com", "guess", None).await.status(), 401);
+    assert_eq!(throttle_rows(&app_state), 1);
+    
+    assert!(attempt(&app, "reset@example.com", "password123", None).await.status().is_success());
+    assert_eq!(throttle_rows(&app_state), 0);
+}
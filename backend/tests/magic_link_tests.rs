@@ -0,0 +1,163 @@
+use actix_web::{test, App, web};
+use chrono::Duration;
+use serde_json::json;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::magic_link::{request_magic_link, verify_magic_link};
+use surjo_backend::handlers::sessions::list_my_sessions;
+use surjo_backend::handlers::users::create_user;
+use surjo_backend::models::{OneTimeToken, TokenPurpose, User};
+
+mod common;
+use common::{
+    access_token, bearer, create_test_app_state_with_mailer, deactivate_user, insert_user, token_from_email, verify_email,
+};
+
+macro_rules! magic_link_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(request_magic_link)
+                .service(verify_magic_link)
+                .service(login)
+                .service(create_user)
+                .service(list_my_sessions)
+        )
+        .await
+    };
+}
+
+#[actix_rt::test]
+async fn test_magic_link_creates_passwordless_account() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let app = magic_link_app!(app_state.clone());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "new@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    
+    let messages = mailer.messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].to, "new@example.com");
+    assert!(messages[0].text_body.contains("http://app.test/magic-link?token="));
+    let token = token_from_email(&messages[0].text_body);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link/verify")
+        .set_json(json!({ "token": token }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["token"].is_string());
+    assert_eq!(body["user"]["email"], "new@example.com");
+    assert_eq!(body["user"]["email_verified"], true);
+    
+    {
+        let database = app_state.database.lock().unwrap();
+        let user_id = body["user"]["id"].as_str().unwrap();
+        assert!(!User::has_password(database.get_connection(), user_id).unwrap());
+    }
+    
+    // Links only work once
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link/verify")
+        .set_json(json!({ "token": token }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_rt::test]
+async fn test_magic_link_signs_in_existing_user() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let user = insert_user(&app_state, "existing@example.com", "password123");
+    verify_email(&app_state, &user.id);
+    let app = magic_link_app!(app_state.clone());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "existing@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    let token = token_from_email(&mailer.messages()[0].text_body);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link/verify")
+        .set_json(json!({ "token": token }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["user"]["id"], user.id);
+    
+    // The password keeps working alongside the link
+    let database = app_state.database.lock().unwrap();
+    assert!(User::has_password(database.get_connection(), &user.id).unwrap());
+}
+
+// Someone who registers an address they do not own must not keep a way in once
+// its owner signs in with a link
+#[actix_rt::test]
+async fn test_magic_link_takes_unverified_accounts_from_squatters() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let app = magic_link_app!(app_state.clone());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/users")
+        .set_json(json!({ "email": "victim@example.com", "password": "squatter-password" }))
+        .to_request();
+    let squatter: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let squatter_token = access_token(&app_state, squatter["id"].as_str().unwrap());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "victim@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    let token = token_from_email(&mailer.messages().last().unwrap().text_body);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link/verify")
+        .set_json(json!({ "token": token }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["user"]["id"], squatter["id"]);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": "victim@example.com", "password": "squatter-password" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&squatter_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_magic_link_refuses_expired_links_and_disabled_accounts() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let user = insert_user(&app_state, "expired@example.com", "password123");
+    let disabled = insert_user(&app_state, "disabled@example.com", "password123");
+    deactivate_user(&app_state, &disabled.id);
+    let expired = {
+        let database = app_state.database.lock().unwrap();
+        OneTimeToken::issue(database.get_connection(), &user.id, TokenPurpose::MagicLink, Duration::seconds(-1)).unwrap()
+    };
+    let app = magic_link_app!(app_state);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link/verify")
+        .set_json(json!({ "token": expired }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/magic-link")
+        .set_json(json!({ "email": "disabled@example.com" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    assert!(mailer.messages().is_empty());
+}
@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use surjo_backend::mailer::{
+    BackgroundMailer, EmailMessage, EmailTemplate, FileMailer, MaildirMailer, Mailer, MemoryMailer,
+};
+
+const SENDER: &str = "Surjo <no-reply@surjo.test>";
+
+fn temp_dir() -> PathBuf {
+    std::env::temp_dir().join(format!("surjo-mail-{}", uuid::Uuid::new_v4()))
+}
+
+fn message() -> EmailMessage {
+    EmailMessage {
+        to: "someone@example.com".to_string(),
+        subject: "Hello".to_string(),
+        text_body: "Body text".to_string(),
+        html_body: Some("<p>Body html</p>".to_string()),
+    }
+}
+
+#[actix_rt::test]
+async fn test_template_rendering() {
+    let template = EmailTemplate {
+        subject: "Hi {{name}}",
+        text: "Hello {{ name }}, visit {{link}} {{unknown}}",
+        html: Some("<p>Hello {{name}}</p>"),
+    };
+    
+    let rendered = template.render(
+        "someone@example.com",
+        &HashMap::from([
+            ("name", "<Ann & Bob>".to_string()),
+            ("link", "http://app.test/?a=1".to_string()),
+        ]),
+    );
+    
+    assert_eq!(rendered.to, "someone@example.com");
+    assert_eq!(rendered.subject, "Hi <Ann & Bob>");
+    // Unknown placeholders are left alone so they stand out
+    assert_eq!(rendered.text_body, "Hello <Ann & Bob>, visit http://app.test/?a=1 {{unknown}}");
+    // HTML bodies get escaped values
+    assert_eq!(rendered.html_body.unwrap(), "<p>Hello &lt;Ann &amp; Bob&gt;</p>");
+}
+
+#[actix_rt::test]
+async fn test_file_mailer_writes_eml() {
+    let dir = temp_dir();
+    let mailer = FileMailer::new(dir.clone(), SENDER.to_string());
+    
+    mailer.send(&message()).unwrap();
+    
+    let files: Vec<_> = std::fs::read_dir(&dir).unwrap().collect();
+    assert_eq!(files.len(), 1);
+    let contents = std::fs::read_to_string(files[0].as_ref().unwrap().path()).unwrap();
+    assert!(contents.contains("To: someone@example.com"));
+    assert!(contents.contains("Subject: Hello"));
+    assert!(contents.contains("multipart/alternative"));
+    assert!(contents.contains("Body text"));
+    assert!(contents.contains("<p>Body html</p>"));
+    
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_maildir_mailer_delivers_to_new() {
+    let dir = temp_dir();
+    let mailer = MaildirMailer::new(dir.clone(), SENDER.to_string());
+    
+    mailer.send(&message()).unwrap();
+    
+    assert_eq!(std::fs::read_dir(dir.join("tmp")).unwrap().count(), 0);
+    assert!(dir.join("cur").is_dir());
+    let delivered: Vec<_> = std::fs::read_dir(dir.join("new")).unwrap().collect();
+    assert_eq!(delivered.len(), 1);
+    let contents = std::fs::read_to_string(delivered[0].as_ref().unwrap().path()).unwrap();
+    assert!(contents.contains("Subject: Hello"));
+    
+    std::fs::remove_dir_all(dir).unwrap();
+}
+
+#[actix_rt::test]
+async fn test_file_mailer_rejects_invalid_recipient() {
+    let dir = temp_dir();
+    let mailer = FileMailer::new(dir.clone(), SENDER.to_string());
+    
+    let mut invalid = message();
+    invalid.to = "not an address".to_string();
+    assert!(mailer.send(&invalid).is_err());
+    assert!(!dir.exists());
+}
+
+#[actix_rt::test]
+async fn test_background_mailer_delivers_asynchronously() {
+    let memory = Arc::new(MemoryMailer::new());
+    let mailer = BackgroundMailer::new(memory.clone());
+    
+    mailer.send(&message()).unwrap();
+    
+    // Delivery happens on the worker thread
+    let mut waited = Duration::ZERO;
+    while memory.messages().is_empty() && waited < Duration::from_secs(5) {
+        std::thread::sleep(Duration::from_millis(10));
+        waited += Duration::from_millis(10);
+    }
+    assert_eq!(memory.messages(), vec![message()]);
+}
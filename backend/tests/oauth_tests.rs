@@ -0,0 +1,187 @@
+use actix_web::{test, web, App, HttpResponse, HttpServer};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use serde_json::json;
+use surjo_backend::handlers::auth::google_auth;
+use surjo_backend::models::{AppState, GoogleAuthRequest};
+use std::net::TcpListener;
+
+mod common;
+use common::{create_test_app_state, insert_user, verify_email};
+
+// Client id the mock server issues ID tokens for, matching create_test_app_state
+const MOCK_CLIENT_ID: &str = "test-client-id";
+
+// Accounts known to the mock Google server, keyed by authorization code:
+// (code, sub, email, email_verified)
+const MOCK_ACCOUNTS: [(&str, &str, &str, bool); 3] = [
+    ("new-user-code", "google-sub-1", "new@example.com", true),
+    ("existing-user-code", "google-sub-2", "existing@example.com", true),
+    ("unverified-code", "google-sub-3", "unverified@example.com", false),
+];
+
+async fn mock_token(form: web::Form<std::collections::HashMap<String, String>>) -> HttpResponse {
+    let code = form.get("code").cloned().unwrap_or_default();
+    let Some((_, sub, _, _)) = MOCK_ACCOUNTS.iter().find(|(c, ..)| *c == code) else {
+        return HttpResponse::BadRequest().json(json!({ "error": "invalid_grant" }));
+    };
+    
+    let now = chrono::Utc::now().timestamp();
+    let id_token = encode(
+        &Header::default(),
+        &json!({
+            "iss": "https://accounts.google.com",
+            "aud": MOCK_CLIENT_ID,
+            "sub": sub,
+            "iat": now,
+            "exp": now + 3600,
+        }),
+        &EncodingKey::from_secret(b"mock-google-key"),
+    ).unwrap();
+    
+    HttpResponse::Ok().json(json!({
+        "access_token": format!("access-{code}"),
+        "id_token": id_token,
+        "token_type": "Bearer",
+    }))
+}
+
+async fn mock_userinfo(req: actix_web::HttpRequest) -> HttpResponse {
+    let auth = req.headers().get("Authorization").and_then(|h| h.to_str().ok()).unwrap_or_default();
+    let code = auth.trim_start_matches("Bearer access-");
+    match MOCK_ACCOUNTS.iter().find(|(c, ..)| *c == code) {
+        Some((_, sub, email, verified)) => HttpResponse::Ok().json(json!({
+            "sub": sub,
+            "email": email,
+            "email_verified": verified,
+            "given_name": "Google",
+            "family_name": "User",
+        })),
+        None => HttpResponse::Unauthorized().finish(),
+    }
+}
+
+// Starts the mock Google server and points the app state at it
+fn start_mock_google(state: &mut AppState) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    
+    let server = HttpServer::new(|| {
+        App::new()
+            .route("/token", web::post().to(mock_token))
+            .route("/userinfo", web::get().to(mock_userinfo))
+    })
+    .workers(1)
+    .listen(listener)
+    .unwrap()
+    .run();
+    actix_rt::spawn(server);
+    
+    state.google.token_url = format!("{base_url}/token");
+    state.google.userinfo_url = format!("{base_url}/userinfo");
+}
+
+async fn google_login(state: &AppState, code: &str) -> (actix_web::http::StatusCode, serde_json::Value) {
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(state.clone()))
+            .service(google_auth)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/google")
+        .set_json(&GoogleAuthRequest { code: code.to_string() })
+        .to_request();
+    
+    let resp = test::call_service(&app, req).await;
+    let status = resp.status();
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    (status, body)
+}
+
+#[actix_rt::test]
+async fn test_google_auth_creates_user() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    
+    let (status, body) = google_login(&app_state, "new-user-code").await;
+    assert_eq!(status, 200);
+    assert!(body["token"].is_string());
+    assert_eq!(body["user"]["email"], "new@example.com");
+    assert_eq!(body["user"]["first_name"], "Google");
+    assert_eq!(body["user"]["email_verified"], true);
+    
+    // Signing in again resolves to the same account through oauth_providers
+    let (status, again) = google_login(&app_state, "new-user-code").await;
+    assert_eq!(status, 200);
+    assert_eq!(again["user"]["id"], body["user"]["id"]);
+}
+
+#[actix_rt::test]
+async fn test_google_auth_links_existing_verified_email() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    let user = insert_user(&app_state, "existing@example.com", "password123");
+    verify_email(&app_state, &user.id);
+    
+    let (status, body) = google_login(&app_state, "existing-user-code").await;
+    assert_eq!(status, 200);
+    assert_eq!(body["user"]["id"], user.id);
+    
+    let database = app_state.database.lock().unwrap();
+    let linked: String = database.get_connection().query_row(
+        "SELECT user_id FROM oauth_providers WHERE provider = 'google' AND provider_user_id = 'google-sub-2'",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(linked, user.id);
+}
+
+#[actix_rt::test]
+async fn test_google_auth_refuses_unverified_email_link() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    insert_user(&app_state, "unverified@example.com", "password123");
+    
+    let (status, _) = google_login(&app_state, "unverified-code").await;
+    assert_eq!(status, 409);
+}
+
+// Otherwise whoever registered the address first would keep a password into the
+// account its real owner signs in to with Google
+#[actix_rt::test]
+async fn test_google_auth_refuses_to_link_unverified_local_account() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    insert_user(&app_state, "existing@example.com", "password123");
+    
+    let (status, _) = google_login(&app_state, "existing-user-code").await;
+    assert_eq!(status, 409);
+    
+    let database = app_state.database.lock().unwrap();
+    let links: i64 = database.get_connection().query_row(
+        "SELECT COUNT(*) FROM oauth_providers WHERE provider_user_id = 'google-sub-2'",
+        [],
+        |row| row.get(0),
+    ).unwrap();
+    assert_eq!(links, 0);
+}
+
+#[actix_rt::test]
+async fn test_google_auth_invalid_code() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    
+    let (status, _) = google_login(&app_state, "bogus-code").await;
+    assert_eq!(status, 401);
+}
+
+#[actix_rt::test]
+async fn test_google_auth_rejects_wrong_audience() {
+    let mut app_state = create_test_app_state();
+    start_mock_google(&mut app_state);
+    // ID tokens issued for another client must not be accepted
+    app_state.google.client_id = "other-client".to_string();
+    
+    let (status, _) = google_login(&app_state, "new-user-code").await;
+    assert_eq!(status, 401);
+}
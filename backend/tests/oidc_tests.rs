@@ -0,0 +1,165 @@
+use actix_web::{test, web, App};
+use serde_json::json;
+use surjo_backend::handlers::oauth::{oauth_callback, start_oauth};
+use surjo_backend::models::OAuthIdentity;
+
+mod common;
+use common::oidc::{grant, start_mock_provider};
+use common::{create_test_app_state, insert_user, verify_email};
+
+macro_rules! oidc_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(start_oauth)
+                .service(oauth_callback)
+        )
+        .await
+    };
+}
+
+// Starts sign-in and has the mock provider authorize `hint`, returning the code
+// and state the web app would receive
+async fn authorize<S>(app: &S, provider: &str, hint: &str) -> (String, String)
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/auth/oauth/{provider}/start"))
+        .to_request();
+    let started: serde_json::Value = test::call_and_read_body_json(app, req).await;
+    grant(started["authorization_url"].as_str().unwrap(), hint).await
+}
+
+async fn callback<S>(app: &S, provider: &str, code: &str, state: &str) -> (u16, serde_json::Value)
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/auth/oauth/{provider}/callback"))
+        .set_json(json!({ "code": code, "state": state }))
+        .to_request();
+    let resp = test::call_service(app, req).await;
+    let status = resp.status().as_u16();
+    let body = serde_json::from_slice(&test::read_body(resp).await).unwrap_or_default();
+    (status, body)
+}
+
+#[actix_rt::test]
+async fn test_oidc_sign_in_creates_and_reuses_user() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    let app = oidc_app!(app_state.clone());
+    
+    let req = test::TestRequest::get().uri("/api/auth/oauth/acme/start").to_request();
+    let started: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let url = started["authorization_url"].as_str().unwrap();
+    assert!(url.contains("/authorize?response_type=code&client_id=mock-client"));
+    assert!(url.contains("code_challenge_method=S256"));
+    assert!(url.contains("nonce="));
+    assert!(url.contains("scope=openid+email"));
+    
+    let (code, state) = authorize(&app, "acme", "new").await;
+    let (status, body) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["user"]["email"], "new@example.com");
+    assert_eq!(body["user"]["first_name"], "Issuer");
+    assert_eq!(body["user"]["email_verified"], true);
+    let user_id = body["user"]["id"].clone();
+    
+    {
+        let database = app_state.database.lock().unwrap();
+        let linked = OAuthIdentity::find_user_id(database.get_connection(), "acme", "subject-1").unwrap();
+        assert_eq!(linked.as_deref(), user_id.as_str());
+    }
+    
+    // The state cannot be replayed
+    let (status, _) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 400);
+    
+    let (code, state) = authorize(&app, "acme", "new").await;
+    let (status, body) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["user"]["id"], user_id);
+}
+
+#[actix_rt::test]
+async fn test_oidc_links_only_verified_emails() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    let existing = insert_user(&app_state, "existing@example.com", "password123");
+    verify_email(&app_state, &existing.id);
+    let unverified = insert_user(&app_state, "unverified@example.com", "password123");
+    verify_email(&app_state, &unverified.id);
+    let app = oidc_app!(app_state);
+    
+    let (code, state) = authorize(&app, "acme", "existing").await;
+    let (status, body) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["user"]["id"], existing.id);
+    
+    let (code, state) = authorize(&app, "acme", "unverified").await;
+    let (status, _) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 409);
+}
+
+#[actix_rt::test]
+async fn test_callback_rejects_mismatched_state() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    start_mock_provider(&mut app_state, "other", false);
+    let app = oidc_app!(app_state);
+    
+    let (status, _) = callback(&app, "missing", "code", "state").await;
+    assert_eq!(status, 404);
+    let (status, _) = callback(&app, "acme", "code", "not-a-state").await;
+    assert_eq!(status, 400);
+    
+    // A state only works with the provider it was issued for
+    let (code, state) = authorize(&app, "acme", "new").await;
+    let (status, _) = callback(&app, "other", &code, &state).await;
+    assert_eq!(status, 400);
+    
+    // Finishing with another request's state sends the wrong PKCE verifier
+    let (code, _) = authorize(&app, "acme", "new").await;
+    let (_, state) = authorize(&app, "acme", "new").await;
+    let (status, _) = callback(&app, "acme", &code, &state).await;
+    assert_eq!(status, 401);
+}
+
+#[actix_rt::test]
+async fn test_discovery_must_match_issuer() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "acme", false);
+    let issuer = app_state.oauth_providers[0].issuer.clone().unwrap();
+    app_state.oauth_providers[0].issuer = Some(format!("{issuer}/"));
+    let app = oidc_app!(app_state);
+    
+    let req = test::TestRequest::get().uri("/api/auth/oauth/acme/start").to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 502);
+}
+
+#[actix_rt::test]
+async fn test_plain_oauth2_provider() {
+    let mut app_state = create_test_app_state();
+    start_mock_provider(&mut app_state, "github", true);
+    let existing = insert_user(&app_state, "existing@example.com", "password123");
+    verify_email(&app_state, &existing.id);
+    let app = oidc_app!(app_state);
+    
+    let req = test::TestRequest::get().uri("/api/auth/oauth/github/start").to_request();
+    let started: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(!started["authorization_url"].as_str().unwrap().contains("nonce="));
+    
+    let (code, state) = authorize(&app, "github", "new").await;
+    let (status, body) = callback(&app, "github", &code, &state).await;
+    assert_eq!(status, 200);
+    assert_eq!(body["user"]["email"], "new@example.com");
+    assert_eq!(body["user"]["email_verified"], false);
+    
+    // Without a verified email the account cannot be linked to an existing user
+    let (code, state) = authorize(&app, "github", "existing").await;
+    let (status, _) = callback(&app, "github", &code, &state).await;
+    assert_eq!(status, 409);
+}
@@ -0,0 +1,413 @@
+use actix_web::{test, App, web};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use ciborium::Value;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use rand::RngCore;
+use serde_json::json;
+use sha2::{Digest, Sha256};
+use surjo_backend::handlers::passkeys::{
+    delete_my_passkey, finish_passkey_login, finish_passkey_registration, list_my_passkeys, rename_my_passkey,
+    start_passkey_login, start_passkey_registration,
+};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, insert_user};
+
+const RP_ID: &str = "app.test";
+const ORIGIN: &str = "http://app.test";
+
+// Software authenticator producing the same structures a browser would hand back
+struct SoftAuthenticator {
+    signing_key: SigningKey,
+    credential_id: Vec<u8>,
+    user_handle: Option<String>,
+    sign_count: u32,
+    origin: String,
+}
+
+impl SoftAuthenticator {
+    fn new() -> Self {
+        let mut secret = [0u8; 32];
+        let mut credential_id = vec![0u8; 16];
+        rand::rng().fill_bytes(&mut secret);
+        rand::rng().fill_bytes(&mut credential_id);
+        SoftAuthenticator {
+            signing_key: SigningKey::from_slice(&secret).expect("Valid P-256 scalar"),
+            credential_id,
+            user_handle: None,
+            sign_count: 0,
+            origin: ORIGIN.to_string(),
+        }
+    }
+    
+    fn id(&self) -> String {
+        URL_SAFE_NO_PAD.encode(&self.credential_id)
+    }
+    
+    fn client_data(&self, ceremony: &str, challenge: &str) -> Vec<u8> {
+        json!({ "type": ceremony, "challenge": challenge, "origin": self.origin, "crossOrigin": false })
+            .to_string()
+            .into_bytes()
+    }
+    
+    fn authenticator_data(&self, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(RP_ID.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+    
+    // Answers `navigator.credentials.create()` with the given options
+    fn register(&mut self, options: &serde_json::Value, name: Option<&str>) -> serde_json::Value {
+        self.user_handle = options["user"]["id"].as_str().map(str::to_string);
+        let client_data = self.client_data("webauthn.create", options["challenge"].as_str().unwrap());
+        
+        let point = self.signing_key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (Value::Integer(1.into()), Value::Integer(2.into())),
+            (Value::Integer(3.into()), Value::Integer((-7).into())),
+            (Value::Integer((-1).into()), Value::Integer(1.into())),
+            (Value::Integer((-2).into()), Value::Bytes(point.x().unwrap().to_vec())),
+            (Value::Integer((-3).into()), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+        
+        // User present, user verified, attested credential data included
+        let mut auth_data = self.authenticator_data(0x45);
+        auth_data.extend_from_slice(&[0u8; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+        
+        let attestation = Value::Map(vec![
+            (Value::Text("fmt".to_string()), Value::Text("none".to_string())),
+            (Value::Text("attStmt".to_string()), Value::Map(vec![])),
+            (Value::Text("authData".to_string()), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+        
+        json!({
+            "name": name,
+            "credential": {
+                "id": self.id(),
+                "response": {
+                    "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                    "attestationObject": URL_SAFE_NO_PAD.encode(attestation_object),
+                }
+            }
+        })
+    }
+    
+    // Answers `navigator.credentials.get()`, bumping the signature counter
+    fn assert(&mut self, options: &serde_json::Value) -> serde_json::Value {
+        self.sign_count += 1;
+        let client_data = self.client_data("webauthn.get", options["challenge"].as_str().unwrap());
+        let auth_data = self.authenticator_data(0x05);
+        
+        let mut signed = auth_data.clone();
+        signed.extend_from_slice(&Sha256::digest(&client_data));
+        let signature: Signature = self.signing_key.sign(&signed);
+        
+        json!({
+            "id": self.id(),
+            "response": {
+                "clientDataJSON": URL_SAFE_NO_PAD.encode(client_data),
+                "authenticatorData": URL_SAFE_NO_PAD.encode(auth_data),
+                "signature": URL_SAFE_NO_PAD.encode(signature.to_der()),
+                "userHandle": self.user_handle,
+            }
+        })
+    }
+}
+
+macro_rules! passkey_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(start_passkey_registration)
+                .service(finish_passkey_registration)
+                .service(list_my_passkeys)
+                .service(rename_my_passkey)
+                .service(delete_my_passkey)
+                .service(start_passkey_login)
+                .service(finish_passkey_login)
+        )
+        .await
+    };
+}
+
+async fn register_passkey<S>(app: &S, token: &str, authenticator: &mut SoftAuthenticator, name: Option<&str>) -> u16
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/me/passkeys/register/start")
+        .insert_header(bearer(token))
+        .to_request();
+    let options: serde_json::Value = test::call_and_read_body_json(app, req).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/passkeys/register/finish")
+        .insert_header(bearer(token))
+        .set_json(authenticator.register(&options, name))
+        .to_request();
+    test::call_service(app, req).await.status().as_u16()
+}
+
+async fn login_options<S>(app: &S) -> serde_json::Value
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post().uri("/api/auth/passkey/start").to_request();
+    test::call_and_read_body_json(app, req).await
+}
+
+#[actix_rt::test]
+async fn test_register_and_sign_in_with_passkey() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "passkey@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = passkey_app!(app_state);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/passkeys/register/start")
+        .insert_header(bearer(&token))
+        .to_request();
+    let options: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(options["rp"]["id"], RP_ID);
+    assert_eq!(options["user"]["name"], "passkey@example.com");
+    assert_eq!(options["pubKeyCredParams"][0]["alg"], -7);
+    
+    let mut authenticator = SoftAuthenticator::new();
+    let req = test::TestRequest::post()
+        .uri("/api/me/passkeys/register/finish")
+        .insert_header(bearer(&token))
+        .set_json(authenticator.register(&options, Some("Laptop")))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let passkey: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(passkey["name"], "Laptop");
+    assert!(passkey["last_used_at"].is_null());
+    
+    // Usernameless sign-in: the authenticator picks the account
+    let options = login_options(&app).await;
+    assert_eq!(options["rpId"], RP_ID);
+    assert_eq!(options["allowCredentials"].as_array().unwrap().len(), 0);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(authenticator.assert(&options))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["token"].is_string());
+    assert_eq!(body["user"]["id"], user.id.as_str());
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/passkeys")
+        .insert_header(bearer(&token))
+        .to_request();
+    let passkeys: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(passkeys[0]["last_used_at"].is_string());
+}
+
+#[actix_rt::test]
+async fn test_challenges_are_single_use_and_origin_bound() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "replay@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = passkey_app!(app_state);
+    
+    let mut phishing = SoftAuthenticator::new();
+    phishing.origin = "http://evil.test".to_string();
+    assert_eq!(register_passkey(&app, &token, &mut phishing, None).await, 400);
+    
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &token, &mut authenticator, None).await, 201);
+    
+    let options = login_options(&app).await;
+    let assertion = authenticator.assert(&options);
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(&assertion)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(&assertion)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_sign_counter_must_increase() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "counter@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = passkey_app!(app_state);
+    
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &token, &mut authenticator, None).await, 201);
+    
+    authenticator.sign_count = 10;
+    let options = login_options(&app).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(authenticator.assert(&options))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    
+    // A cloned authenticator would lag behind the stored counter
+    authenticator.sign_count = 4;
+    let options = login_options(&app).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(authenticator.assert(&options))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_passkey_must_belong_to_user_handle() {
+    let app_state = create_test_app_state();
+    let alice = insert_user(&app_state, "alice@example.com", "password123");
+    let bob = insert_user(&app_state, "bob@example.com", "password123");
+    let alice_token = access_token(&app_state, &alice.id);
+    let bob_token = access_token(&app_state, &bob.id);
+    let app = passkey_app!(app_state);
+    
+    let mut alice_key = SoftAuthenticator::new();
+    let mut bob_key = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &alice_token, &mut alice_key, None).await, 201);
+    assert_eq!(register_passkey(&app, &bob_token, &mut bob_key, None).await, 201);
+    
+    alice_key.user_handle = bob_key.user_handle.clone();
+    let options = login_options(&app).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(alice_key.assert(&options))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_login_options_do_not_reveal_accounts() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "known@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = passkey_app!(app_state);
+    
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &token, &mut authenticator, None).await, 201);
+    
+    // Naming an account, real or not, gets the same discoverable ceremony
+    for email in ["known@example.com", "unknown@example.com"] {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/passkey/start")
+            .set_json(json!({ "email": email }))
+            .to_request();
+        let options: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(options["allowCredentials"], json!([]));
+    }
+}
+
+#[actix_rt::test]
+async fn test_failed_assertions_lock_the_account() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.login_throttle.account.max_failures = 2;
+    app_state.policy.login_throttle.account.progressive_delay = false;
+    let user = insert_user(&app_state, "locked@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let app = passkey_app!(app_state);
+    
+    let mut authenticator = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &token, &mut authenticator, None).await, 201);
+    
+    // Assertions signed by the wrong key count as failed attempts
+    let mut forger = SoftAuthenticator::new();
+    forger.credential_id = authenticator.credential_id.clone();
+    for _ in 0..2 {
+        let options = login_options(&app).await;
+        let req = test::TestRequest::post()
+            .uri("/api/auth/passkey/finish")
+            .set_json(forger.assert(&options))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+    
+    let options = login_options(&app).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(authenticator.assert(&options))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 423);
+    assert!(resp.headers().contains_key("retry-after"));
+}
+
+#[actix_rt::test]
+async fn test_manage_multiple_passkeys() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "many@example.com", "password123");
+    let other = insert_user(&app_state, "other@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let other_token = access_token(&app_state, &other.id);
+    let app = passkey_app!(app_state);
+    
+    let mut phone = SoftAuthenticator::new();
+    let mut laptop = SoftAuthenticator::new();
+    assert_eq!(register_passkey(&app, &token, &mut phone, Some("Phone")).await, 201);
+    assert_eq!(register_passkey(&app, &token, &mut laptop, None).await, 201);
+    assert_eq!(register_passkey(&app, &token, &mut laptop, None).await, 409);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/passkeys")
+        .insert_header(bearer(&token))
+        .to_request();
+    let passkeys: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let passkeys = passkeys.as_array().unwrap();
+    assert_eq!(passkeys.len(), 2);
+    assert_eq!(passkeys[0]["name"], "Phone");
+    assert_eq!(passkeys[1]["name"], "Passkey");
+    let laptop_id = passkeys[1]["id"].as_str().unwrap().to_string();
+    
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/me/passkeys/{laptop_id}"))
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "Work laptop" }))
+        .to_request();
+    let renamed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(renamed["name"], "Work laptop");
+    
+    // Other users can neither rename nor remove it
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/me/passkeys/{laptop_id}"))
+        .insert_header(bearer(&other_token))
+        .set_json(json!({ "name": "Mine now" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/passkeys/{laptop_id}"))
+        .insert_header(bearer(&other_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/passkeys/{laptop_id}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    // A removed passkey no longer signs in
+    let options = login_options(&app).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/passkey/finish")
+        .set_json(laptop.assert(&options))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
@@ -0,0 +1,131 @@
+use actix_web::{test, App, web};
+use chrono::Duration;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::password::{confirm_password_reset, request_password_reset};
+use surjo_backend::handlers::sessions::list_my_sessions;
+use surjo_backend::models::{
+    LoginRequest, OneTimeToken, PasswordResetConfirmRequest, PasswordResetRequest, TokenPurpose,
+};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state_with_mailer, insert_user, token_from_email};
+
+#[actix_rt::test]
+async fn test_password_reset_flow() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let user = insert_user(&app_state, "reset@example.com", "old-password");
+    let old_token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(request_password_reset)
+            .service(confirm_password_reset)
+            .service(list_my_sessions)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/request")
+        .set_json(&PasswordResetRequest { email: "reset@example.com".to_string() })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    
+    let messages = mailer.messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].to, "reset@example.com");
+    assert!(messages[0].text_body.contains("http://app.test/reset-password?token="));
+    assert!(messages[0].html_body.as_ref().unwrap().contains("<a href=\"http://app.test/reset-password?token="));
+    let reset_token = token_from_email(&messages[0].text_body);
+    
+    // Too short passwords are refused without using up the token
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/confirm")
+        .set_json(&PasswordResetConfirmRequest {
+            token: reset_token.clone(),
+            new_password: "short".to_string(),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/confirm")
+        .set_json(&PasswordResetConfirmRequest {
+            token: reset_token.clone(),
+            new_password: "new-password".to_string(),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    // Existing sessions were revoked
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&old_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    // The token is single-use
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/confirm")
+        .set_json(&PasswordResetConfirmRequest {
+            token: reset_token,
+            new_password: "another-password".to_string(),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    for (password, expected) in [("old-password", 401), ("new-password", 200)] {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(&LoginRequest {
+                email: "reset@example.com".to_string(),
+                password: password.to_string(),
+            })
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), expected);
+    }
+}
+
+#[actix_rt::test]
+async fn test_password_reset_request_does_not_reveal_unknown_email() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(request_password_reset)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/request")
+        .set_json(&PasswordResetRequest { email: "ghost@example.com".to_string() })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    assert!(mailer.messages().is_empty());
+}
+
+#[actix_rt::test]
+async fn test_password_reset_token_expires() {
+    let (app_state, _mailer) = create_test_app_state_with_mailer();
+    let user = insert_user(&app_state, "late@example.com", "old-password");
+    let token = {
+        let database = app_state.database.lock().unwrap();
+        OneTimeToken::issue(database.get_connection(), &user.id, TokenPurpose::PasswordReset, Duration::minutes(-1))
+            .unwrap()
+    };
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(confirm_password_reset)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/password-reset/confirm")
+        .set_json(&PasswordResetConfirmRequest {
+            token,
+            new_password: "new-password".to_string(),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
@@ -0,0 +1,298 @@
+use actix_web::{test, App, HttpResponse, web};
+use serde_json::json;
+use surjo_backend::handlers::groups::{add_group_manager, add_group_member, create_group};
+use surjo_backend::handlers::permissions::{
+    create_permission, delete_permission, get_user_permissions, grant_user_permission, list_permissions,
+    revoke_user_permission,
+};
+use surjo_backend::handlers::users::{get_user, list_users, update_user};
+use surjo_backend::middleware::RequirePermission;
+use surjo_backend::models::UpdateUserRequest;
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+#[actix_rt::test]
+async fn test_list_users_requires_admin() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "plain@example.com", "password123");
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(list_users)
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&access_token(&app_state, &user.id)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&access_token(&app_state, &admin.id)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["users"].as_array().unwrap().len(), 2);
+}
+
+#[actix_rt::test]
+async fn test_users_can_only_access_their_own_record() {
+    let app_state = create_test_app_state();
+    let alice = insert_user(&app_state, "alice@example.com", "password123");
+    let bob = insert_user(&app_state, "bob@example.com", "password123");
+    let alice_token = access_token(&app_state, &alice.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(get_user)
+            .service(update_user)
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", alice.id))
+        .insert_header(bearer(&alice_token))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", bob.id))
+        .insert_header(bearer(&alice_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/users/{}", bob.id))
+        .insert_header(bearer(&alice_token))
+        .set_json(&UpdateUserRequest {
+            first_name: Some("Hijacked".to_string()),
+            last_name: None,
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+}
+
+#[actix_rt::test]
+async fn test_admin_can_update_other_users() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let bob = insert_user(&app_state, "bob@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let admin_token = access_token(&app_state, &admin.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(update_user)
+    ).await;
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/users/{}", bob.id))
+        .insert_header(bearer(&admin_token))
+        .set_json(&UpdateUserRequest {
+            first_name: Some("Robert".to_string()),
+            last_name: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body["first_name"], "Robert");
+}
+
+#[actix_rt::test]
+async fn test_require_permission_scope() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(
+                web::scope("/admin")
+                    .wrap(RequirePermission("admin"))
+                    .route("/ping", web::get().to(|| async { HttpResponse::Ok().json("pong") }))
+            )
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/admin/ping")
+        .insert_header(bearer(&access_token(&app_state, &user.id)))
+        .to_request();
+    let err = test::try_call_service(&app, req).await.unwrap_err();
+    assert_eq!(err.as_response_error().status_code(), 403);
+    
+    let req = test::TestRequest::get()
+        .uri("/admin/ping")
+        .insert_header(bearer(&access_token(&app_state, &admin.id)))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_admin_manages_permissions_and_direct_grants() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "plain@example.com", "password123");
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let token = access_token(&app_state, &admin.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(list_permissions)
+            .service(create_permission)
+            .service(delete_permission)
+            .service(get_user_permissions)
+            .service(grant_user_permission)
+            .service(revoke_user_permission)
+    ).await;
+    
+    let req = test::TestRequest::get()
+        .uri("/api/permissions")
+        .insert_header(bearer(&access_token(&app_state, &user.id)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/permissions")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "reports read" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let req = test::TestRequest::post()
+        .uri("/api/permissions")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "reports.read", "description": "View reports" }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let permission: serde_json::Value = test::read_body_json(resp).await;
+    let req = test::TestRequest::post()
+        .uri("/api/permissions")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "reports.read" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+    
+    let grant_uri = format!("/api/users/{}/permissions/{}", user.id, permission["id"].as_str().unwrap());
+    let req = test::TestRequest::put().uri(&grant_uri).insert_header(bearer(&token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}/permissions", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(body["permissions"], json!(["reports.read"]));
+    assert_eq!(body["direct"], json!(["reports.read"]));
+    
+    let req = test::TestRequest::delete().uri(&grant_uri).insert_header(bearer(&token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let req = test::TestRequest::delete().uri(&grant_uri).insert_header(bearer(&token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    // The seeded permissions are load-bearing and cannot be deleted
+    let req = test::TestRequest::get().uri("/api/permissions").insert_header(bearer(&token)).to_request();
+    let permissions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let admin_permission = permissions
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|permission| permission["name"] == "admin")
+        .unwrap();
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/permissions/{}", admin_permission["id"].as_str().unwrap()))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/permissions/{}", permission["id"].as_str().unwrap()))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+}
+
+#[actix_rt::test]
+async fn test_group_managers_manage_only_their_members() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let manager = insert_user(&app_state, "manager@example.com", "password123");
+    let member = insert_user(&app_state, "member@example.com", "password123");
+    let outsider = insert_user(&app_state, "outsider@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let admin_token = access_token(&app_state, &admin.id);
+    let manager_token = access_token(&app_state, &manager.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(get_user)
+            .service(update_user)
+            .service(create_group)
+            .service(add_group_member)
+            .service(add_group_manager)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/groups")
+        .insert_header(bearer(&admin_token))
+        .set_json(json!({ "name": "finance" }))
+        .to_request();
+    let group: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let group_uri = format!("/api/groups/{}", group["id"].as_str().unwrap());
+    
+    for user_id in [&manager.id, &member.id] {
+        let req = test::TestRequest::put()
+            .uri(&format!("{group_uri}/members/{user_id}"))
+            .insert_header(bearer(&admin_token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 204);
+    }
+    
+    // A plain member cannot see the others
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", member.id))
+        .insert_header(bearer(&manager_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    // Only members can be made managers
+    let req = test::TestRequest::put()
+        .uri(&format!("{group_uri}/managers/{}", outsider.id))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    let req = test::TestRequest::put()
+        .uri(&format!("{group_uri}/managers/{}", manager.id))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/users/{}", member.id))
+        .insert_header(bearer(&manager_token))
+        .set_json(json!({ "first_name": "Renamed" }))
+        .to_request();
+    let updated: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(updated["first_name"], "Renamed");
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}", outsider.id))
+        .insert_header(bearer(&manager_token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let body: String = test::read_body_json(resp).await;
+    assert_eq!(body, "Insufficient permissions");
+}
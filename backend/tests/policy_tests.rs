@@ -0,0 +1,35 @@
+use std::collections::HashSet;
+
+use surjo_backend::policy::{Actor, Policy, UserAction, UserPolicy, UserResource};
+
+fn actor(user_id: &str, permissions: &[&str], managed_groups: &[&str]) -> Actor {
+    Actor {
+        user_id: user_id.to_string(),
+        permissions: permissions.iter().map(|name| name.to_string()).collect(),
+        managed_groups: managed_groups.iter().map(|id| id.to_string()).collect(),
+    }
+}
+
+fn user(id: &str, groups: &[&str]) -> UserResource {
+    UserResource {
+        id: id.to_string(),
+        groups: groups.iter().map(|id| id.to_string()).collect::<HashSet<_>>(),
+    }
+}
+
+#[test]
+fn test_user_policy() {
+    let target = user("alice", &["finance"]);
+    
+    for action in [UserAction::Read, UserAction::Update] {
+        assert!(UserPolicy::can(&actor("alice", &[], &[]), action, &target));
+        assert!(UserPolicy::can(&actor("root", &["admin"], &[]), action, &target));
+        assert!(UserPolicy::can(&actor("bob", &[], &["finance"]), action, &target));
+        
+        assert!(!UserPolicy::can(&actor("bob", &["user"], &[]), action, &target));
+        assert!(!UserPolicy::can(&actor("bob", &[], &["sales"]), action, &target));
+    }
+    
+    // Managing a group says nothing about users outside it
+    assert!(!UserPolicy::can(&actor("bob", &[], &["finance"]), UserAction::Read, &user("carol", &[])));
+}
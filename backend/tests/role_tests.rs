@@ -0,0 +1,167 @@
+use actix_web::{test, App, web};
+use serde_json::json;
+use surjo_backend::handlers::groups::{add_group_member, create_group, list_group_members, remove_group_member};
+use surjo_backend::handlers::roles::{assign_role, create_role, delete_role, list_roles, unassign_role, update_role};
+use surjo_backend::models::{AppState, Permission, Role, ADMIN_ROLE};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, insert_user};
+
+macro_rules! role_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(list_roles)
+                .service(create_role)
+                .service(update_role)
+                .service(delete_role)
+                .service(assign_role)
+                .service(unassign_role)
+                .service(create_group)
+                .service(list_group_members)
+                .service(add_group_member)
+                .service(remove_group_member)
+        )
+        .await
+    };
+}
+
+fn insert_permission(state: &AppState, name: &str) {
+    let database = state.database.lock().unwrap();
+    database
+        .get_connection()
+        .execute("INSERT INTO permissions (id, name) VALUES (?1, ?1)", [name])
+        .expect("Failed to create permission");
+}
+
+fn assign_admin_role(state: &AppState, user_id: &str) {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    let role = Role::find_by_name(conn, ADMIN_ROLE).unwrap().expect("Admin role should be seeded");
+    Role::assign(conn, user_id, &role.id).unwrap();
+}
+
+fn permissions(state: &AppState, user_id: &str) -> Vec<String> {
+    let database = state.database.lock().unwrap();
+    let mut names: Vec<String> = Permission::names_for_user(database.get_connection(), user_id).unwrap().into_iter().collect();
+    names.sort();
+    names
+}
+
+#[actix_rt::test]
+async fn test_default_roles_are_seeded_and_protected() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    assign_admin_role(&app_state, &admin.id);
+    let admin_token = access_token(&app_state, &admin.id);
+    let user_token = access_token(&app_state, &user.id);
+    let app = role_app!(app_state);
+    
+    // The admin role grants `admin`, which is what lets this caller in
+    let req = test::TestRequest::get().uri("/api/roles").insert_header(bearer(&user_token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    let req = test::TestRequest::get().uri("/api/roles").insert_header(bearer(&admin_token)).to_request();
+    let roles: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(roles[0]["name"], "admin");
+    assert_eq!(roles[0]["permissions"], json!(["admin"]));
+    assert_eq!(roles[1]["name"], "user");
+    assert_eq!(roles[1]["permissions"], json!(["user"]));
+    let admin_role = roles[0]["id"].as_str().unwrap();
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/roles/{admin_role}"))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+    
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/roles/{admin_role}"))
+        .insert_header(bearer(&admin_token))
+        .set_json(json!({ "permissions": ["user"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+}
+
+#[actix_rt::test]
+async fn test_permissions_resolve_through_roles_and_groups() {
+    let app_state = create_test_app_state();
+    insert_permission(&app_state, "reports.read");
+    insert_permission(&app_state, "reports.write");
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "analyst@example.com", "password123");
+    assign_admin_role(&app_state, &admin.id);
+    let token = access_token(&app_state, &admin.id);
+    let app = role_app!(app_state.clone());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/roles")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "analyst", "permissions": ["reports.read", "missing"] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/roles")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "analyst", "permissions": ["reports.read"] }))
+        .to_request();
+    let analyst: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/api/roles")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "editor", "permissions": ["reports.read", "reports.write"] }))
+        .to_request();
+    let editor: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/groups")
+        .insert_header(bearer(&token))
+        .set_json(json!({ "name": "finance", "roles": ["analyst"] }))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let group: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(group["roles"], json!(["analyst"]));
+    let members_uri = format!("/api/groups/{}/members", group["id"].as_str().unwrap());
+    
+    // Through group membership
+    let req = test::TestRequest::put()
+        .uri(&format!("{members_uri}/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    assert_eq!(permissions(&app_state, &user.id), vec!["reports.read"]);
+    let req = test::TestRequest::get().uri(&members_uri).insert_header(bearer(&token)).to_request();
+    let members: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(members[0]["id"], user.id);
+    
+    // Plus a directly assigned role
+    let role_uri = format!("/api/users/{}/roles/{}", user.id, editor["id"].as_str().unwrap());
+    let req = test::TestRequest::put().uri(&role_uri).insert_header(bearer(&token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    assert_eq!(permissions(&app_state, &user.id), vec!["reports.read", "reports.write"]);
+    
+    let req = test::TestRequest::delete().uri(&role_uri).insert_header(bearer(&token)).to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let req = test::TestRequest::delete()
+        .uri(&format!("{members_uri}/{}", user.id))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    assert!(permissions(&app_state, &user.id).is_empty());
+    
+    // Deleting a role takes it away from everyone who had it
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/users/{}/roles/{}", user.id, analyst["id"].as_str().unwrap()))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/roles/{}", analyst["id"].as_str().unwrap()))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    assert!(permissions(&app_state, &user.id).is_empty());
+}
@@ -0,0 +1,201 @@
+use actix_web::{test, App, web};
+use surjo_backend::handlers::auth::{login, logout};
+use surjo_backend::handlers::sessions::{
+    list_my_sessions, list_user_sessions, revoke_all_my_sessions, revoke_my_session, revoke_user_session,
+};
+use surjo_backend::models::LoginRequest;
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+#[actix_rt::test]
+async fn test_list_my_sessions() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "sessions@example.com", "password123");
+    let other_token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(list_my_sessions)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .insert_header(("User-Agent", "surjo-test/1.0"))
+        .set_json(&LoginRequest {
+            email: "sessions@example.com".to_string(),
+            password: "password123".to_string(),
+        })
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let token = body["token"].as_str().unwrap().to_string();
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&token))
+        .to_request();
+    let sessions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 2);
+    
+    let current: Vec<_> = sessions.iter().filter(|s| s["current"] == true).collect();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0]["user_agent"], "surjo-test/1.0");
+    assert!(current[0]["last_seen_at"].is_string());
+    
+    // The other session sees itself as current instead
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&other_token))
+        .to_request();
+    let sessions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let current = sessions.as_array().unwrap().iter().find(|s| s["current"] == true).unwrap();
+    assert!(current["user_agent"].is_null());
+}
+
+#[actix_rt::test]
+async fn test_logout_ends_session() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "logout@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(logout)
+            .service(list_my_sessions)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/logout")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_revoke_my_session() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "revoke@example.com", "password123");
+    let stranger = insert_user(&app_state, "stranger@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let second_token = access_token(&app_state, &user.id);
+    let stranger_token = access_token(&app_state, &stranger.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(list_my_sessions)
+            .service(revoke_my_session)
+    ).await;
+
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&second_token))
+        .to_request();
+    let sessions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let second_id = sessions.as_array().unwrap().iter()
+        .find(|s| s["current"] == true).unwrap()["id"].as_str().unwrap().to_string();
+    
+    // Nobody else can revoke it
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/sessions/{second_id}"))
+        .insert_header(bearer(&stranger_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 404);
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/me/sessions/{second_id}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&second_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_revoke_all_my_sessions() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "everywhere@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    let second_token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(list_my_sessions)
+            .service(revoke_all_my_sessions)
+    ).await;
+
+    let req = test::TestRequest::delete()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    for token in [&token, &second_token] {
+        let req = test::TestRequest::get()
+            .uri("/api/me/sessions")
+            .insert_header(bearer(token))
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), 401);
+    }
+}
+
+#[actix_rt::test]
+async fn test_admin_manages_user_sessions() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    let user = insert_user(&app_state, "user@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let admin_token = access_token(&app_state, &admin.id);
+    let user_token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(list_my_sessions)
+            .service(list_user_sessions)
+            .service(revoke_user_session)
+    ).await;
+
+    // Regular users cannot look at other accounts
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}/sessions", admin.id))
+        .insert_header(bearer(&user_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users/{}/sessions", user.id))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    let sessions: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let sessions = sessions.as_array().unwrap();
+    assert_eq!(sessions.len(), 1);
+    let session_id = sessions[0]["id"].as_str().unwrap();
+    
+    let req = test::TestRequest::delete()
+        .uri(&format!("/api/users/{}/sessions/{session_id}", user.id))
+        .insert_header(bearer(&admin_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(&user_token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
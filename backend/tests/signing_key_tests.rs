@@ -0,0 +1,156 @@
+use actix_web::{test, App, web};
+use chrono::{Duration, Utc};
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use serde_json::json;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::jwks::jwks;
+use surjo_backend::handlers::sessions::list_my_sessions;
+use surjo_backend::models::{AppState, Claims, KeyAlgorithm, SigningKey, JWKS_MAX_AGE_SECONDS, RETIRED_KEY_GRACE_MINUTES};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, insert_user};
+
+macro_rules! signing_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(login)
+                .service(jwks)
+                .service(list_my_sessions)
+        )
+        .await
+    };
+}
+
+fn generate_key(state: &AppState, algorithm: KeyAlgorithm) -> SigningKey {
+    let database = state.database.lock().unwrap();
+    SigningKey::generate(database.get_connection(), algorithm).expect("Failed to generate signing key")
+}
+
+// Moves a key's creation back as if verifiers had had time to fetch it
+fn wait_out_publication(state: &AppState, kid: &str) {
+    let database = state.database.lock().unwrap();
+    let created_at = Utc::now() - Duration::seconds(JWKS_MAX_AGE_SECONDS + 1);
+    database
+        .get_connection()
+        .execute("UPDATE signing_keys SET created_at = ?1 WHERE kid = ?2", [created_at.to_rfc3339(), kid.to_string()])
+        .unwrap();
+}
+
+async fn login_token<S>(app: &S, email: &str) -> String
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(json!({ "email": email, "password": "password123" }))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(app, req).await;
+    body["token"].as_str().unwrap().to_string()
+}
+
+async fn status_with_token<S>(app: &S, token: &str) -> u16
+where
+    S: actix_web::dev::Service<actix_http::Request, Response = actix_web::dev::ServiceResponse, Error = actix_web::Error>,
+{
+    let req = test::TestRequest::get()
+        .uri("/api/me/sessions")
+        .insert_header(bearer(token))
+        .to_request();
+    test::call_service(app, req).await.status().as_u16()
+}
+
+#[actix_rt::test]
+async fn test_tokens_verify_against_published_keys() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "keys@example.com", "password123");
+    let key = generate_key(&app_state, KeyAlgorithm::EdDsa);
+    let app = signing_app!(app_state);
+    
+    let token = login_token(&app, "keys@example.com").await;
+    let header = decode_header(&token).unwrap();
+    assert_eq!(header.alg, Algorithm::EdDSA);
+    assert_eq!(header.kid.as_deref(), Some(key.kid.as_str()));
+    
+    // A third party only needs the JWKS document to check the token
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let set: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(set.keys.len(), 1);
+    let jwk = set.find(&key.kid).expect("Key should be published");
+    let claims = decode::<Claims>(&token, &DecodingKey::from_jwk(jwk).unwrap(), &Validation::new(Algorithm::EdDSA))
+        .expect("Token should verify")
+        .claims;
+    assert_eq!(claims.sub, user.id);
+    
+    assert_eq!(status_with_token(&app, &token).await, 200);
+}
+
+#[actix_rt::test]
+async fn test_rotation_keeps_existing_tokens_valid() {
+    let app_state = create_test_app_state();
+    insert_user(&app_state, "rotate@example.com", "password123");
+    let old_key = generate_key(&app_state, KeyAlgorithm::EdDsa);
+    wait_out_publication(&app_state, &old_key.kid);
+    let app = signing_app!(app_state.clone());
+    
+    let old_token = login_token(&app, "rotate@example.com").await;
+    
+    let new_key = generate_key(&app_state, KeyAlgorithm::Rs256);
+    {
+        let database = app_state.database.lock().unwrap();
+        assert_eq!(SigningKey::retire_all_except(database.get_connection(), &new_key.kid).unwrap(), 1);
+    }
+    
+    // The new key is published at once but only signs once cached key sets have
+    // had time to expire
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let set: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert!(set.find(&new_key.kid).is_some());
+    let token = login_token(&app, "rotate@example.com").await;
+    assert_eq!(decode_header(&token).unwrap().kid.as_deref(), Some(old_key.kid.as_str()));
+    
+    wait_out_publication(&app_state, &new_key.kid);
+    let new_token = login_token(&app, "rotate@example.com").await;
+    let header = decode_header(&new_token).unwrap();
+    assert_eq!(header.alg, Algorithm::RS256);
+    assert_eq!(header.kid.as_deref(), Some(new_key.kid.as_str()));
+    
+    // Both keys are published while tokens from the old one can still be live
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let set: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert!(set.find(&old_key.kid).is_some());
+    assert!(set.find(&new_key.kid).is_some());
+    assert_eq!(status_with_token(&app, &old_token).await, 200);
+    assert_eq!(status_with_token(&app, &new_token).await, 200);
+    
+    // Once the grace period has passed the old key stops verifying
+    {
+        let database = app_state.database.lock().unwrap();
+        let retired_at = Utc::now() - Duration::minutes(RETIRED_KEY_GRACE_MINUTES + 1);
+        database
+            .get_connection()
+            .execute("UPDATE signing_keys SET retired_at = ?1 WHERE kid = ?2", [retired_at.to_rfc3339(), old_key.kid.clone()])
+            .unwrap();
+    }
+    assert_eq!(status_with_token(&app, &old_token).await, 401);
+    assert_eq!(status_with_token(&app, &new_token).await, 200);
+}
+
+#[actix_rt::test]
+async fn test_shared_secret_tokens_rejected_once_keys_exist() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "secret@example.com", "password123");
+    let hs256_token = access_token(&app_state, &user.id);
+    let app = signing_app!(app_state.clone());
+    
+    // Without keys the shared secret still signs and verifies
+    assert_eq!(status_with_token(&app, &hs256_token).await, 200);
+    let req = test::TestRequest::get().uri("/.well-known/jwks.json").to_request();
+    let set: JwkSet = test::call_and_read_body_json(&app, req).await;
+    assert!(set.keys.is_empty());
+    
+    generate_key(&app_state, KeyAlgorithm::EdDsa);
+    assert_eq!(status_with_token(&app, &hs256_token).await, 401);
+}
@@ -0,0 +1,404 @@
+use actix_web::{test, App, web};
+use data_encoding::BASE32_NOPAD;
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::two_factor::{
+    confirm_two_factor, disable_two_factor, regenerate_recovery_codes, setup_two_factor, verify_two_factor,
+};
+use surjo_backend::handlers::users::list_users;
+use surjo_backend::models::{
+    current_totp_step, totp_code, AppState, LoginRequest, TwoFactor, TwoFactorCodeRequest, TwoFactorVerifyRequest,
+};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, grant_permission, insert_user};
+
+fn credentials(email: &str) -> LoginRequest {
+    LoginRequest {
+        email: email.to_string(),
+        password: "password123".to_string(),
+    }
+}
+
+fn code_request(code: &str) -> TwoFactorCodeRequest {
+    TwoFactorCodeRequest { code: code.to_string() }
+}
+
+fn current_code(secret: &str) -> String {
+    let secret = BASE32_NOPAD.decode(secret.as_bytes()).expect("Secret should be base32");
+    format!("{:06}", totp_code(&secret, current_totp_step()))
+}
+
+fn wrong_code(secret: &str) -> String {
+    let code: u32 = current_code(secret).parse().unwrap();
+    format!("{:06}", (code + 1) % 1_000_000)
+}
+
+// Turns 2FA on directly and returns the secret and recovery codes
+fn enable_two_factor(state: &AppState, user_id: &str) -> (String, Vec<String>) {
+    let database = state.database.lock().unwrap();
+    let conn = database.get_connection();
+    let secret = TwoFactor::begin_enrollment(conn, user_id).expect("Failed to start enrollment");
+    assert!(TwoFactor::confirm(conn, user_id, &current_code(&secret)).unwrap());
+    allow_code_reuse(conn, user_id);
+    let recovery_codes = TwoFactor::generate_recovery_codes(conn, user_id).expect("Failed to create recovery codes");
+    (secret, recovery_codes)
+}
+
+// Forgets which step was last used so a test can present the current code again
+fn allow_code_reuse(conn: &rusqlite::Connection, user_id: &str) {
+    conn.execute("UPDATE user_totp SET last_used_step = NULL WHERE user_id = ?1", [user_id])
+        .expect("Failed to reset last used step");
+}
+
+#[actix_rt::test]
+async fn test_totp_matches_rfc_6238_vector() {
+    // SHA1 test vector from RFC 6238 Appendix B: T = 59s gives 94287082
+    assert_eq!(totp_code(b"12345678901234567890", 59 / 30), 287082);
+}
+
+#[actix_rt::test]
+async fn test_enrollment_and_two_step_login() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "totp@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(login)
+            .service(verify_two_factor)
+            .service(setup_two_factor)
+            .service(confirm_two_factor)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/setup")
+        .insert_header(bearer(&token))
+        .to_request();
+    let setup: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let secret = setup["secret"].as_str().unwrap().to_string();
+    let uri = setup["otpauth_uri"].as_str().unwrap();
+    assert!(uri.starts_with("otpauth://totp/Surjo%3Atotp%40example%2Ecom?"));
+    assert!(uri.contains(&format!("secret={secret}")));
+    
+    // Until confirmed, 2FA is not enforced
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("totp@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/confirm")
+        .insert_header(bearer(&token))
+        .set_json(code_request(&wrong_code(&secret)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/confirm")
+        .insert_header(bearer(&token))
+        .set_json(code_request(&current_code(&secret)))
+        .to_request();
+    let confirmed: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(confirmed["recovery_codes"].as_array().unwrap().len(), 10);
+    
+    // Setup cannot be restarted over an enabled secret
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/setup")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("totp@example.com"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 202);
+    let challenge: serde_json::Value = test::read_body_json(resp).await;
+    assert!(challenge.get("token").is_none());
+    assert_eq!(challenge["expires_in"], 300);
+    
+    {
+        let database = app_state.database.lock().unwrap();
+        allow_code_reuse(database.get_connection(), &user.id);
+    }
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: challenge["challenge_token"].as_str().unwrap().to_string(),
+            code: Some(current_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 200);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert!(body["token"].is_string());
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(body["user"]["id"], user.id.as_str());
+}
+
+#[actix_rt::test]
+async fn test_codes_and_challenges_are_single_use() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "replay@example.com", "password123");
+    let (secret, _) = enable_two_factor(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(verify_two_factor)
+    ).await;
+    
+    let mut challenges = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(credentials("replay@example.com"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        challenges.push(body["challenge_token"].as_str().unwrap().to_string());
+    }
+    
+    // A newer login replaces the earlier challenge
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: challenges[0].clone(),
+            code: Some(current_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    // A wrong code spends the challenge
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: challenges[1].clone(),
+            code: Some(wrong_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: challenges[1].clone(),
+            code: Some(current_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    // A code that already signed in cannot be replayed with a fresh challenge
+    let mut statuses = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(credentials("replay@example.com"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/auth/2fa/verify")
+            .set_json(TwoFactorVerifyRequest {
+                challenge_token: body["challenge_token"].as_str().unwrap().to_string(),
+                code: Some(current_code(&secret)),
+                recovery_code: None,
+            })
+            .to_request();
+        statuses.push(test::call_service(&app, req).await.status().as_u16());
+    }
+    assert_eq!(statuses, vec![200, 401]);
+}
+
+// A stolen password must not reset the counter between guesses at the second factor
+#[actix_rt::test]
+async fn test_wrong_codes_count_towards_lockout() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.login_throttle.account.max_failures = 2;
+    app_state.policy.login_throttle.account.progressive_delay = false;
+    let user = insert_user(&app_state, "guessed@example.com", "password123");
+    let (secret, _) = enable_two_factor(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(verify_two_factor)
+    ).await;
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("guessed@example.com"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: body["challenge_token"].as_str().unwrap().to_string(),
+            code: Some(wrong_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    // The right password alone leaves the failure counted
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("guessed@example.com"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let challenge_token = body["challenge_token"].as_str().unwrap().to_string();
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(LoginRequest {
+            email: "guessed@example.com".to_string(),
+            password: "guess".to_string(),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+    
+    // Even the right code is refused once the account is locked
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token,
+            code: Some(current_code(&secret)),
+            recovery_code: None,
+        })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 423);
+    assert!(resp.headers().contains_key("Retry-After"));
+}
+
+#[actix_rt::test]
+async fn test_recovery_codes() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "recovery@example.com", "password123");
+    let (secret, recovery_codes) = enable_two_factor(&app_state, &user.id);
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(verify_two_factor)
+            .service(regenerate_recovery_codes)
+    ).await;
+    
+    let mut statuses = Vec::new();
+    for _ in 0..2 {
+        let req = test::TestRequest::post()
+            .uri("/api/auth/login")
+            .set_json(credentials("recovery@example.com"))
+            .to_request();
+        let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        let req = test::TestRequest::post()
+            .uri("/api/auth/2fa/verify")
+            .set_json(TwoFactorVerifyRequest {
+                challenge_token: body["challenge_token"].as_str().unwrap().to_string(),
+                code: None,
+                recovery_code: Some(recovery_codes[0].to_uppercase()),
+            })
+            .to_request();
+        statuses.push(test::call_service(&app, req).await.status().as_u16());
+    }
+    assert_eq!(statuses, vec![200, 401]);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/me/2fa/recovery-codes")
+        .insert_header(bearer(&token))
+        .set_json(code_request(&current_code(&secret)))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let new_codes = body["recovery_codes"].as_array().unwrap();
+    assert_eq!(new_codes.len(), 10);
+    
+    // Regenerating invalidates the old set
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("recovery@example.com"))
+        .to_request();
+    let body: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    let req = test::TestRequest::post()
+        .uri("/api/auth/2fa/verify")
+        .set_json(TwoFactorVerifyRequest {
+            challenge_token: body["challenge_token"].as_str().unwrap().to_string(),
+            code: None,
+            recovery_code: Some(recovery_codes[1].clone()),
+        })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 401);
+}
+
+#[actix_rt::test]
+async fn test_disable_requires_current_code() {
+    let app_state = create_test_app_state();
+    let user = insert_user(&app_state, "disable@example.com", "password123");
+    let (secret, _) = enable_two_factor(&app_state, &user.id);
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(login)
+            .service(disable_two_factor)
+    ).await;
+    
+    let req = test::TestRequest::delete()
+        .uri("/api/me/2fa")
+        .insert_header(bearer(&token))
+        .set_json(code_request(&wrong_code(&secret)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    
+    let req = test::TestRequest::delete()
+        .uri("/api/me/2fa")
+        .insert_header(bearer(&token))
+        .set_json(code_request(&current_code(&secret)))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 204);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("disable@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 200);
+}
+
+#[actix_rt::test]
+async fn test_policy_requires_two_factor_for_admins() {
+    let mut app_state = create_test_app_state();
+    app_state.policy.require_admin_2fa = true;
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let token = access_token(&app_state, &admin.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state.clone()))
+            .service(list_users)
+    ).await;
+    
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&token))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 403);
+    let body: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(body, "Two-factor authentication is required for admin access");
+    
+    enable_two_factor(&app_state, &admin.id);
+    let req = test::TestRequest::get()
+        .uri("/api/users")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
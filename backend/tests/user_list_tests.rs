@@ -0,0 +1,113 @@
+use actix_web::{test, App, web};
+use surjo_backend::handlers::users::list_users;
+use surjo_backend::models::AppState;
+
+mod common;
+use common::{access_token, bearer, create_test_app_state, deactivate_user, grant_permission, insert_user};
+
+macro_rules! user_list_app {
+    ($app_state:expr) => {
+        test::init_service(
+            App::new()
+                .app_data(web::Data::new($app_state))
+                .service(list_users)
+        )
+        .await
+    };
+}
+
+fn set_created_at(state: &AppState, user_id: &str, created_at: &str) {
+    let database = state.database.lock().unwrap();
+    database
+        .get_connection()
+        .execute("UPDATE users SET created_at = ?1 WHERE id = ?2", [created_at, user_id])
+        .expect("Failed to update user");
+}
+
+fn emails(page: &serde_json::Value) -> Vec<&str> {
+    page["users"].as_array().unwrap().iter().map(|user| user["email"].as_str().unwrap()).collect()
+}
+
+#[actix_rt::test]
+async fn test_list_users_pages_with_a_cursor() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    for email in ["carol@example.com", "alice@example.com", "dave@example.com", "bob@example.com"] {
+        insert_user(&app_state, email, "password123");
+    }
+    let token = access_token(&app_state, &admin.id);
+    let app = user_list_app!(app_state);
+    
+    let mut seen = Vec::new();
+    let mut uri = "/api/users?sort=email&order=asc&limit=2&include_total=true".to_string();
+    loop {
+        let req = test::TestRequest::get().uri(&uri).insert_header(bearer(&token)).to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(page["total"], 5);
+        seen.extend(emails(&page).into_iter().map(str::to_string));
+        match page["next_cursor"].as_str() {
+            Some(cursor) => uri = format!("/api/users?sort=email&order=asc&limit=2&include_total=true&cursor={cursor}"),
+            None => break,
+        }
+    }
+    assert_eq!(
+        seen,
+        vec!["admin@example.com", "alice@example.com", "bob@example.com", "carol@example.com", "dave@example.com"]
+    );
+    
+    // The total is only counted on request
+    let req = test::TestRequest::get().uri("/api/users?limit=1").insert_header(bearer(&token)).to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert!(page["total"].is_null());
+    let cursor = page["next_cursor"].as_str().unwrap();
+    
+    // A cursor only continues the ordering it was issued for
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/users?sort=email&cursor={cursor}"))
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let req = test::TestRequest::get()
+        .uri("/api/users?cursor=not-a-cursor")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+    let req = test::TestRequest::get()
+        .uri("/api/users?sort=password_hash")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_rt::test]
+async fn test_list_users_filters() {
+    let app_state = create_test_app_state();
+    let admin = insert_user(&app_state, "admin@example.com", "password123");
+    grant_permission(&app_state, &admin.id, "admin");
+    let old = insert_user(&app_state, "old_timer@example.com", "password123");
+    let disabled = insert_user(&app_state, "disabled@example.org", "password123");
+    insert_user(&app_state, "oldtimer@example.com", "password123");
+    set_created_at(&app_state, &old.id, "2020-01-01T00:00:00+00:00");
+    deactivate_user(&app_state, &disabled.id);
+    let token = access_token(&app_state, &admin.id);
+    let app = user_list_app!(app_state);
+    
+    let cases = [
+        ("is_active=false", vec!["disabled@example.org"]),
+        ("email=EXAMPLE.ORG", vec!["disabled@example.org"]),
+        // `_` is matched literally, not as a wildcard
+        ("email=old_", vec!["old_timer@example.com"]),
+        ("created_before=2021-01-01T00:00:00Z", vec!["old_timer@example.com"]),
+        ("created_after=2021-01-01T00:00:00Z&email=old", vec!["oldtimer@example.com"]),
+        ("permission=admin", vec!["admin@example.com"]),
+    ];
+    for (filter, expected) in cases {
+        let req = test::TestRequest::get()
+            .uri(&format!("/api/users?sort=email&order=asc&{filter}"))
+            .insert_header(bearer(&token))
+            .to_request();
+        let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+        assert_eq!(emails(&page), expected, "{filter}");
+    }
+}
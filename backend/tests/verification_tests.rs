@@ -0,0 +1,184 @@
+use actix_web::{test, App, web};
+use surjo_backend::handlers::auth::login;
+use surjo_backend::handlers::users::create_user;
+use surjo_backend::handlers::verification::{resend_verification_email, verify_email};
+use surjo_backend::models::{CreateUserRequest, LoginRequest, VerifyEmailRequest};
+
+mod common;
+use common::{access_token, bearer, create_test_app_state_with_mailer, insert_user, token_from_email};
+
+fn signup(email: &str) -> CreateUserRequest {
+    CreateUserRequest {
+        email: email.to_string(),
+        password: "password123".to_string(),
+        first_name: None,
+        last_name: None,
+    }
+}
+
+fn credentials(email: &str) -> LoginRequest {
+    LoginRequest {
+        email: email.to_string(),
+        password: "password123".to_string(),
+    }
+}
+
+#[actix_rt::test]
+async fn test_signup_sends_verification_email() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(create_user)
+            .service(verify_email)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/users")
+        .set_json(signup("verify@example.com"))
+        .to_request();
+    let created: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(created["email_verified"], false);
+    assert!(created["email_verified_at"].is_null());
+    
+    let messages = mailer.messages();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0].to, "verify@example.com");
+    let token = token_from_email(&messages[0].text_body);
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email")
+        .set_json(&VerifyEmailRequest { token: token.clone() })
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert!(resp.status().is_success());
+    let verified: serde_json::Value = test::read_body_json(resp).await;
+    assert_eq!(verified["id"], created["id"]);
+    assert_eq!(verified["email_verified"], true);
+    assert!(verified["email_verified_at"].is_string());
+    
+    // Verification tokens are single-use
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email")
+        .set_json(&VerifyEmailRequest { token })
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 400);
+}
+
+#[actix_rt::test]
+async fn test_login_policy_requires_verified_email() {
+    let (mut app_state, mailer) = create_test_app_state_with_mailer();
+    app_state.policy.require_email_verification = true;
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(create_user)
+            .service(login)
+            .service(verify_email)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/users")
+        .set_json(signup("policy@example.com"))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("policy@example.com"))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 403);
+    
+    let token = token_from_email(&mailer.messages()[0].text_body);
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email")
+        .set_json(&VerifyEmailRequest { token })
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/login")
+        .set_json(credentials("policy@example.com"))
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+}
+
+#[actix_rt::test]
+async fn test_resend_verification_email() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    let user = insert_user(&app_state, "resend@example.com", "password123");
+    let token = access_token(&app_state, &user.id);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(web::Data::new(app_state))
+            .service(resend_verification_email)
+            .service(verify_email)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email/resend")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    
+    let verification_token = token_from_email(&mailer.messages()[0].text_body);
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email")
+        .set_json(&VerifyEmailRequest { token: verification_token })
+        .to_request();
+    assert!(test::call_service(&app, req).await.status().is_success());
+    
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email/resend")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 409);
+}
+
+#[actix_rt::test]
+async fn test_signup_succeeds_when_verification_token_fails() {
+    let (app_state, mailer) = create_test_app_state_with_mailer();
+    {
+        let database = app_state.database.lock().unwrap();
+        database
+            .get_connection()
+            .execute_batch(
+                "CREATE TRIGGER refuse_tokens BEFORE INSERT ON one_time_tokens
+                 BEGIN SELECT RAISE(ABORT, 'refused'); END",
+            )
+            .unwrap();
+    }
+    let app_state = web::Data::new(app_state);
+    
+    let app = test::init_service(
+        App::new()
+            .app_data(app_state.clone())
+            .service(create_user)
+            .service(resend_verification_email)
+    ).await;
+
+    let req = test::TestRequest::post()
+        .uri("/api/users")
+        .set_json(signup("stranded@example.com"))
+        .to_request();
+    let resp = test::call_service(&app, req).await;
+    assert_eq!(resp.status(), 201);
+    let created: serde_json::Value = test::read_body_json(resp).await;
+    assert!(mailer.messages().is_empty());
+    
+    // Once tokens can be issued again the user asks for a fresh mail
+    {
+        let database = app_state.database.lock().unwrap();
+        database.get_connection().execute_batch("DROP TRIGGER refuse_tokens").unwrap();
+    }
+    let token = access_token(&app_state, created["id"].as_str().unwrap());
+    let req = test::TestRequest::post()
+        .uri("/api/auth/verify-email/resend")
+        .insert_header(bearer(&token))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), 202);
+    assert_eq!(mailer.messages()[0].to, "stranded@example.com");
+}